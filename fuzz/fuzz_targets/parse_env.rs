@@ -0,0 +1,15 @@
+#![no_main]
+
+use abundantis::source::{BytesSource, EnvSource};
+use libfuzzer_sys::fuzz_target;
+
+// Arbitrary bytes must parse without panicking, and every variable that comes
+// out must have a key free of NUL bytes.
+fuzz_target!(|data: &[u8]| {
+    let source = BytesSource::new("fuzz", data);
+    let snapshot = source.load().expect("in-memory load never fails");
+    for variable in snapshot.variables.iter() {
+        assert!(!variable.key.contains('\0'));
+    }
+    let _ = source.diagnostics();
+});
@@ -0,0 +1,59 @@
+//! Synchronous entry points for async builds.
+//!
+//! Lets otherwise-sync programs use async sources (e.g. a remote provider)
+//! by driving them on a lazily-created current-thread runtime. These helpers
+//! must not be called from inside a tokio runtime; doing so returns
+//! [`AbundantisError::Runtime`] instead of panicking.
+
+use crate::error::{AbundantisError, Result};
+use crate::{Abundantis, RefreshOptions, RefreshReport, ResolvedVariable};
+use std::future::Future;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+fn runtime() -> Result<&'static tokio::runtime::Runtime> {
+    static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime);
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| AbundantisError::Runtime(format!("Failed to start runtime: {}", e)))?;
+    Ok(RUNTIME.get_or_init(|| runtime))
+}
+
+/// Runs `future` to completion on the shared blocking runtime.
+pub fn block_on<F: Future>(future: F) -> Result<F::Output> {
+    if tokio::runtime::Handle::try_current().is_ok() {
+        return Err(AbundantisError::Runtime(
+            "Blocking calls cannot be made from within an async runtime; use the async API instead"
+                .into(),
+        ));
+    }
+
+    Ok(runtime()?.block_on(future))
+}
+
+impl Abundantis {
+    /// Blocking form of [`Abundantis::get_for_file`].
+    pub fn get_for_file_blocking(
+        &self,
+        key: &str,
+        file_path: &Path,
+    ) -> Result<Option<Arc<ResolvedVariable>>> {
+        block_on(self.get_for_file(key, file_path))?
+    }
+
+    /// Blocking form of [`Abundantis::all_for_file`].
+    pub fn all_for_file_blocking(&self, file_path: &Path) -> Result<Vec<Arc<ResolvedVariable>>> {
+        block_on(self.all_for_file(file_path))?
+    }
+
+    /// Blocking form of [`Abundantis::refresh`].
+    pub fn refresh_blocking(&self, options: RefreshOptions) -> Result<RefreshReport> {
+        block_on(self.refresh(options))?
+    }
+}
@@ -2,7 +2,7 @@ use compact_str::CompactString;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct AbundantisConfig {
     pub workspace: WorkspaceConfig,
@@ -10,9 +10,95 @@ pub struct AbundantisConfig {
     pub interpolation: InterpolationConfig,
     pub cache: CacheConfig,
     pub sources: SourcesConfig,
+    pub selection: SelectionConfig,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl AbundantisConfig {
+    /// Serializes the config as TOML, e.g. to persist edits made at runtime.
+    pub fn to_toml(&self) -> crate::Result<String> {
+        toml::to_string(self).map_err(|e| crate::AbundantisError::Config {
+            message: format!("Failed to serialize config: {}", e),
+            path: None,
+        })
+    }
+
+    /// Env file patterns discovered as sources: `workspace.env_files` plus
+    /// the configured environment's files.
+    pub(crate) fn env_file_patterns(&self) -> Vec<CompactString> {
+        let mut patterns = self.workspace.env_files.clone();
+        for name in self.selection.environment_files() {
+            if !patterns.contains(&name) {
+                patterns.push(name);
+            }
+        }
+        patterns
+    }
+
+    /// `resolution` with the configured environment's files ranked in
+    /// template order, after any explicitly ordered files.
+    pub(crate) fn effective_resolution(&self) -> ResolutionConfig {
+        let mut resolution = self.resolution.clone();
+        for name in self.selection.environment_files() {
+            if !resolution.files.order.contains(&name) {
+                resolution.files.order.push(name);
+            }
+        }
+        resolution
+    }
+
+    /// Parses a config previously written by [`AbundantisConfig::to_toml`] or
+    /// by hand. Missing sections fall back to their defaults.
+    pub fn from_toml(content: &str) -> crate::Result<Self> {
+        toml::from_str(content).map_err(|e| crate::AbundantisError::Config {
+            message: format!("Failed to parse config: {}", e),
+            path: None,
+        })
+    }
+}
+
+/// Controls which env files are activated when none are chosen explicitly.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SelectionConfig {
+    /// Environment name (e.g. `production`). When set, `.env`, `.env.local`,
+    /// `.env.<environment>` and `.env.<environment>.local` are activated, in
+    /// that order of increasing precedence.
+    pub environment: Option<CompactString>,
+    /// Let wildcards in active file patterns match a leading `.`, so `*`
+    /// also picks up `.env`. Off by default, matching shell globbing.
+    pub match_dotfiles: bool,
+    /// Files tried in order when no environment is set and neither `.env`
+    /// nor `.env.local` exists; the first existing one is active.
+    pub auto_priority: Vec<CompactString>,
+}
+
+impl Default for SelectionConfig {
+    fn default() -> Self {
+        Self {
+            environment: None,
+            match_dotfiles: false,
+            auto_priority: default_auto_priority(),
+        }
+    }
+}
+
+impl SelectionConfig {
+    /// File names activated for the configured environment, lowest precedence
+    /// first. Empty when no environment is set.
+    pub fn environment_files(&self) -> Vec<CompactString> {
+        match &self.environment {
+            Some(env) => vec![
+                ".env".into(),
+                ".env.local".into(),
+                compact_str::format_compact!(".env.{}", env),
+                compact_str::format_compact!(".env.{}.local", env),
+            ],
+            None => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct WorkspaceConfig {
     pub root: Option<PathBuf>,
@@ -40,6 +126,26 @@ impl Default for WorkspaceConfig {
     }
 }
 
+impl WorkspaceConfig {
+    /// Compiles `ignores` into a single matcher. Invalid patterns are skipped
+    /// with a warning.
+    pub fn ignore_set(&self) -> globset::GlobSet {
+        let mut builder = globset::GlobSetBuilder::new();
+        for pattern in &self.ignores {
+            match globset::Glob::new(pattern) {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(e) => tracing::warn!("Invalid ignore pattern {}: {}", pattern, e),
+            }
+        }
+        builder.build().unwrap_or_else(|e| {
+            tracing::warn!("Failed to build ignore set: {}", e);
+            globset::GlobSet::empty()
+        })
+    }
+}
+
 fn default_env_files() -> Vec<CompactString> {
     vec![
         ".env".into(),
@@ -49,6 +155,19 @@ fn default_env_files() -> Vec<CompactString> {
     ]
 }
 
+fn default_auto_priority() -> Vec<CompactString> {
+    vec![
+        ".env.local".into(),
+        ".env.development".into(),
+        ".env.dev".into(),
+        ".env".into(),
+        ".env.test".into(),
+        ".env.staging".into(),
+        ".env.production".into(),
+        ".env.prod".into(),
+    ]
+}
+
 fn default_ignores() -> Vec<CompactString> {
     vec![
         "**/node_modules/**".into(),
@@ -70,17 +189,33 @@ pub enum MonorepoProviderType {
     Yarn,
     Cargo,
     Custom,
+    /// Each entry of `roots` is a package directory, taken as is.
+    Directories,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ResolutionConfig {
+    /// Source types to consult, highest precedence first: a key defined by
+    /// several types resolves to the first type's value.
     #[serde(default = "default_precedence")]
     pub precedence: Vec<SourcePrecedence>,
     #[serde(default)]
     pub files: FileResolutionConfig,
     #[serde(default = "default_true")]
     pub type_check: bool,
+    #[serde(default)]
+    pub interpolation_scope: InterpolationScope,
+    /// Record how long each key takes to resolve; see
+    /// `Abundantis::last_profile`.
+    #[serde(default)]
+    pub profile: bool,
+    #[serde(default)]
+    pub on_undefined: OnUndefined,
+    /// How to combine a key defined by several sources, per key. Keys not
+    /// listed take the highest-precedence value.
+    #[serde(default)]
+    pub merge: std::collections::HashMap<CompactString, MergeStrategy>,
 }
 
 impl Default for ResolutionConfig {
@@ -89,6 +224,10 @@ impl Default for ResolutionConfig {
             precedence: default_precedence(),
             files: FileResolutionConfig::default(),
             type_check: true,
+            interpolation_scope: InterpolationScope::default(),
+            profile: false,
+            on_undefined: OnUndefined::default(),
+            merge: std::collections::HashMap::new(),
         }
     }
 }
@@ -113,7 +252,7 @@ fn default_precedence() -> Vec<SourcePrecedence> {
     vec![SourcePrecedence::Shell, SourcePrecedence::File]
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum SourcePrecedence {
     Shell,
@@ -121,7 +260,49 @@ pub enum SourcePrecedence {
     Remote,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Which variables a file-defined value may reference during interpolation.
+///
+/// `Package` and `File` only consider env files: the package's own files
+/// (or, for a file outside the current package, the files in its directory)
+/// and the defining file respectively. References that fall outside the
+/// scope are left unresolved.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InterpolationScope {
+    #[default]
+    Global,
+    Package,
+    File,
+}
+
+/// What interpolation does with a plain `${KEY}` or `$KEY` reference to a
+/// variable no source defines. References with a fallback such as
+/// `${KEY:-default}` are left to the fallback.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnUndefined {
+    /// Fail resolution with `UndefinedVariable`.
+    Error,
+    /// Substitute an empty string.
+    Empty,
+    /// Leave the reference in the value as written.
+    #[default]
+    Keep,
+}
+
+/// How the definitions of one key across sources combine into its value.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum MergeStrategy {
+    /// The highest-precedence definition wins.
+    #[default]
+    Override,
+    /// Every definition is kept, joined by `separator` from highest to
+    /// lowest precedence, as for `PATH`.
+    AppendList { separator: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct FileResolutionConfig {
     #[serde(default)]
@@ -151,7 +332,7 @@ pub enum FileMergeMode {
     Override,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct InterpolationConfig {
     #[serde(default = "default_true")]
@@ -176,7 +357,7 @@ fn default_max_depth() -> u32 {
     64
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct InterpolationFeatures {
     #[serde(default = "default_true")]
@@ -187,6 +368,10 @@ pub struct InterpolationFeatures {
     pub recursion: bool,
     #[serde(default)]
     pub commands: bool,
+    /// Resolve `${pkg:<name>.<KEY>}` to `KEY` as defined by package `name`'s
+    /// own env files.
+    #[serde(default)]
+    pub cross_package: bool,
 }
 
 impl Default for InterpolationFeatures {
@@ -196,17 +381,22 @@ impl Default for InterpolationFeatures {
             alternates: true,
             recursion: true,
             commands: false,
+            cross_package: false,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct CacheConfig {
     pub enabled: bool,
     pub hot_cache_size: usize,
     #[serde(with = "humantime_serde")]
     pub ttl: std::time::Duration,
+    /// TTL for values from remote sources, which usually should expire sooner
+    /// than file-derived ones. Falls back to `ttl` when unset.
+    #[serde(default, with = "humantime_serde")]
+    pub remote_ttl: Option<std::time::Duration>,
 }
 
 impl Default for CacheConfig {
@@ -215,6 +405,7 @@ impl Default for CacheConfig {
             enabled: true,
             hot_cache_size: 1000,
             ttl: std::time::Duration::from_secs(300),
+            remote_ttl: None,
         }
     }
 }
@@ -223,7 +414,7 @@ fn default_true() -> bool {
     true
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct SourcesConfig {
     pub defaults: SourceDefaults,
@@ -238,7 +429,7 @@ pub struct SourcesConfig {
 }
 
 /// Configuration for external out-of-process providers.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ProvidersConfig {
     /// Path to the directory containing provider binaries.
@@ -276,7 +467,7 @@ fn default_providers_path() -> PathBuf {
 }
 
 /// Configuration for an individual external provider.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct ExternalProviderConfig {
     /// Whether this provider is enabled.
@@ -357,7 +548,7 @@ pub enum SpawnStrategy {
     Eager,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(default)]
 pub struct SourceDefaults {
     #[serde(default = "default_true")]
@@ -366,6 +557,11 @@ pub struct SourceDefaults {
     pub file: bool,
     #[serde(default)]
     pub remote: bool,
+    /// Upper bound on the env files discovery may register. A glob that
+    /// matches more fails the build instead of silently loading them all.
+    /// Unlimited by default.
+    #[serde(default)]
+    pub max_sources: Option<usize>,
 }
 
 impl Default for SourceDefaults {
@@ -374,6 +570,7 @@ impl Default for SourceDefaults {
             shell: true,
             file: true,
             remote: false,
+            max_sources: None,
         }
     }
 }
@@ -14,6 +14,12 @@ pub struct AbundantisBuilder {
     _event_buffer_size: Option<usize>,
     active_files: Option<Vec<String>>,
     active_files_for_directory: HashMap<PathBuf, Vec<String>>,
+    #[cfg(feature = "file")]
+    schema_file: Option<PathBuf>,
+    #[cfg(feature = "file")]
+    config_files: Vec<PathBuf>,
+    conflict_resolver: Option<super::resolution::ConflictResolver>,
+    watch_disabled: bool,
 }
 
 impl AbundantisBuilder {
@@ -36,6 +42,21 @@ impl AbundantisBuilder {
         self
     }
 
+    /// Uses exactly these directories, relative to the root, as the
+    /// packages instead of discovering them.
+    pub fn package_dirs(mut self, dirs: Vec<impl Into<CompactString>>) -> Self {
+        self.config.workspace.provider = Some(super::config::MonorepoProviderType::Directories);
+        self.config.workspace.roots = dirs.into_iter().map(|d| d.into()).collect();
+        self
+    }
+
+    /// Whether to watch the discovered env files for changes; on by
+    /// default. Has no effect without the `watch` feature.
+    pub fn watch(mut self, enabled: bool) -> Self {
+        self.watch_disabled = !enabled;
+        self
+    }
+
     pub fn cascading(mut self, enabled: bool) -> Self {
         self.config.workspace.cascading = enabled;
         self
@@ -69,11 +90,25 @@ impl AbundantisBuilder {
         self
     }
 
+    /// Source types to consult, highest precedence first.
     pub fn precedence(mut self, precedence: Vec<super::config::SourcePrecedence>) -> Self {
         self.config.resolution.precedence = precedence;
         self
     }
 
+    /// Records per-key resolution times, readable via
+    /// [`Abundantis::last_profile`](super::Abundantis::last_profile).
+    pub fn profile(mut self, enabled: bool) -> Self {
+        self.config.resolution.profile = enabled;
+        self
+    }
+
+    /// How references to undefined variables are interpolated.
+    pub fn on_undefined(mut self, on_undefined: super::config::OnUndefined) -> Self {
+        self.config.resolution.on_undefined = on_undefined;
+        self
+    }
+
     pub fn interpolation(mut self, enabled: bool) -> Self {
         self.config.interpolation.enabled = enabled;
         self
@@ -124,6 +159,14 @@ impl AbundantisBuilder {
         self
     }
 
+    /// Chooses the winner when sources of equal precedence define the same
+    /// key; see [`ConflictResolver`](super::resolution::ConflictResolver).
+    /// By default the source with the greatest id wins.
+    pub fn conflict_resolver(mut self, resolver: super::resolution::ConflictResolver) -> Self {
+        self.conflict_resolver = Some(resolver);
+        self
+    }
+
     pub fn subscribe(mut self, subscriber: Arc<dyn super::events::EventSubscriber>) -> Self {
         self.subscribers.push(subscriber);
         self
@@ -134,6 +177,38 @@ impl AbundantisBuilder {
         self
     }
 
+    /// Activates `.env`, `.env.local`, `.env.<environment>` and
+    /// `.env.<environment>.local` by default, mirroring the usual
+    /// `NODE_ENV`-style layering.
+    pub fn environment(mut self, environment: impl Into<CompactString>) -> Self {
+        self.config.selection.environment = Some(environment.into());
+        self
+    }
+
+    /// Lets `*` and other wildcards in active file patterns match dotfiles.
+    pub fn match_dotfiles(mut self, enabled: bool) -> Self {
+        self.config.selection.match_dotfiles = enabled;
+        self
+    }
+
+    /// Reads declared defaults and descriptions from a declarations file such
+    /// as `.env.example`. Relative paths are taken from the workspace root.
+    #[cfg(feature = "file")]
+    pub fn schema_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.schema_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Reads variables from a config file, choosing the source by extension:
+    /// `.json` files are flattened into env keys by
+    /// [`JsonSource`](super::source::JsonSource). Relative paths are taken
+    /// from the workspace root; other extensions fail the build.
+    #[cfg(feature = "file")]
+    pub fn config_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.config_files.push(path.as_ref().to_path_buf());
+        self
+    }
+
     pub fn active_files(mut self, patterns: Vec<impl AsRef<str>>) -> Self {
         self.active_files = Some(patterns.iter().map(|p| p.as_ref().to_string()).collect());
         self
@@ -156,6 +231,30 @@ impl AbundantisBuilder {
         self
     }
 
+    /// Runs [`build`](Self::build) on a temporary current-thread runtime, for
+    /// sync initialization code with no runtime at hand. Calling it from
+    /// inside a tokio runtime returns [`AbundantisError::Runtime`] instead of
+    /// panicking.
+    ///
+    /// [`AbundantisError::Runtime`]: super::AbundantisError::Runtime
+    #[cfg(feature = "async")]
+    pub fn build_blocking(self) -> Result<super::Abundantis, super::AbundantisError> {
+        if tokio::runtime::Handle::try_current().is_ok() {
+            return Err(super::AbundantisError::Runtime(
+                "build_blocking cannot be called from within an async runtime; use build().await instead"
+                    .into(),
+            ));
+        }
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| {
+                super::AbundantisError::Runtime(format!("Failed to start runtime: {}", e))
+            })?;
+        runtime.block_on(self.build())
+    }
+
     #[cfg(feature = "async")]
     pub async fn build(self) -> Result<super::Abundantis, super::AbundantisError> {
         let mut config = self.config.clone();
@@ -192,6 +291,11 @@ impl AbundantisBuilder {
             registry.register_sync(Arc::clone(source));
         }
 
+        #[cfg(feature = "file")]
+        for path in &self.config_files {
+            registry.register_sync(config_file_source(&root.join(path))?);
+        }
+
         // Register custom async sources
         for source in &self.custom_async_sources {
             registry.register_async(Arc::clone(source));
@@ -206,17 +310,20 @@ impl AbundantisBuilder {
         }
 
         #[cfg(all(feature = "watch", feature = "async"))]
-        let watch_manager: Arc<Option<super::watch_manager::WatchManager>> = Arc::new(
-            match super::watch_manager::WatchManager::new(Arc::clone(&event_bus)) {
-                Ok(m) => Some(m),
-                Err(e) => {
-                    return Err(super::AbundantisError::Runtime(format!(
-                        "Failed to initialize file watcher: {}",
-                        e
-                    )))
+        let watch_manager: Arc<Option<super::watch_manager::WatchManager>> =
+            Arc::new(if self.watch_disabled {
+                None
+            } else {
+                match super::watch_manager::WatchManager::new(Arc::clone(&event_bus)) {
+                    Ok(m) => Some(m),
+                    Err(e) => {
+                        return Err(super::AbundantisError::Runtime(format!(
+                            "Failed to initialize file watcher: {}",
+                            e
+                        )))
+                    }
                 }
-            },
-        );
+            });
 
         #[cfg(feature = "file")]
         if config.sources.defaults.file {
@@ -236,20 +343,30 @@ impl AbundantisBuilder {
                 Arc::new(super::source::ShellSource::new()) as Arc<dyn super::source::EnvSource>;
             registry.register_sync(shell_source);
         }
+        // Sources registered from here on are announced to subscribers.
+        registry.set_event_bus(Arc::clone(&event_bus));
 
         let resolution_engine = Arc::new(super::resolution::ResolutionEngine::new(
-            &config.resolution,
+            &config.effective_resolution(),
             &config.interpolation,
             &config.cache,
         ));
+        resolution_engine.set_packages(&workspace.packages());
+        resolution_engine.set_conflict_resolver(self.conflict_resolver.clone());
+        #[cfg(feature = "file")]
+        if let Some(schema_file) = &self.schema_file {
+            let schema = super::schema::EnvSchema::load(root.join(schema_file))?;
+            resolution_engine.set_schema(&schema);
+        }
 
         let cache = Arc::clone(resolution_engine.cache());
 
         let path_cache = super::path_cache::PathCache::new();
 
-        let selector = Arc::new(super::selection::ActiveFileSelector::new(
+        let selector = Arc::new(super::selection::ActiveFileSelector::with_config(
             &root,
             Arc::new(path_cache.clone()),
+            config.selection.clone(),
         ));
 
         #[cfg(all(feature = "watch", feature = "async"))]
@@ -268,6 +385,9 @@ impl AbundantisBuilder {
             directory_active_files: parking_lot::RwLock::new(self.active_files_for_directory),
             path_to_source_id: parking_lot::RwLock::new(HashMap::new()),
             path_cache,
+            last_config_check: parking_lot::Mutex::new(std::time::Instant::now()),
+            #[cfg(all(feature = "watch", feature = "async"))]
+            watch_manager,
             event_bus,
         })
     }
@@ -280,10 +400,11 @@ impl AbundantisBuilder {
         config: &super::AbundantisConfig,
     ) -> Result<Vec<Arc<super::source::FileSource>>, super::AbundantisError> {
         let mut sources = Vec::new();
+        let ignores = config.workspace.ignore_set();
 
-        for package in workspace.packages() {
-            for pattern in &config.workspace.env_files {
-                let full_pattern = package.root.join(pattern.as_str());
+        for dir in env_file_dirs(workspace) {
+            for pattern in &config.env_file_patterns() {
+                let full_pattern = dir.join(pattern.as_str());
                 let pattern_str = full_pattern.to_string_lossy();
 
                 match glob::glob(&pattern_str) {
@@ -291,11 +412,18 @@ impl AbundantisBuilder {
                         for entry in paths {
                             match entry {
                                 Ok(path) => {
-                                    if path.is_file() {
+                                    if path.is_file()
+                                        && !is_ignored(&ignores, workspace.root(), &path)
+                                    {
                                         match super::source::FileSource::new(&path) {
                                             Ok(file_source) => {
                                                 let arc_source = Arc::new(file_source);
                                                 sources.push(arc_source);
+                                                check_max_sources(
+                                                    config,
+                                                    sources.len(),
+                                                    &pattern_str,
+                                                )?;
                                             }
                                             Err(e) => {
                                                 tracing::warn!(
@@ -330,7 +458,8 @@ impl AbundantisBuilder {
         if config.workspace.provider.is_none() {
             return Err(super::AbundantisError::MissingConfig {
                 field: "workspace.provider",
-                suggestion: "Set to one of: turbo, nx, lerna, pnpm, npm, cargo, custom".into(),
+                suggestion:
+                    "Set to one of: turbo, nx, lerna, pnpm, npm, cargo, custom, directories".into(),
             });
         }
 
@@ -352,6 +481,11 @@ impl AbundantisBuilder {
             registry.register_sync(Arc::clone(source));
         }
 
+        #[cfg(feature = "file")]
+        for path in &self.config_files {
+            registry.register_sync(config_file_source(&root.join(path))?);
+        }
+
         #[cfg(feature = "file")]
         if config.sources.defaults.file {
             let file_sources = self.discover_file_sources(&workspace, &config)?;
@@ -368,18 +502,26 @@ impl AbundantisBuilder {
         }
 
         let resolution_engine = Arc::new(super::resolution::ResolutionEngine::new(
-            &config.resolution,
+            &config.effective_resolution(),
             &config.interpolation,
             &config.cache,
         ));
+        resolution_engine.set_packages(&workspace.packages());
+        resolution_engine.set_conflict_resolver(self.conflict_resolver.clone());
+        #[cfg(feature = "file")]
+        if let Some(schema_file) = &self.schema_file {
+            let schema = super::schema::EnvSchema::load(root.join(schema_file))?;
+            resolution_engine.set_schema(&schema);
+        }
 
         let cache = Arc::clone(resolution_engine.cache());
 
         let path_cache = super::path_cache::PathCache::new();
 
-        let selector = Arc::new(super::selection::ActiveFileSelector::new(
+        let selector = Arc::new(super::selection::ActiveFileSelector::with_config(
             &root,
             Arc::new(path_cache.clone()),
+            config.selection.clone(),
         ));
 
         let event_bus = Arc::new(super::events::EventBus::new(
@@ -389,6 +531,8 @@ impl AbundantisBuilder {
         for subscriber in &self.subscribers {
             event_bus.subscribe(Arc::clone(subscriber));
         }
+        // Sources registered from here on are announced to subscribers.
+        registry.set_event_bus(Arc::clone(&event_bus));
 
         Ok(super::Abundantis {
             config,
@@ -401,6 +545,7 @@ impl AbundantisBuilder {
             directory_active_files: parking_lot::RwLock::new(self.active_files_for_directory),
             path_to_source_id: parking_lot::RwLock::new(HashMap::new()),
             path_cache,
+            last_config_check: parking_lot::Mutex::new(std::time::Instant::now()),
             event_bus,
         })
     }
@@ -413,10 +558,11 @@ impl AbundantisBuilder {
         config: &super::AbundantisConfig,
     ) -> Result<Vec<Arc<super::source::FileSource>>, super::AbundantisError> {
         let mut sources = Vec::new();
+        let ignores = config.workspace.ignore_set();
 
-        for package in workspace.packages() {
-            for pattern in &config.workspace.env_files {
-                let full_pattern = package.root.join(pattern.as_str());
+        for dir in env_file_dirs(workspace) {
+            for pattern in &config.env_file_patterns() {
+                let full_pattern = dir.join(pattern.as_str());
                 let pattern_str = full_pattern.to_string_lossy();
 
                 match glob::glob(&pattern_str) {
@@ -424,11 +570,18 @@ impl AbundantisBuilder {
                         for entry in paths {
                             match entry {
                                 Ok(path) => {
-                                    if path.is_file() {
+                                    if path.is_file()
+                                        && !is_ignored(&ignores, workspace.root(), &path)
+                                    {
                                         match super::source::FileSource::new(&path) {
                                             Ok(file_source) => {
                                                 let arc_source = Arc::new(file_source);
                                                 sources.push(arc_source);
+                                                check_max_sources(
+                                                    config,
+                                                    sources.len(),
+                                                    &pattern_str,
+                                                )?;
                                             }
                                             Err(e) => {
                                                 tracing::warn!(
@@ -456,3 +609,65 @@ impl AbundantisBuilder {
         Ok(sources)
     }
 }
+
+/// Fails once discovery has found `count` env files and that's more than
+/// `sources.defaults.max_sources` allows; `pattern` is the glob that went over.
+#[cfg(feature = "file")]
+pub(crate) fn check_max_sources(
+    config: &super::AbundantisConfig,
+    count: usize,
+    pattern: &str,
+) -> Result<(), super::AbundantisError> {
+    match config.sources.defaults.max_sources {
+        Some(max) if count > max => Err(super::AbundantisError::Config {
+            message: format!(
+                "Env file pattern '{}' exceeds the limit of {} discovered sources \
+                 (sources.defaults.max_sources)",
+                pattern, max
+            ),
+            path: None,
+        }),
+        _ => Ok(()),
+    }
+}
+
+/// Directories whose env files are registered as sources: every package root,
+/// plus the workspace root when its files cascade into packages, even if the
+/// root isn't a package itself.
+#[cfg(feature = "file")]
+pub(crate) fn env_file_dirs(workspace: &super::workspace::WorkspaceManager) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if workspace.cascading() {
+        dirs.push(workspace.root().to_path_buf());
+    }
+    for package in workspace.packages() {
+        if !dirs.contains(&package.root) {
+            dirs.push(package.root);
+        }
+    }
+    dirs
+}
+
+/// The source for a config file registered with
+/// [`AbundantisBuilder::config_file`], picked by its extension.
+#[cfg(feature = "file")]
+fn config_file_source(
+    path: &Path,
+) -> Result<Arc<dyn super::source::EnvSource>, super::AbundantisError> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => Ok(Arc::new(super::source::JsonSource::new(path)?)),
+        _ => Err(super::AbundantisError::Config {
+            message: format!("Unsupported config file type: {}", path.display()),
+            path: Some(path.to_path_buf()),
+        }),
+    }
+}
+
+/// Matches `path` against the ignore set both relative to the workspace root
+/// and as given, so `dist/**` and `**/node_modules/**` style patterns both work.
+#[cfg(feature = "file")]
+pub(crate) fn is_ignored(ignores: &globset::GlobSet, root: &Path, path: &Path) -> bool {
+    path.strip_prefix(root)
+        .is_ok_and(|relative| ignores.is_match(relative))
+        || ignores.is_match(path)
+}
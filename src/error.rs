@@ -15,7 +15,7 @@ pub enum AbundantisError {
         suggestion: String,
     },
 
-    #[error("Unknown provider `{provider}`. Valid options: turbo, nx, lerna, pnpm, npm, yarn, cargo, custom")]
+    #[error("Unknown provider `{provider}`. Valid options: turbo, nx, lerna, pnpm, npm, yarn, cargo, custom, directories")]
     UnknownProvider { provider: String },
 
     #[error("Invalid glob pattern `{pattern}`: {reason}")]
@@ -39,12 +39,19 @@ pub enum AbundantisError {
     #[error("Circular dependency detected: {chain}")]
     CircularDependency { chain: String },
 
-    #[error("Max interpolation depth ({depth}) exceeded for `{key}`")]
-    MaxDepthExceeded { key: String, depth: u32 },
+    #[error("Max interpolation depth ({depth}) exceeded for `{key}` via {chain}")]
+    MaxDepthExceeded {
+        key: String,
+        depth: u32,
+        chain: String,
+    },
 
     #[error("Undefined variable `{key}` referenced in interpolation")]
     UndefinedVariable { key: String },
 
+    #[error("Invalid value for `{key}`: {reason}")]
+    InvalidValue { key: String, reason: String },
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -102,6 +109,96 @@ pub enum SourceError {
     UnknownProvider { provider: String },
 }
 
+impl SourceError {
+    /// Maps an error raised by `provider`'s client to the closest variant:
+    /// timeouts, refused connections and auth failures are told apart for
+    /// `reqwest` and I/O errors; anything else becomes `Remote`.
+    pub fn from_remote(provider: impl Into<String>, err: impl std::error::Error + 'static) -> Self {
+        let provider = provider.into();
+        let err: &(dyn std::error::Error + 'static) = &err;
+
+        #[cfg(feature = "remote")]
+        if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+            return Self::from_reqwest(provider, err);
+        }
+
+        if let Some(io) = err.downcast_ref::<std::io::Error>() {
+            use std::io::ErrorKind;
+            match io.kind() {
+                ErrorKind::TimedOut => {
+                    return Self::Timeout {
+                        source_name: provider,
+                    }
+                }
+                ErrorKind::PermissionDenied => {
+                    return Self::Permission {
+                        source_name: provider,
+                    }
+                }
+                ErrorKind::ConnectionRefused
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+                | ErrorKind::NotConnected => {
+                    return Self::Connection {
+                        provider,
+                        reason: io.to_string(),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Self::Remote {
+            provider,
+            reason: err.to_string(),
+        }
+    }
+
+    #[cfg(feature = "remote")]
+    fn from_reqwest(provider: String, err: &reqwest::Error) -> Self {
+        if err.is_timeout() {
+            return Self::Timeout {
+                source_name: provider,
+            };
+        }
+        if err.is_connect() {
+            return Self::Connection {
+                provider,
+                reason: err.to_string(),
+            };
+        }
+        match err.status().map(|status| status.as_u16()) {
+            Some(401) => Self::Authentication {
+                source_name: provider,
+            },
+            Some(403) => Self::Permission {
+                source_name: provider,
+            },
+            Some(429) => Self::RateLimited {
+                provider,
+                retry_after_secs: 0,
+            },
+            _ => Self::Remote {
+                provider,
+                reason: err.to_string(),
+            },
+        }
+    }
+}
+
+/// Uses the request's host as the provider name.
+#[cfg(feature = "remote")]
+impl From<reqwest::Error> for SourceError {
+    fn from(err: reqwest::Error) -> Self {
+        let provider = err
+            .url()
+            .and_then(|url| url.host_str())
+            .unwrap_or("http")
+            .to_string();
+        Self::from_reqwest(provider, &err)
+    }
+}
+
 pub type Result<T> = std::result::Result<T, AbundantisError>;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -112,6 +209,17 @@ pub enum DiagnosticSeverity {
     Hint,
 }
 
+impl DiagnosticSeverity {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            DiagnosticSeverity::Error => "error",
+            DiagnosticSeverity::Warning => "warning",
+            DiagnosticSeverity::Info => "info",
+            DiagnosticSeverity::Hint => "hint",
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Diagnostic {
     pub severity: DiagnosticSeverity,
@@ -120,20 +228,82 @@ pub struct Diagnostic {
     pub path: PathBuf,
     pub line: u32,
     pub column: u32,
+    /// The source line the diagnostic points at, without its newline.
+    pub snippet: Option<String>,
+    /// Short note printed next to the caret when rendering.
+    pub label: Option<String>,
+}
+
+impl Diagnostic {
+    /// Renders the diagnostic in the style of rustc, with a caret under
+    /// `column` when a snippet is available:
+    ///
+    /// ```text
+    /// error[EDF001]: Expected `KEY=VALUE`, found `oops`
+    ///  --> .env:3:1
+    ///   |
+    /// 3 | oops
+    ///   | ^ expected `KEY=VALUE`
+    /// ```
+    pub fn render(&self) -> String {
+        let mut out = format!(
+            "{}[{}]: {}\n",
+            self.severity.as_str(),
+            self.code,
+            self.message
+        );
+
+        let line_number = self.line.to_string();
+        let gutter = " ".repeat(line_number.len());
+        out.push_str(&format!(
+            "{} --> {}:{}:{}\n",
+            gutter,
+            self.path.display(),
+            self.line,
+            self.column
+        ));
+
+        if let Some(snippet) = &self.snippet {
+            // Keep tabs so the caret lines up with the snippet as displayed.
+            let padding: String = snippet
+                .chars()
+                .take((self.column as usize).saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+
+            out.push_str(&format!("{} |\n", gutter));
+            out.push_str(&format!("{} | {}\n", line_number, snippet));
+            out.push_str(&format!("{} | {}^", gutter, padding));
+            if let Some(label) = &self.label {
+                out.push(' ');
+                out.push_str(label);
+            }
+            out.push('\n');
+        }
+
+        out
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum DiagnosticCode {
+    /// Malformed line that is not a `KEY=VALUE` entry or comment, or is not
+    /// valid UTF-8.
     EDF001,
+    /// Key defined more than once in the same file; the last definition wins.
     EDF002,
+    /// Line containing a NUL byte; it is skipped rather than parsed.
     EDF003,
+    /// Unknown or malformed setting in a file's `#!abundantis` directive.
     EDF004,
 
+    /// Reference to a variable that nothing defines and that has no fallback.
     RES001,
     RES002,
     RES003,
 
     WS001,
+    /// Key set to different values by env files in the same directory.
     WS002,
 }
 
@@ -1,5 +1,9 @@
 use compact_str::CompactString;
+#[cfg(feature = "async")]
+use parking_lot::Mutex;
 use parking_lot::RwLock;
+#[cfg(feature = "async")]
+use std::collections::VecDeque;
 use std::path::PathBuf;
 use std::sync::Arc;
 
@@ -29,6 +33,11 @@ pub enum AbundantisEvent {
     CacheInvalidated {
         scope: Option<super::workspace::WorkspaceContext>,
     },
+    /// Emitted when a refresh registers new env files or drops deleted ones.
+    SourcesRediscovered {
+        added: Vec<super::source::SourceId>,
+        removed: Vec<super::source::SourceId>,
+    },
     /// Emitted when variable resolution fails.
     ResolutionError {
         key: CompactString,
@@ -53,6 +62,38 @@ pub enum AbundantisEvent {
 /// Synchronous event subscriber trait.
 pub trait EventSubscriber: Send + Sync {
     fn on_event(&self, event: &AbundantisEvent);
+
+    /// Whether the subscriber no longer wants events. The bus drops closed
+    /// subscribers on the next publish.
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+/// Forwards every event into an mpsc channel. Events are dropped while the
+/// channel is full; once the receiver is gone the forwarder unsubscribes.
+#[cfg(feature = "async")]
+pub(crate) struct ChannelForwarder(pub(crate) tokio::sync::mpsc::Sender<AbundantisEvent>);
+
+#[cfg(feature = "async")]
+impl EventSubscriber for ChannelForwarder {
+    fn on_event(&self, event: &AbundantisEvent) {
+        if let Err(tokio::sync::mpsc::error::TrySendError::Full(_)) = self.0.try_send(event.clone())
+        {
+            tracing::debug!("on_change channel full, dropping event");
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+}
+
+/// Removes the subscribers that report themselves closed.
+fn prune_closed(subscribers: &RwLock<Vec<Arc<dyn EventSubscriber>>>) {
+    if subscribers.read().iter().any(|s| s.is_closed()) {
+        subscribers.write().retain(|s| !s.is_closed());
+    }
 }
 
 /// Asynchronous event subscriber trait.
@@ -67,6 +108,42 @@ pub struct EventBus {
     subscribers: Arc<RwLock<Vec<Arc<dyn EventSubscriber>>>>,
     async_subscribers: Arc<RwLock<Vec<Arc<dyn AsyncEventSubscriber>>>>,
     broadcast_tx: tokio::sync::broadcast::Sender<AbundantisEvent>,
+    replay: Option<Arc<Mutex<ReplayBuffer>>>,
+}
+
+/// Bounded ring buffer of the most recently broadcast events.
+#[cfg(feature = "async")]
+struct ReplayBuffer {
+    events: VecDeque<AbundantisEvent>,
+    capacity: usize,
+}
+
+/// Channel receiver that yields buffered events before live ones.
+#[cfg(feature = "async")]
+pub struct ReplayReceiver {
+    buffered: VecDeque<AbundantisEvent>,
+    live: tokio::sync::broadcast::Receiver<AbundantisEvent>,
+}
+
+#[cfg(feature = "async")]
+impl ReplayReceiver {
+    pub async fn recv(
+        &mut self,
+    ) -> Result<AbundantisEvent, tokio::sync::broadcast::error::RecvError> {
+        match self.buffered.pop_front() {
+            Some(event) => Ok(event),
+            None => self.live.recv().await,
+        }
+    }
+
+    pub fn try_recv(
+        &mut self,
+    ) -> Result<AbundantisEvent, tokio::sync::broadcast::error::TryRecvError> {
+        match self.buffered.pop_front() {
+            Some(event) => Ok(event),
+            None => self.live.try_recv(),
+        }
+    }
 }
 
 #[cfg(feature = "async")]
@@ -78,16 +155,51 @@ impl EventBus {
             subscribers: Arc::new(RwLock::new(Vec::new())),
             async_subscribers: Arc::new(RwLock::new(Vec::new())),
             broadcast_tx,
+            replay: None,
         }
     }
 
+    /// Keeps the last `capacity` broadcast events so that channels opened with
+    /// [`subscribe_channel_with_replay`](Self::subscribe_channel_with_replay)
+    /// can catch up on what they missed.
+    pub fn with_replay(mut self, capacity: usize) -> Self {
+        self.replay = Some(Arc::new(Mutex::new(ReplayBuffer {
+            events: VecDeque::with_capacity(capacity),
+            capacity,
+        })));
+        self
+    }
+
     pub fn publish(&self, event: AbundantisEvent) {
-        let subscribers = self.subscribers.read();
-        for subscriber in subscribers.iter() {
-            subscriber.on_event(&event);
+        {
+            let subscribers = self.subscribers.read();
+            for subscriber in subscribers.iter() {
+                subscriber.on_event(&event);
+            }
         }
+        prune_closed(&self.subscribers);
 
-        let _ = self.broadcast_tx.send(event.clone());
+        let _ = self.broadcast(event);
+    }
+
+    fn broadcast(
+        &self,
+        event: AbundantisEvent,
+    ) -> Result<usize, tokio::sync::broadcast::error::SendError<AbundantisEvent>> {
+        let Some(replay) = &self.replay else {
+            return self.broadcast_tx.send(event);
+        };
+
+        // Hold the buffer lock while sending so replay subscribers never see an
+        // event both in their snapshot and on the live channel.
+        let mut buffer = replay.lock();
+        if buffer.capacity > 0 {
+            if buffer.events.len() == buffer.capacity {
+                buffer.events.pop_front();
+            }
+            buffer.events.push_back(event.clone());
+        }
+        self.broadcast_tx.send(event)
     }
 
     pub async fn publish_async(&self, event: AbundantisEvent) {
@@ -104,6 +216,7 @@ impl EventBus {
         if let Err(e) = join_handle.await {
             tracing::error!("Sync event subscriber failed: {:?}", e);
         }
+        prune_closed(&self.subscribers);
 
         // Notify async subscribers
         let async_subscribers = self.async_subscribers.read().clone();
@@ -111,7 +224,7 @@ impl EventBus {
             subscriber.on_event(&event).await;
         }
 
-        if self.broadcast_tx.send(event).is_err() {
+        if self.broadcast(event).is_err() {
             tracing::debug!("No receivers for event bus broadcast");
         }
     }
@@ -140,6 +253,24 @@ impl EventBus {
         self.broadcast_tx.subscribe()
     }
 
+    /// Like [`subscribe_channel`](Self::subscribe_channel), but first yields the
+    /// events retained by [`with_replay`](Self::with_replay).
+    pub fn subscribe_channel_with_replay(&self) -> ReplayReceiver {
+        match &self.replay {
+            Some(replay) => {
+                let buffer = replay.lock();
+                ReplayReceiver {
+                    buffered: buffer.events.clone(),
+                    live: self.broadcast_tx.subscribe(),
+                }
+            }
+            None => ReplayReceiver {
+                buffered: VecDeque::new(),
+                live: self.broadcast_tx.subscribe(),
+            },
+        }
+    }
+
     pub fn subscriber_count(&self) -> usize {
         self.subscribers.read().len()
     }
@@ -160,6 +291,7 @@ impl Clone for EventBus {
             subscribers: Arc::clone(&self.subscribers),
             async_subscribers: Arc::clone(&self.async_subscribers),
             broadcast_tx: self.broadcast_tx.clone(),
+            replay: self.replay.clone(),
         }
     }
 }
@@ -178,10 +310,13 @@ impl EventBus {
     }
 
     pub fn publish(&self, event: AbundantisEvent) {
-        let subscribers = self.subscribers.read();
-        for subscriber in subscribers.iter() {
-            subscriber.on_event(&event);
+        {
+            let subscribers = self.subscribers.read();
+            for subscriber in subscribers.iter() {
+                subscriber.on_event(&event);
+            }
         }
+        prune_closed(&self.subscribers);
     }
 
     pub fn subscribe(&self, subscriber: Arc<dyn EventSubscriber>) {
@@ -272,4 +407,40 @@ mod tests {
 
         assert_eq!(event_count.load(Ordering::SeqCst), 0);
     }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_closed_channel_forwarder_is_unsubscribed() {
+        let bus = EventBus::new(100);
+        let (tx, rx) = tokio::sync::mpsc::channel(4);
+        bus.subscribe(Arc::new(ChannelForwarder(tx)));
+        bus.publish(AbundantisEvent::CacheInvalidated { scope: None });
+        assert_eq!(bus.subscriber_count(), 1);
+
+        drop(rx);
+        bus.publish(AbundantisEvent::CacheInvalidated { scope: None });
+        assert_eq!(bus.subscriber_count(), 0);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_replay_for_late_subscriber() {
+        let bus = EventBus::new(100).with_replay(3);
+        let config_changed = |field: &str| AbundantisEvent::ConfigChanged {
+            field: field.into(),
+        };
+
+        bus.publish(config_changed("dropped"));
+        bus.publish(config_changed("first"));
+        bus.publish(config_changed("second"));
+        bus.publish(config_changed("third"));
+
+        let mut receiver = bus.subscribe_channel_with_replay();
+        bus.publish(config_changed("live"));
+
+        for expected in ["first", "second", "third", "live"] {
+            assert_eq!(receiver.recv().await.unwrap(), config_changed(expected));
+        }
+        assert!(receiver.try_recv().is_err());
+    }
 }
@@ -0,0 +1,116 @@
+//! Shell syntax for exporting resolved variables, e.g. for
+//! `eval "$(corncopia export)"`.
+
+use crate::resolution::ResolvedVariable;
+use std::sync::Arc;
+
+/// Shell whose syntax [`format_exports`] emits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellKind {
+    Bash,
+    Zsh,
+    Fish,
+    PowerShell,
+}
+
+impl ShellKind {
+    /// One statement exporting `key` with `value` quoted for this shell.
+    pub fn export_line(self, key: &str, value: &str) -> String {
+        match self {
+            ShellKind::Bash | ShellKind::Zsh => {
+                format!("export {}='{}'", key, value.replace('\'', r"'\''"))
+            }
+            ShellKind::Fish => format!(
+                "set -x {} '{}'",
+                key,
+                value.replace('\\', r"\\").replace('\'', r"\'")
+            ),
+            ShellKind::PowerShell => {
+                // PowerShell also reads the typographic quotes ‘ ’ ‚ ‛ as
+                // single quotes; each is escaped by doubling it.
+                let mut quoted = String::with_capacity(value.len());
+                for c in value.chars() {
+                    if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') {
+                        quoted.push(c);
+                    }
+                    quoted.push(c);
+                }
+                format!("$env:{}='{}'", key, quoted)
+            }
+        }
+    }
+}
+
+/// Export statements for `variables`, one per line. Keys that aren't valid
+/// shell identifiers are skipped, since no shell could reference them.
+pub fn format_exports(variables: &[Arc<ResolvedVariable>], shell: ShellKind) -> String {
+    let mut output = String::new();
+    for variable in variables {
+        if !is_identifier(&variable.key) {
+            tracing::debug!(key = %variable.key, "Skipping key that is not a shell identifier");
+            continue;
+        }
+        output.push_str(&shell.export_line(&variable.key, &variable.resolved_value));
+        output.push('\n');
+    }
+    output
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_single_quote_is_escaped_per_shell() {
+        let value = "it's $HOME";
+        assert_eq!(
+            ShellKind::Bash.export_line("GREETING", value),
+            r"export GREETING='it'\''s $HOME'"
+        );
+        assert_eq!(
+            ShellKind::Zsh.export_line("GREETING", value),
+            r"export GREETING='it'\''s $HOME'"
+        );
+        assert_eq!(
+            ShellKind::Fish.export_line("GREETING", r"it's C:\tmp"),
+            r"set -x GREETING 'it\'s C:\\tmp'"
+        );
+        assert_eq!(
+            ShellKind::PowerShell.export_line("GREETING", value),
+            "$env:GREETING='it''s $HOME'"
+        );
+        assert_eq!(
+            ShellKind::PowerShell.export_line("GREETING", "‘it’s’ ‚a‛"),
+            "$env:GREETING='‘‘it’’s’’ ‚‚a‛‛'"
+        );
+    }
+
+    #[test]
+    fn test_format_exports_skips_invalid_keys() {
+        let variable = |key: &str, value: &str| {
+            Arc::new(ResolvedVariable {
+                key: key.into(),
+                raw_value: value.into(),
+                resolved_value: value.into(),
+                source: crate::source::VariableSource::Memory,
+                description: None,
+                has_warnings: false,
+                interpolation_depth: 0,
+            })
+        };
+        let variables = [variable("PORT", "3000"), variable("bad-key", "x")];
+
+        assert_eq!(
+            format_exports(&variables, ShellKind::Bash),
+            "export PORT='3000'\n"
+        );
+    }
+}
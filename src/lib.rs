@@ -74,11 +74,16 @@ let _abundantis = Abundantis::builder()
 //! - Cache-friendly data structures with `hashbrown`
 //! - Small string optimization with `compact_str`
 
+#[cfg(feature = "blocking")]
+pub mod blocking;
 pub mod config;
 pub mod error;
 pub mod events;
+pub mod export;
 pub mod path_cache;
 pub mod resolution;
+#[cfg(feature = "file")]
+pub mod schema;
 pub mod selection;
 pub mod source;
 pub mod workspace;
@@ -86,6 +91,7 @@ pub mod workspace;
 pub mod watch;
 pub mod watch_manager;
 
+use compact_str::CompactString;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -102,16 +108,24 @@ pub use config::{
 pub use error::{AbundantisError, Diagnostic, DiagnosticCode, DiagnosticSeverity, Result};
 #[cfg(feature = "async")]
 pub use events::{AbundantisEvent, EventBus, EventSubscriber};
+pub use export::ShellKind;
 pub use path_cache::PathCache;
 pub use resolution::{
-    CacheKey, DependencyGraph, ResolutionCache, ResolutionEngine, ResolvedVariable,
+    CacheKey, ConflictResolver, DependencyGraph, EnvDiff, EnvSnapshot, ResolutionCache,
+    ResolutionCacheStats, ResolutionEngine, ResolvedVariable,
 };
 #[cfg(feature = "async")]
-pub use source::AsyncEnvSource;
+pub use source::{AsyncAdapter, AsyncEnvSource};
+#[cfg(feature = "file")]
+pub use source::BytesSource;
 #[cfg(feature = "file")]
 pub use source::FileSource;
 #[cfg(feature = "file")]
 pub use source::FileSourceManager;
+#[cfg(feature = "git")]
+pub use source::GitSource;
+#[cfg(feature = "file")]
+pub use source::JsonSource;
 #[cfg(feature = "shell")]
 pub use source::ShellSource;
 pub use source::{
@@ -124,6 +138,10 @@ pub use workspace::{PackageInfo, WorkspaceContext, WorkspaceManager};
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// How often lookups check the workspace provider's config files for edits
+/// that change the package list.
+const CONFIG_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
 /// Options for Abundantis refresh operations.
 
 #[derive(Debug, Clone, Default)]
@@ -163,6 +181,10 @@ pub struct Abundantis {
     directory_active_files: parking_lot::RwLock<HashMap<PathBuf, Vec<String>>>,
     path_to_source_id: parking_lot::RwLock<HashMap<PathBuf, source::SourceId>>,
     path_cache: path_cache::PathCache,
+    /// When the provider's config files were last checked for changes.
+    last_config_check: parking_lot::Mutex<std::time::Instant>,
+    #[cfg(all(feature = "watch", feature = "async"))]
+    watch_manager: Arc<Option<watch_manager::WatchManager>>,
     #[cfg(feature = "async")]
     event_bus: Arc<events::EventBus>,
     #[cfg(not(feature = "async"))]
@@ -181,24 +203,493 @@ impl Abundantis {
         key: &str,
         file_path: &std::path::Path,
     ) -> crate::Result<Option<Arc<ResolvedVariable>>> {
-        let context = {
-            let workspace = self.workspace.read();
-            workspace
-                .context_for_file(file_path)
-                .ok_or_else(|| AbundantisError::Config {
-                    message: format!(
-                        "No workspace context found for file: {}",
-                        file_path.display()
-                    ),
-                    path: Some(file_path.to_path_buf()),
-                })?
-        };
+        let context = self.context_for_file(file_path)?;
 
         let active_files = self.active_env_files(file_path);
         self.get_in_context_with_filter(key, &context, &active_files)
             .await
     }
 
+    /// Like [`get_for_file`](Self::get_for_file), with the values in `overlay`
+    /// (e.g. request-scoped ones like a tenant id) winning over every source
+    /// and visible to interpolation. Nothing is registered, and the overlay
+    /// doesn't affect other lookups.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn get_for_file_with_overlay(
+        &self,
+        key: &str,
+        file_path: &std::path::Path,
+        overlay: &HashMap<CompactString, CompactString>,
+    ) -> crate::Result<Option<Arc<ResolvedVariable>>> {
+        let context = self.context_for_file(file_path)?;
+
+        let active_files = self.active_env_files(file_path);
+        let file_source_ids = self.get_source_ids_for_paths(&active_files);
+        self.resolution
+            .resolve_with_overlay(
+                key,
+                &context,
+                &self.registry,
+                Some(&file_source_ids),
+                overlay,
+            )
+            .await
+    }
+
+    /// Like [`get_for_file`](Self::get_for_file), but treats a failed lookup
+    /// (no workspace context, an interpolation error, ...) the same as an
+    /// undefined key. Errors are logged at debug level.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn try_get_for_file(
+        &self,
+        key: &str,
+        file_path: &std::path::Path,
+    ) -> Option<Arc<ResolvedVariable>> {
+        match self.get_for_file(key, file_path).await {
+            Ok(variable) => variable,
+            Err(e) => {
+                tracing::debug!(
+                    "Resolving {} for {} failed: {}",
+                    key,
+                    file_path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Resolves `key` for `file_path` and returns its value along with where
+    /// the winning definition came from.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn get_with_source(
+        &self,
+        key: &str,
+        file_path: &std::path::Path,
+    ) -> crate::Result<Option<(CompactString, VariableSource)>> {
+        Ok(self
+            .get_for_file(key, file_path)
+            .await?
+            .map(|variable| (variable.resolved_value.clone(), variable.source.clone())))
+    }
+
+    /// Resolves `key` for `file_path` as if `resolution.precedence` were
+    /// `precedence`, e.g. `&[SourcePrecedence::File]` to ignore the shell.
+    /// Neither the configuration nor values cached for other queries change.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn get_for_file_with_precedence(
+        &self,
+        key: &str,
+        file_path: &std::path::Path,
+        precedence: &[config::SourcePrecedence],
+    ) -> crate::Result<Option<Arc<ResolvedVariable>>> {
+        let context = self.context_for_file(file_path)?;
+        let active_files = self.active_env_files(file_path);
+        let file_source_ids = self.get_source_ids_for_paths(&active_files);
+
+        self.resolution
+            .resolve_with_precedence(
+                key,
+                &context,
+                &self.registry,
+                Some(&file_source_ids),
+                precedence,
+            )
+            .await
+    }
+
+    /// Everything known about `key` as seen from `file_path`, e.g. for an
+    /// editor hover: its values, description, origin and the diagnostics
+    /// reported on the lines defining it in the active env files.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn describe(
+        &self,
+        key: &str,
+        file_path: &std::path::Path,
+    ) -> crate::Result<Option<VariableInfo>> {
+        let Some(variable) = self.get_for_file(key, file_path).await? else {
+            return Ok(None);
+        };
+
+        #[cfg(feature = "file")]
+        let diagnostics = match &variable.source {
+            VariableSource::File { path, offset } => {
+                let content = std::fs::read_to_string(path).unwrap_or_default();
+                let (line, _) = source::parse::line_col(&content, *offset);
+                self.validate_file(file_path)?
+                    .into_iter()
+                    .filter(|d| d.path == *path && d.line == line)
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        #[cfg(not(feature = "file"))]
+        let diagnostics = Vec::new();
+
+        Ok(Some(VariableInfo {
+            key: variable.key.clone(),
+            resolved_value: variable.resolved_value.clone(),
+            raw_value: variable.raw_value.clone(),
+            description: variable.description.clone(),
+            source: variable.source.clone(),
+            diagnostics,
+        }))
+    }
+
+    /// Resolves several keys for `file_path` at once, loading the sources a
+    /// single time. Results are in the same order as `keys`.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn get_many(
+        &self,
+        keys: &[&str],
+        file_path: &std::path::Path,
+    ) -> crate::Result<Vec<Option<Arc<ResolvedVariable>>>> {
+        let context = self.context_for_file(file_path)?;
+
+        let active_files = self.active_env_files(file_path);
+        let file_source_ids = self.get_source_ids_for_paths(&active_files);
+        self.resolution
+            .resolve_batch(keys, &context, &self.registry, Some(&file_source_ids))
+            .await
+    }
+
+    /// Resolves `key` for `file_path` and parses it as `T`. `Ok(None)` when
+    /// the key is undefined; an `InvalidValue` error when it doesn't parse.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn get_as<T>(
+        &self,
+        key: &str,
+        file_path: &std::path::Path,
+    ) -> crate::Result<Option<T>>
+    where
+        T: std::str::FromStr,
+        T::Err: std::fmt::Display,
+    {
+        self.get_for_file(key, file_path)
+            .await?
+            .map(|variable| variable.parse())
+            .transpose()
+    }
+
+    /// Like [`get_as`](Self::get_as) for flags; see [`ResolvedVariable::as_bool`].
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn get_bool(
+        &self,
+        key: &str,
+        file_path: &std::path::Path,
+    ) -> crate::Result<Option<bool>> {
+        self.get_for_file(key, file_path)
+            .await?
+            .map(|variable| variable.as_bool())
+            .transpose()
+    }
+
+    /// Like [`get_as`](Self::get_as) for durations such as `30s`; see
+    /// [`ResolvedVariable::as_duration`].
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn get_duration(
+        &self,
+        key: &str,
+        file_path: &std::path::Path,
+    ) -> crate::Result<Option<std::time::Duration>> {
+        self.get_for_file(key, file_path)
+            .await?
+            .map(|variable| variable.as_duration())
+            .transpose()
+    }
+
+    /// Resolves `key` and splits it on `separator`; see
+    /// [`ResolvedVariable::as_list`].
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn get_list(
+        &self,
+        key: &str,
+        separator: &str,
+        file_path: &std::path::Path,
+    ) -> crate::Result<Option<Vec<String>>> {
+        Ok(self
+            .get_for_file(key, file_path)
+            .await?
+            .map(|variable| variable.as_list(separator)))
+    }
+
+    /// Returns the pre-interpolation value of `key` from the winning source,
+    /// e.g. `${HOST}/api` rather than its resolved form.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn get_raw(
+        &self,
+        key: &str,
+        file_path: &std::path::Path,
+    ) -> crate::Result<Option<CompactString>> {
+        self.context_for_file(file_path)?;
+
+        let active_files = self.active_env_files(file_path);
+        let file_source_ids = self.get_source_ids_for_paths(&active_files);
+        let variable = self
+            .resolution
+            .resolve_raw_with_filter(key, &self.registry, Some(&file_source_ids))
+            .await?;
+
+        Ok(variable.map(|v| v.raw_value))
+    }
+
+    /// Removes the winning definition of `key` for `file_path` from the env
+    /// file that holds it. Returns `Ok(None)` when the key is undefined or comes
+    /// from a source that can't be written back (shell, remote, ...).
+    #[cfg(feature = "file")]
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn unset(
+        &self,
+        key: &str,
+        file_path: &std::path::Path,
+    ) -> crate::Result<Option<source::ParsedVariable>> {
+        self.context_for_file(file_path)?;
+
+        let active_files = self.active_env_files(file_path);
+        let file_source_ids = self.get_source_ids_for_paths(&active_files);
+        let variable = self
+            .resolution
+            .resolve_raw_with_filter(key, &self.registry, Some(&file_source_ids))
+            .await?;
+
+        let Some(path) = variable.as_ref().and_then(|v| v.source.file_path()) else {
+            return Ok(None);
+        };
+
+        let registered = self.registry.sources_for_paths(std::slice::from_ref(path));
+        let removed = source::FileSource::new(path)
+            .map_err(AbundantisError::Io)?
+            .remove_variable(key)
+            .map_err(AbundantisError::Source)?;
+
+        for source in &registered {
+            source.invalidate();
+        }
+        self.cache.clear();
+
+        let source_id = registered.first().map_or_else(
+            || source::SourceId::canonical(source::SourceType::File, path.display()),
+            |source| source.id().clone(),
+        );
+        self.event_bus
+            .publish(events::AbundantisEvent::VariablesChanged {
+                source_id,
+                added: Vec::new(),
+                removed: vec![removed.key.clone()],
+            });
+
+        Ok(Some(removed))
+    }
+
+    /// Applies each file's `(key, value)` edits, appending keys the file
+    /// doesn't define yet. Every file is checked for writability before
+    /// anything is written, and if any write still fails the files already
+    /// edited are restored to their original contents.
+    #[cfg(feature = "file")]
+    pub fn set_many_files(&self, edits: &[(PathBuf, Vec<(String, String)>)]) -> Result<()> {
+        let mut originals = Vec::with_capacity(edits.len());
+        for (path, _) in edits {
+            std::fs::OpenOptions::new().append(true).open(path)?;
+            originals.push(std::fs::read(path)?);
+        }
+
+        for (index, (path, variables)) in edits.iter().enumerate() {
+            let applied = source::FileSource::new(path)
+                .map_err(AbundantisError::Io)
+                .and_then(|file| {
+                    file.set_variables(variables)
+                        .map_err(AbundantisError::Source)
+                });
+            if let Err(e) = applied {
+                for ((path, _), original) in edits.iter().zip(&originals).take(index + 1) {
+                    if let Err(restore) = std::fs::write(path, original) {
+                        tracing::error!("Failed to restore {}: {}", path.display(), restore);
+                    }
+                }
+                return Err(e);
+            }
+        }
+
+        // The registry indexes file sources by canonical path.
+        let paths: Vec<PathBuf> = edits
+            .iter()
+            .map(|(path, _)| self.path_cache.canonicalize(path))
+            .collect();
+        for source in self.registry.sources_for_paths(&paths) {
+            source.invalidate();
+        }
+        self.cache.clear();
+
+        self.event_bus
+            .publish(events::AbundantisEvent::CacheInvalidated { scope: None });
+
+        Ok(())
+    }
+
+    /// Checks the env files active for `file_path`, returning their parse
+    /// diagnostics plus a `WS002` warning for each key that files in the same
+    /// directory set to different values. Keys overridden across directories
+    /// (root vs. package) are intentional in cascading setups and not reported.
+    ///
+    /// References without a fallback to keys that no active file, non-file
+    /// source or schema default defines are reported as `RES001`, unless async
+    /// sources are registered and might define them.
+    #[cfg(feature = "file")]
+    pub fn validate_file(&self, file_path: &std::path::Path) -> crate::Result<Vec<Diagnostic>> {
+        self.context_for_file(file_path)?;
+
+        let active_files = self
+            .path_cache
+            .canonicalize_many(&self.active_env_files(file_path));
+        let mut diagnostics = Vec::new();
+        let mut loaded = Vec::new();
+        let mut by_directory: HashMap<PathBuf, Vec<(PathBuf, source::SourceSnapshot)>> =
+            HashMap::new();
+
+        for path in &active_files {
+            for source in self.registry.sources_for_paths(std::slice::from_ref(path)) {
+                diagnostics.extend(source.diagnostics());
+                let snapshot = source.load().map_err(AbundantisError::Source)?;
+                let directory = path.parent().map(Path::to_path_buf).unwrap_or_default();
+                loaded.push((path.clone(), snapshot.clone()));
+                by_directory
+                    .entry(directory)
+                    .or_default()
+                    .push((path.clone(), snapshot));
+            }
+        }
+
+        for files in by_directory.values() {
+            let mut first_seen: HashMap<&str, (&Path, &source::ParsedVariable)> = HashMap::new();
+            for (path, snapshot) in files {
+                for variable in snapshot.variables.iter().filter(|v| !v.is_commented) {
+                    let Some((first_path, first)) = first_seen.get(variable.key.as_str()) else {
+                        first_seen.insert(variable.key.as_str(), (path, variable));
+                        continue;
+                    };
+                    if *first_path == path.as_path() || first.raw_value == variable.raw_value {
+                        continue;
+                    }
+                    diagnostics.push(conflict_diagnostic(first_path, first, path, variable));
+                }
+            }
+        }
+
+        if !self.registry.has_async_sources() {
+            diagnostics.extend(self.undefined_references(&loaded)?);
+        }
+
+        Ok(diagnostics)
+    }
+
+    /// The `RES001` diagnostics for the variables in `files`, the active env
+    /// files in order.
+    #[cfg(feature = "file")]
+    fn undefined_references(
+        &self,
+        files: &[(PathBuf, source::SourceSnapshot)],
+    ) -> crate::Result<Vec<Diagnostic>> {
+        let mut defined: std::collections::HashSet<CompactString> = files
+            .iter()
+            .flat_map(|(_, snapshot)| snapshot.variables.iter())
+            .filter(|v| !v.is_commented)
+            .map(|v| v.key.clone())
+            .collect();
+        for source in self.registry.sync_sources_by_priority() {
+            if source.source_type() == source::SourceType::File {
+                continue;
+            }
+            let snapshot = source.load().map_err(AbundantisError::Source)?;
+            defined.extend(snapshot.variables.iter().map(|v| v.key.clone()));
+        }
+
+        let mut diagnostics = Vec::new();
+        for (path, snapshot) in files {
+            for variable in snapshot.variables.iter() {
+                if variable.is_commented || variable.is_literal {
+                    continue;
+                }
+                let mut reported: Vec<String> = Vec::new();
+                for reference in germi::find_variable_references(&variable.raw_value) {
+                    if reported.contains(&reference)
+                        || defined.contains(reference.as_str())
+                        || self.resolution.has_schema_default(&reference)
+                        || !resolution::references_plainly(&variable.raw_value, &reference)
+                    {
+                        continue;
+                    }
+                    reported.push(reference.clone());
+
+                    let (line, column, snippet) = variable_location(path, variable);
+                    diagnostics.push(Diagnostic {
+                        severity: DiagnosticSeverity::Warning,
+                        code: DiagnosticCode::RES001,
+                        message: format!(
+                            "`{}` references undefined variable `{}`",
+                            variable.key, reference
+                        ),
+                        path: path.clone(),
+                        line,
+                        column,
+                        snippet,
+                        label: Some(format!("`{}` is not defined", reference)),
+                    });
+                }
+            }
+        }
+        Ok(diagnostics)
+    }
+
+    /// [`validate_file`](Self::validate_file)'s diagnostics as a JSON array
+    /// of `{file, line, col, severity, code, message}` objects, with `file`
+    /// relative to the workspace root, for CI to turn into annotations.
+    #[cfg(feature = "file")]
+    pub fn diagnostics_json(&self, file_path: &std::path::Path) -> crate::Result<String> {
+        let root = self.workspace.read().root().to_path_buf();
+        let entries: Vec<serde_json::Value> = self
+            .validate_file(file_path)?
+            .iter()
+            .map(|d| {
+                serde_json::json!({
+                    "file": d.path.strip_prefix(&root).unwrap_or(&d.path).to_string_lossy(),
+                    "line": d.line,
+                    "col": d.column,
+                    "severity": d.severity.as_str(),
+                    "code": d.code.to_string(),
+                    "message": d.message,
+                })
+            })
+            .collect();
+        Ok(serde_json::Value::Array(entries).to_string())
+    }
+
+    /// Resolves every variable visible from `file_path` into the resolution
+    /// cache so subsequent `get_for_file` calls for that file are cache hits.
+    /// Returns the number of variables cached.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn warm_cache(&self, file_path: &std::path::Path) -> crate::Result<usize> {
+        let context = self.context_for_file(file_path)?;
+
+        let active_files = self.active_env_files(file_path);
+        let file_source_ids = self.get_source_ids_for_paths(&active_files);
+        self.resolution
+            .warm_with_filter(&context, &self.registry, Some(&file_source_ids))
+            .await
+    }
+
     #[cfg_attr(feature = "async", must_be_async)]
     #[cfg_attr(not(feature = "async"), must_be_sync)]
     pub async fn get_in_context(
@@ -215,24 +706,138 @@ impl Abundantis {
         &self,
         file_path: &std::path::Path,
     ) -> crate::Result<Vec<Arc<ResolvedVariable>>> {
-        let context = {
-            let workspace = self.workspace.read();
-            workspace
-                .context_for_file(file_path)
-                .ok_or_else(|| AbundantisError::Config {
-                    message: format!(
-                        "No workspace context found for file: {}",
-                        file_path.display()
-                    ),
-                    path: Some(file_path.to_path_buf()),
-                })?
-        };
+        let context = self.context_for_file(file_path)?;
 
         let active_files = self.active_env_files(file_path);
         self.all_in_context_with_filter(&context, &active_files)
             .await
     }
 
+    /// Renders the template at `template_path` into `output_path`, replacing
+    /// each `${KEY}` with the value `KEY` resolves to for `file_path`.
+    /// Undefined keys follow `resolution.on_undefined`.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn resolve_template_file(
+        &self,
+        template_path: &std::path::Path,
+        output_path: &std::path::Path,
+        file_path: &std::path::Path,
+    ) -> crate::Result<()> {
+        let template = std::fs::read_to_string(template_path)?;
+        let variables = self.all_for_file(file_path).await?;
+        let rendered = self.resolution.render_template(&template, &variables)?;
+        std::fs::write(output_path, rendered)?;
+        Ok(())
+    }
+
+    /// The references between the variables active for `file_path` as a
+    /// Graphviz DOT digraph, with edges on a cycle drawn in red.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn graph_dot(&self, file_path: &std::path::Path) -> crate::Result<String> {
+        let mut variables = self.all_for_file(file_path).await?;
+        variables.sort_by(|a, b| a.key.cmp(&b.key));
+
+        let mut graph = DependencyGraph::new();
+        for variable in &variables {
+            for reference in germi::find_variable_references(&variable.raw_value) {
+                graph.add_edge(variable.key.clone(), reference.into(), None);
+            }
+        }
+        Ok(graph.to_dot())
+    }
+
+    /// Every variable active for `file_path` as `shell` export statements,
+    /// one per line, ready to be `eval`ed.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn export_for_shell(
+        &self,
+        file_path: &std::path::Path,
+        shell: ShellKind,
+    ) -> crate::Result<String> {
+        let variables = self.all_for_file(file_path).await?;
+        Ok(export::format_exports(&variables, shell))
+    }
+
+    /// Resolves every variable visible to the package called `name` (or at
+    /// that workspace-relative path), as seen from the package root. In a
+    /// monorepo this includes the root env files cascaded into the package.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn all_for_package(&self, name: &str) -> crate::Result<Vec<Arc<ResolvedVariable>>> {
+        let package_root = self.package_root(name)?;
+        self.all_for_file(&package_root).await
+    }
+
+    /// Resolves every package in the workspace, keyed by package name (or
+    /// workspace-relative path for unnamed packages). With the `parallel`
+    /// feature the packages are resolved concurrently.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn resolve_all_packages(
+        &self,
+    ) -> crate::Result<HashMap<CompactString, Vec<Arc<ResolvedVariable>>>> {
+        let packages = self.workspace.read().packages();
+
+        #[cfg(all(feature = "parallel", feature = "async"))]
+        let resolved = futures::future::join_all(
+            packages
+                .iter()
+                .map(|package| self.all_for_file(&package.root)),
+        )
+        .await;
+
+        #[cfg(all(feature = "parallel", not(feature = "async")))]
+        let resolved: Vec<_> = std::thread::scope(|scope| {
+            let handles: Vec<_> = packages
+                .iter()
+                .map(|package| scope.spawn(|| self.all_for_file(&package.root)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    handle
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect()
+        });
+
+        #[cfg(not(feature = "parallel"))]
+        let resolved = {
+            let mut resolved = Vec::with_capacity(packages.len());
+            for package in &packages {
+                resolved.push(self.all_for_file(&package.root).await);
+            }
+            resolved
+        };
+
+        packages
+            .into_iter()
+            .zip(resolved)
+            .map(|(package, variables)| {
+                Ok((package.name.unwrap_or(package.relative_path), variables?))
+            })
+            .collect()
+    }
+
+    /// Resolves everything visible from `file_path` into an immutable
+    /// [`EnvSnapshot`] for lock-free lookups on hot paths.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn snapshot(&self, file_path: &std::path::Path) -> crate::Result<EnvSnapshot> {
+        let context = self.context_for_file(file_path)?;
+
+        let active_files = self.active_env_files(file_path);
+        let variables = self
+            .all_in_context_with_filter(&context, &active_files)
+            .await?;
+
+        Ok(EnvSnapshot::new(variables, context))
+    }
+
     #[cfg_attr(feature = "async", must_be_async)]
     #[cfg_attr(not(feature = "async"), must_be_sync)]
     pub async fn all_in_context(
@@ -270,21 +875,43 @@ impl Abundantis {
             .await
     }
 
+    /// Drops cached resolutions and parsed env files without rediscovering
+    /// sources, e.g. after env files were edited outside this crate. Publishes
+    /// `CacheInvalidated` for the whole workspace.
+    pub fn clear_cache(&self) {
+        let paths = self.registry.registered_file_paths();
+        for source in self.registry.sources_for_paths(&paths) {
+            source.invalidate();
+        }
+        self.cache.clear();
+        self.path_to_source_id.write().clear();
+
+        self.event_bus
+            .publish(events::AbundantisEvent::CacheInvalidated { scope: None });
+    }
+
+    /// Reloads every source and rediscovers env files, reporting which file
+    /// sources appeared or disappeared and how many cached values were dropped.
     #[cfg(feature = "async")]
-    pub async fn refresh(&self, options: RefreshOptions) -> Result<()> {
-        self.refresh_inner(&options)?;
+    pub async fn refresh(&self, options: RefreshOptions) -> Result<RefreshReport> {
+        let report = self.refresh_inner(&options)?;
         self.event_bus
             .publish_async(events::AbundantisEvent::CacheInvalidated { scope: None })
             .await;
-        Ok(())
+        Ok(report)
     }
 
+    /// Reloads every source and rediscovers env files, reporting which file
+    /// sources appeared or disappeared and how many cached values were dropped.
     #[cfg(not(feature = "async"))]
-    pub fn refresh(&self, options: RefreshOptions) -> Result<()> {
+    pub fn refresh(&self, options: RefreshOptions) -> Result<RefreshReport> {
         self.refresh_inner(&options)
     }
 
-    fn refresh_inner(&self, options: &RefreshOptions) -> Result<()> {
+    fn refresh_inner(&self, options: &RefreshOptions) -> Result<RefreshReport> {
+        let cached_entries = self.cache.key_count();
+        #[cfg(feature = "file")]
+        self.resolution.reload_schema_if_changed()?;
         let file_config_backup = if options.preserve_file_config {
             let current_global = self.global_active_files.read().clone();
 
@@ -322,36 +949,137 @@ impl Abundantis {
             source.refresh(&source_options);
         }
 
-        {
-            let workspace = self.workspace.write();
-            workspace.refresh()?;
-        }
-
-        self.rediscover_file_sources()?;
+        let rediscovered = self.rediscover_workspace();
 
         if let Some((global, directory)) = file_config_backup {
             *self.global_active_files.write() = global;
             *self.directory_active_files.write() = directory;
         }
 
+        let mut report = rediscovered?;
+        report.cache_entries_cleared = cached_entries;
         self.cache.clear();
         self.path_to_source_id.write().clear();
 
-        Ok(())
+        Ok(report)
     }
 
     pub fn event_bus(&self) -> &events::EventBus {
         &self.event_bus
     }
 
+    /// Returns a channel receiving every event published from now on, without
+    /// implementing [`EventSubscriber`]. Holds up to 256 unread events; later
+    /// ones are dropped until the receiver catches up. Dropping the receiver
+    /// unsubscribes it on the next published event.
+    #[cfg(feature = "async")]
+    pub fn on_change(&self) -> tokio::sync::mpsc::Receiver<AbundantisEvent> {
+        let (tx, rx) = tokio::sync::mpsc::channel(256);
+        self.event_bus
+            .subscribe(Arc::new(events::ChannelForwarder(tx)));
+        rx
+    }
+
+    /// Streams what changes in the variables visible from `file_path` as the
+    /// watched env files are edited. Each item is the difference from the
+    /// previously yielded state, starting from the values resolved when the
+    /// stream is first polled; changes that leave the values as they were
+    /// yield nothing.
+    #[cfg(all(feature = "watch", feature = "async"))]
+    pub fn watch_and_stream(
+        &self,
+        file_path: &std::path::Path,
+    ) -> impl futures::Stream<Item = EnvDiff> + '_ {
+        let events = self.on_change();
+        let file_path = file_path.to_path_buf();
+
+        futures::stream::unfold(
+            (events, None::<EnvSnapshot>),
+            move |(mut events, previous)| {
+                let file_path = file_path.clone();
+                async move {
+                    let mut previous = match previous {
+                        Some(previous) => previous,
+                        None => self.snapshot(&file_path).await.ok()?,
+                    };
+
+                    loop {
+                        let event = events.recv().await?;
+                        if !matches!(
+                            event,
+                            AbundantisEvent::VariablesChanged { .. }
+                                | AbundantisEvent::CacheInvalidated { .. }
+                                | AbundantisEvent::SourcesRediscovered { .. }
+                        ) {
+                            continue;
+                        }
+                        // A single edit publishes several events; resolve once.
+                        while events.try_recv().is_ok() {}
+
+                        let current = match self.snapshot(&file_path).await {
+                            Ok(current) => current,
+                            Err(e) => {
+                                tracing::warn!(
+                                    "Failed to re-resolve {}: {}",
+                                    file_path.display(),
+                                    e
+                                );
+                                continue;
+                            }
+                        };
+                        let diff = previous.diff(&current);
+                        previous = current;
+                        if !diff.is_empty() {
+                            return Some((diff, (events, Some(previous))));
+                        }
+                    }
+                }
+            },
+        )
+    }
+
     pub fn config(&self) -> &AbundantisConfig {
         &self.config
     }
 
     pub fn stats(&self) -> AbundantisStats {
+        let cache_stats = self.cache.stats();
         AbundantisStats {
             cached_variables: self.cache.len(),
             source_count: self.registry.source_count(),
+            cache_hits: cache_stats.hits,
+            cache_misses: cache_stats.misses,
+        }
+    }
+
+    /// Counters for the resolution cache and the path canonicalization
+    /// cache in one report.
+    pub fn cache_stats(&self) -> CacheReport {
+        CacheReport {
+            resolution: self.cache.stats(),
+            path: self.path_cache.stats(),
+        }
+    }
+
+    /// How long each key took to resolve in the latest uncached resolution,
+    /// with `resolution.profile` enabled. Useful for finding the variable
+    /// whose interpolation dominates load time.
+    pub fn last_profile(&self) -> Vec<(CompactString, std::time::Duration)> {
+        self.resolution.last_profile()
+    }
+
+    /// Refresh time and error count for a registered source, if any.
+    pub fn source_metadata(&self, id: &source::SourceId) -> Option<source::SourceMetadata> {
+        self.registry.metadata(id)
+    }
+
+    /// Layers `variables` over every source until the returned guard is
+    /// dropped, without touching any files. Nested calls stack, the latest
+    /// winning. Useful for tests and request-scoped overrides.
+    pub fn with_overrides(&self, variables: &[(&str, &str)]) -> OverrideGuard {
+        OverrideGuard {
+            resolution: Arc::clone(&self.resolution),
+            layer: self.resolution.push_override_layer(variables),
         }
     }
 
@@ -390,6 +1118,23 @@ impl Abundantis {
         )
     }
 
+    /// The innermost workspace package containing `file_path`, without
+    /// building its full context. `None` for files outside the workspace.
+    pub fn package_for_file(&self, file_path: &std::path::Path) -> Option<PackageInfo> {
+        self.reload_if_config_changed();
+        self.workspace.read().package_for_file(file_path)
+    }
+
+    /// Env files active for the package called `name` (or at that
+    /// workspace-relative path), as seen from its root. Empty when there is
+    /// no such package.
+    pub fn active_files_for_package(&self, name: &str) -> Vec<PathBuf> {
+        match self.package_root(name) {
+            Ok(root) => self.active_env_files(root),
+            Err(_) => Vec::new(),
+        }
+    }
+
     pub fn clear_active_files(&self) {
         *self.global_active_files.write() = None;
         self.path_to_source_id.write().clear();
@@ -444,13 +1189,14 @@ impl Abundantis {
         }
 
         let new_workspace = workspace::WorkspaceManager::with_root(new_root, &workspace_config)?;
+        let discovered = self.discover_env_files(&new_workspace)?;
 
         {
             let mut workspace = self.workspace.write();
             *workspace = new_workspace;
         }
 
-        self.rediscover_file_sources()?;
+        self.sync_file_sources(discovered);
 
         self.cache.clear();
         self.path_to_source_id.write().clear();
@@ -458,6 +1204,63 @@ impl Abundantis {
         Ok(())
     }
 
+    fn package_root(&self, name: &str) -> Result<PathBuf> {
+        self.workspace
+            .read()
+            .packages()
+            .into_iter()
+            .find(|package| package.name.as_deref() == Some(name) || package.relative_path == name)
+            .map(|package| package.root)
+            .ok_or_else(|| AbundantisError::Config {
+                message: format!("No package named `{}` in the workspace", name),
+                path: None,
+            })
+    }
+
+    fn context_for_file(&self, file_path: &Path) -> Result<workspace::WorkspaceContext> {
+        self.reload_if_config_changed();
+        let workspace = self.workspace.read();
+        workspace
+            .context_for_file(file_path)
+            .ok_or_else(|| AbundantisError::Config {
+                message: format!(
+                    "No workspace context found for file: {}",
+                    file_path.display()
+                ),
+                path: Some(file_path.to_path_buf()),
+            })
+    }
+
+    /// Reloads the schema file once it changed, and rediscovers packages and
+    /// their env files, as [`refresh`](Self::refresh) does, once the
+    /// workspace provider's config files changed (a package added to
+    /// `pnpm-workspace.yaml`, ...). They're checked at most once per
+    /// [`CONFIG_CHECK_INTERVAL`].
+    fn reload_if_config_changed(&self) {
+        {
+            let mut last = self.last_config_check.lock();
+            if last.elapsed() < CONFIG_CHECK_INTERVAL {
+                return;
+            }
+            *last = std::time::Instant::now();
+        }
+        #[cfg(feature = "file")]
+        if let Err(e) = self.resolution.reload_schema_if_changed() {
+            tracing::warn!("Failed to reload the schema file: {}", e);
+        }
+        if !self.workspace.read().needs_rediscovery() {
+            return;
+        }
+
+        tracing::info!("Workspace config changed, rediscovering packages");
+        if let Err(e) = self.rediscover_workspace() {
+            tracing::warn!("Failed to rediscover the workspace: {}", e);
+            return;
+        }
+        self.cache.clear();
+        self.path_to_source_id.write().clear();
+    }
+
     fn get_source_ids_for_paths(
         &self,
         paths: &[PathBuf],
@@ -473,7 +1276,8 @@ impl Abundantis {
                 continue;
             }
 
-            let source_id = source::SourceId::from(format!("file:{}", canonical.display()));
+            let source_id =
+                source::SourceId::canonical(source::SourceType::File, canonical.display());
             cache.insert(canonical, source_id.clone());
             result.insert(source_id);
         }
@@ -481,66 +1285,226 @@ impl Abundantis {
         result
     }
 
-    #[cfg(feature = "file")]
-    fn rediscover_file_sources(&self) -> Result<()> {
-        use std::collections::HashSet;
+    /// Rediscovers packages and their env files. Nothing changes if either
+    /// fails, e.g. on going over `sources.defaults.max_sources`.
+    fn rediscover_workspace(&self) -> Result<RefreshReport> {
+        let refreshed = self.workspace.read().rediscovered()?;
+        let discovered = self.discover_env_files(&refreshed)?;
 
-        let workspace = self.workspace.read();
-        let mut discovered_paths: HashSet<PathBuf> = HashSet::new();
+        self.resolution.set_packages(&refreshed.packages());
+        *self.workspace.write() = refreshed;
+        Ok(self.sync_file_sources(discovered))
+    }
 
-        for package in workspace.packages() {
-            for pattern in &self.config.workspace.env_files {
-                let full_pattern = package.root.join(pattern.as_str());
+    /// The env files `workspace` registers as sources.
+    #[cfg(feature = "file")]
+    fn discover_env_files(
+        &self,
+        workspace: &workspace::WorkspaceManager,
+    ) -> Result<std::collections::HashSet<PathBuf>> {
+        let mut discovered_paths = std::collections::HashSet::new();
+        let ignores = self.config.workspace.ignore_set();
+
+        for dir in core::env_file_dirs(workspace) {
+            for pattern in &self.config.env_file_patterns() {
+                let full_pattern = dir.join(pattern.as_str());
                 let pattern_str = full_pattern.to_string_lossy();
 
                 if let Ok(paths) = glob::glob(&pattern_str) {
                     for entry in paths.flatten() {
-                        if entry.is_file() {
+                        if entry.is_file() && !core::is_ignored(&ignores, workspace.root(), &entry)
+                        {
                             if let Ok(canonical) = entry.canonicalize() {
                                 discovered_paths.insert(canonical);
                             } else {
                                 discovered_paths.insert(entry);
                             }
+                            core::check_max_sources(
+                                &self.config,
+                                discovered_paths.len(),
+                                &pattern_str,
+                            )?;
                         }
                     }
                 }
             }
         }
 
+        Ok(discovered_paths)
+    }
+
+    /// Registers the env files in `discovered_paths` that aren't yet and
+    /// unregisters those deleted since.
+    #[cfg(feature = "file")]
+    fn sync_file_sources(
+        &self,
+        discovered_paths: std::collections::HashSet<PathBuf>,
+    ) -> RefreshReport {
+        let mut added = Vec::new();
         for path in &discovered_paths {
-            let source_id = source::SourceId::from(format!("file:{}", path.display()));
+            let source_id = source::SourceId::canonical(source::SourceType::File, path.display());
             if !self.registry.is_registered(&source_id) {
                 if let Ok(file_source) = source::FileSource::new(path) {
                     tracing::info!("Discovered new env file: {}", path.display());
-                    self.registry
-                        .register_sync(Arc::new(file_source) as Arc<dyn source::EnvSource>);
+                    let file_source = Arc::new(file_source);
+                    #[cfg(all(feature = "watch", feature = "async"))]
+                    if let Some(manager) = &*self.watch_manager {
+                        manager.watch_file(Arc::clone(&file_source));
+                    }
+                    added.push(
+                        self.registry
+                            .register_sync(file_source as Arc<dyn source::EnvSource>),
+                    );
                 }
             }
         }
 
+        let mut removed = Vec::new();
         let registered_paths = self.registry.registered_file_paths();
         for registered_path in registered_paths {
             if !discovered_paths.contains(&registered_path) && !registered_path.exists() {
-                let source_id =
-                    source::SourceId::from(format!("file:{}", registered_path.display()));
+                let source_id = source::SourceId::canonical(
+                    source::SourceType::File,
+                    registered_path.display(),
+                );
                 tracing::info!("Removing deleted env file: {}", registered_path.display());
+                #[cfg(all(feature = "watch", feature = "async"))]
+                if let Some(manager) = &*self.watch_manager {
+                    manager.unwatch_file(&registered_path);
+                }
                 self.registry.unregister_sync(&source_id);
+                removed.push(source_id);
             }
         }
 
-        Ok(())
+        added.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        removed.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        if !added.is_empty() || !removed.is_empty() {
+            self.event_bus
+                .publish(events::AbundantisEvent::SourcesRediscovered {
+                    added: added.clone(),
+                    removed: removed.clone(),
+                });
+        }
+
+        RefreshReport {
+            sources_added: added,
+            sources_removed: removed,
+            cache_entries_cleared: 0,
+        }
     }
 
     #[cfg(not(feature = "file"))]
-    fn rediscover_file_sources(&self) -> Result<()> {
-        Ok(())
+    fn discover_env_files(
+        &self,
+        _workspace: &workspace::WorkspaceManager,
+    ) -> Result<std::collections::HashSet<PathBuf>> {
+        Ok(std::collections::HashSet::new())
+    }
+
+    #[cfg(not(feature = "file"))]
+    fn sync_file_sources(
+        &self,
+        _discovered_paths: std::collections::HashSet<PathBuf>,
+    ) -> RefreshReport {
+        RefreshReport::default()
     }
 }
 
+/// Line, column and source line of `variable`'s definition in the env file
+/// at `path`.
+#[cfg(feature = "file")]
+fn variable_location(path: &Path, variable: &source::ParsedVariable) -> (u32, u32, Option<String>) {
+    let content = std::fs::read_to_string(path).unwrap_or_default();
+    let offset = match variable.source {
+        source::VariableSource::File { offset, .. } => offset,
+        _ => 0,
+    };
+    let (line, column) = source::parse::line_col(&content, offset);
+    let snippet = content.lines().nth(line as usize - 1).map(str::to_string);
+    (line, column, snippet)
+}
+
+/// Builds the `WS002` diagnostic for `variable`, which disagrees with the
+/// definition `first` from another file in the same directory.
+#[cfg(feature = "file")]
+fn conflict_diagnostic(
+    first_path: &Path,
+    first: &source::ParsedVariable,
+    path: &Path,
+    variable: &source::ParsedVariable,
+) -> Diagnostic {
+    let (first_line, _, _) = variable_location(first_path, first);
+    let (line, column, snippet) = variable_location(path, variable);
+    let first_name = first_path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    Diagnostic {
+        severity: DiagnosticSeverity::Warning,
+        code: DiagnosticCode::WS002,
+        message: format!(
+            "`{}` is `{}` here but `{}` in {}",
+            variable.key, variable.raw_value, first.raw_value, first_name
+        ),
+        path: path.to_path_buf(),
+        line,
+        column,
+        snippet,
+        label: Some(format!("conflicts with {}:{}", first_name, first_line)),
+    }
+}
+
+/// What a call to [`Abundantis::refresh`] changed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RefreshReport {
+    /// Env files discovered since the last refresh, sorted by id.
+    pub sources_added: Vec<source::SourceId>,
+    /// Env files that no longer exist, sorted by id.
+    pub sources_removed: Vec<source::SourceId>,
+    pub cache_entries_cleared: usize,
+}
+
 #[derive(Debug, Clone)]
 pub struct AbundantisStats {
     pub cached_variables: usize,
     pub source_count: usize,
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+}
+
+/// A variable as reported by [`Abundantis::describe`].
+#[derive(Debug, Clone)]
+pub struct VariableInfo {
+    pub key: CompactString,
+    pub resolved_value: CompactString,
+    pub raw_value: CompactString,
+    /// From the comment above the definition, or else the schema.
+    pub description: Option<CompactString>,
+    pub source: VariableSource,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Counters of both caches, from [`Abundantis::cache_stats`].
+#[derive(Debug, Clone)]
+pub struct CacheReport {
+    pub resolution: ResolutionCacheStats,
+    pub path: path_cache::CacheStats,
+}
+
+/// Keeps an override layer from [`Abundantis::with_overrides`] in place;
+/// dropping it removes the layer and clears the resolution cache.
+#[must_use = "the overrides are removed as soon as the guard is dropped"]
+pub struct OverrideGuard {
+    resolution: Arc<resolution::ResolutionEngine>,
+    layer: u64,
+}
+
+impl Drop for OverrideGuard {
+    fn drop(&mut self) {
+        self.resolution.remove_override_layer(self.layer);
+    }
 }
 
 mod core;
@@ -15,9 +15,31 @@ pub struct PathCache {
 
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct CacheStats {
-    hits: usize,
-    misses: usize,
-    errors: usize,
+    pub hits: usize,
+    pub misses: usize,
+    /// Misses whose path couldn't be canonicalized and is cached as given.
+    pub errors: usize,
+}
+
+impl CacheStats {
+    /// Fraction of lookups served from the cache, or 0 before any lookup.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / total as f64
+    }
+
+    /// Fraction of uncached lookups whose path couldn't be canonicalized, or
+    /// 0 before any miss. A high rate usually means something keeps asking
+    /// for paths that don't exist.
+    pub fn error_rate(&self) -> f64 {
+        if self.misses == 0 {
+            return 0.0;
+        }
+        self.errors as f64 / self.misses as f64
+    }
 }
 
 impl PathCache {
@@ -99,13 +121,18 @@ impl PathCache {
         self.resolved.is_empty() && self.fallback.read().is_empty()
     }
 
+    /// Entries for paths that couldn't be canonicalized, included in
+    /// [`PathCache::len`]. They stay cached until invalidated or cleared.
+    pub fn fallback_len(&self) -> usize {
+        self.fallback.read().len()
+    }
+
     pub fn hit_rate(&self) -> f64 {
-        let stats = self.stats.read();
-        let total = stats.hits + stats.misses;
-        if total == 0 {
-            return 0.0;
-        }
-        stats.hits as f64 / total as f64
+        self.stats.read().hit_rate()
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        self.stats.read().error_rate()
     }
 }
 
@@ -184,4 +211,24 @@ mod tests {
         let stats = cache.stats();
         assert_eq!(stats.errors, 1);
     }
+
+    #[test]
+    fn test_error_rate_and_fallback_len() {
+        let cache = PathCache::new();
+        assert_eq!(cache.error_rate(), 0.0);
+
+        cache.canonicalize(&PathBuf::from("."));
+        for i in 0..3 {
+            cache.canonicalize(&PathBuf::from(format!("/nonexistent/abundantis/{}", i)));
+        }
+        cache.canonicalize(&PathBuf::from("/nonexistent/abundantis/0"));
+
+        assert_eq!(cache.fallback_len(), 3);
+        assert_eq!(cache.len(), 4);
+        assert_eq!(cache.stats().errors, 3);
+        assert_eq!(cache.error_rate(), 0.75);
+
+        cache.invalidate(&PathBuf::from("/nonexistent/abundantis/1"));
+        assert_eq!(cache.fallback_len(), 2);
+    }
 }
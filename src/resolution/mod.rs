@@ -19,6 +19,9 @@ use maybe_async::must_be_sync;
 pub struct CacheKey {
     pub key: CompactString,
     pub context_hash: u64,
+    /// Fingerprint of the active-file filter the value was resolved under;
+    /// 0 when unfiltered.
+    pub filter_hash: u64,
 }
 
 impl CacheKey {
@@ -26,14 +29,51 @@ impl CacheKey {
         Self {
             key: key.into(),
             context_hash,
+            filter_hash: 0,
         }
     }
+
+    /// Scopes the key to `file_source_filter`, so values resolved under
+    /// different filters don't share a cache entry.
+    pub fn with_filter(
+        mut self,
+        file_source_filter: Option<&HashSet<crate::source::SourceId>>,
+    ) -> Self {
+        self.filter_hash = hash_filter(file_source_filter);
+        self
+    }
+}
+
+/// Order-independent fingerprint of a source filter. `None` hashes to 0 so
+/// unfiltered lookups share entries with [`CacheKey::new`].
+fn hash_filter(file_source_filter: Option<&HashSet<crate::source::SourceId>>) -> u64 {
+    use ahash::AHasher;
+    use std::hash::{Hash, Hasher};
+
+    let Some(filter) = file_source_filter else {
+        return 0;
+    };
+    let mut ids: Vec<&str> = filter.iter().map(|id| id.as_str()).collect();
+    ids.sort_unstable();
+
+    let mut hasher = AHasher::default();
+    "filter".hash(&mut hasher);
+    ids.hash(&mut hasher);
+    hasher.finish()
 }
 
 #[derive(Debug, Clone)]
 pub struct CachedValue {
     pub value: Arc<ResolvedVariable>,
     pub cached_at: Instant,
+    /// Overrides the cache-wide TTL for this entry.
+    pub ttl: Option<Duration>,
+}
+
+impl CachedValue {
+    fn is_fresh(&self, now: Instant, default_ttl: Duration) -> bool {
+        now.duration_since(self.cached_at) < self.ttl.unwrap_or(default_ttl)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -44,10 +84,167 @@ pub struct ResolvedVariable {
     pub source: super::source::VariableSource,
     pub description: Option<CompactString>,
     pub has_warnings: bool,
+    /// Longest chain of substitutions behind `resolved_value`: 0 for a
+    /// literal, 1 when it only references literals, and so on.
     pub interpolation_depth: u32,
 }
 
+impl ResolvedVariable {
+    /// Whether interpolation changed the value.
+    pub fn is_interpolated(&self) -> bool {
+        self.raw_value != self.resolved_value
+    }
+
+    /// Parses the resolved value with `T`'s `FromStr` implementation.
+    pub fn parse<T>(&self) -> Result<T>
+    where
+        T: std::str::FromStr,
+        T::Err: std::fmt::Display,
+    {
+        self.resolved_value
+            .trim()
+            .parse()
+            .map_err(|e: T::Err| self.invalid(e.to_string()))
+    }
+
+    /// Reads `true`/`false`, `1`/`0`, `yes`/`no` or `on`/`off`, ignoring case.
+    pub fn as_bool(&self) -> Result<bool> {
+        match self.resolved_value.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => Ok(true),
+            "false" | "0" | "no" | "off" => Ok(false),
+            other => Err(self.invalid(format!("`{}` is not a boolean", other))),
+        }
+    }
+
+    /// Reads a human-friendly duration such as `30s`, `5m` or `1h 30m`.
+    pub fn as_duration(&self) -> Result<Duration> {
+        humantime::parse_duration(self.resolved_value.trim())
+            .map_err(|e| self.invalid(e.to_string()))
+    }
+
+    /// Splits the value on `separator`, trimming each item and dropping empty
+    /// ones, so `a, b,` yields `["a", "b"]`.
+    pub fn as_list(&self, separator: &str) -> Vec<String> {
+        self.resolved_value
+            .split(separator)
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    fn invalid(&self, reason: String) -> AbundantisError {
+        AbundantisError::InvalidValue {
+            key: self.key.to_string(),
+            reason,
+        }
+    }
+}
+
+/// Point-in-time copy of every variable resolved for a file.
+///
+/// Lookups are plain map reads: no locks are taken and later changes to the
+/// underlying sources are not reflected. Cloning is cheap.
 #[derive(Debug, Clone)]
+pub struct EnvSnapshot {
+    variables: Arc<HashMap<CompactString, Arc<ResolvedVariable>>>,
+    context: Arc<super::workspace::WorkspaceContext>,
+    created_at: Instant,
+}
+
+impl EnvSnapshot {
+    pub fn new(
+        variables: impl IntoIterator<Item = Arc<ResolvedVariable>>,
+        context: super::workspace::WorkspaceContext,
+    ) -> Self {
+        Self {
+            variables: Arc::new(variables.into_iter().map(|v| (v.key.clone(), v)).collect()),
+            context: Arc::new(context),
+            created_at: Instant::now(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Arc<ResolvedVariable>> {
+        self.variables.get(key)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.variables.contains_key(key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<ResolvedVariable>> {
+        self.variables.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.variables.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.variables.is_empty()
+    }
+
+    pub fn context(&self) -> &super::workspace::WorkspaceContext {
+        &self.context
+    }
+
+    pub fn created_at(&self) -> Instant {
+        self.created_at
+    }
+
+    /// What changed between this snapshot and a `newer` one, by key.
+    pub fn diff(&self, newer: &EnvSnapshot) -> EnvDiff {
+        let mut diff = EnvDiff::default();
+        for (key, before) in self.variables.iter() {
+            match newer.variables.get(key) {
+                None => diff.removed.push(Arc::clone(before)),
+                Some(after)
+                    if after.resolved_value != before.resolved_value
+                        || after.source != before.source =>
+                {
+                    diff.changed.push((Arc::clone(before), Arc::clone(after)));
+                }
+                Some(_) => {}
+            }
+        }
+        for (key, after) in newer.variables.iter() {
+            if !self.variables.contains_key(key) {
+                diff.added.push(Arc::clone(after));
+            }
+        }
+
+        diff.added.sort_by(|a, b| a.key.cmp(&b.key));
+        diff.removed.sort_by(|a, b| a.key.cmp(&b.key));
+        diff.changed.sort_by(|a, b| a.0.key.cmp(&b.0.key));
+        diff
+    }
+}
+
+/// Differences between two [`EnvSnapshot`]s, each list sorted by key.
+#[derive(Debug, Clone, Default)]
+pub struct EnvDiff {
+    pub added: Vec<Arc<ResolvedVariable>>,
+    pub removed: Vec<Arc<ResolvedVariable>>,
+    /// Keys whose value or source changed, as `(before, after)`.
+    pub changed: Vec<(Arc<ResolvedVariable>, Arc<ResolvedVariable>)>,
+}
+
+impl EnvDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Every key the diff touches.
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.added
+            .iter()
+            .chain(self.removed.iter())
+            .chain(self.changed.iter().map(|(before, _)| before))
+            .map(|variable| variable.key.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DependencyEdge {
     pub from: CompactString,
     pub to: CompactString,
@@ -58,6 +255,8 @@ pub struct DependencyEdge {
 pub struct DependencyGraph {
     edges: Vec<DependencyEdge>,
     nodes: HashMap<CompactString, Vec<DependencyEdge>>,
+    /// Edges added through `replace_source_edges`, by contributing source.
+    by_source: HashMap<crate::source::SourceId, Vec<DependencyEdge>>,
 }
 
 impl DependencyGraph {
@@ -65,6 +264,42 @@ impl DependencyGraph {
         Self {
             edges: Vec::new(),
             nodes: HashMap::new(),
+            by_source: HashMap::new(),
+        }
+    }
+
+    /// Swaps the edges contributed by `source` for `edges`, leaving every
+    /// other source's edges in place.
+    pub fn replace_source_edges(
+        &mut self,
+        source: &crate::source::SourceId,
+        edges: Vec<DependencyEdge>,
+    ) {
+        self.remove_source(source);
+        for edge in &edges {
+            self.add_edge(edge.from.clone(), edge.to.clone(), edge.span);
+        }
+        self.by_source.insert(source.clone(), edges);
+    }
+
+    /// Drops the edges contributed by `source`.
+    pub fn remove_source(&mut self, source: &crate::source::SourceId) {
+        let Some(removed) = self.by_source.remove(source) else {
+            return;
+        };
+
+        for edge in &removed {
+            if let Some(edges) = self.nodes.get_mut(&edge.from) {
+                if let Some(index) = edges.iter().position(|e| e == edge) {
+                    edges.remove(index);
+                }
+                if edges.is_empty() {
+                    self.nodes.remove(&edge.from);
+                }
+            }
+            if let Some(index) = self.edges.iter().position(|e| e == edge) {
+                self.edges.remove(index);
+            }
         }
     }
 
@@ -139,6 +374,52 @@ impl DependencyGraph {
     pub fn clear(&mut self) {
         self.edges.clear();
         self.nodes.clear();
+        self.by_source.clear();
+    }
+
+    /// Renders the graph as Graphviz DOT, one `"FROM" -> "TO";` line per
+    /// distinct edge. Edges that lie on a cycle are drawn in red.
+    pub fn to_dot(&self) -> String {
+        let quote = |key: &str| format!("\"{}\"", key.replace('\\', "\\\\").replace('"', "\\\""));
+
+        let mut dot = String::from("digraph env {\n");
+        let mut seen = HashSet::new();
+        for edge in &self.edges {
+            if !seen.insert((&edge.from, &edge.to)) {
+                continue;
+            }
+            let style = if self.reaches(&edge.to, &edge.from) {
+                " [color=red]"
+            } else {
+                ""
+            };
+            dot.push_str(&format!(
+                "    {} -> {}{};\n",
+                quote(&edge.from),
+                quote(&edge.to),
+                style
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Whether `target` can be reached from `start` by following edges.
+    fn reaches(&self, start: &str, target: &str) -> bool {
+        let mut stack = vec![start];
+        let mut visited = HashSet::new();
+        while let Some(current) = stack.pop() {
+            if current == target {
+                return true;
+            }
+            if !visited.insert(current) {
+                continue;
+            }
+            if let Some(edges) = self.nodes.get(current) {
+                stack.extend(edges.iter().map(|edge| edge.to.as_str()));
+            }
+        }
+        false
     }
 }
 
@@ -148,11 +429,32 @@ impl Default for DependencyGraph {
     }
 }
 
+/// Lookup counters for a [`ResolutionCache`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ResolutionCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl ResolutionCacheStats {
+    /// Fraction of lookups served from the cache, or 0 before any lookup.
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / total as f64
+    }
+}
+
 pub struct ResolutionCache {
     hot_cache: Arc<RwLock<LruCache<CacheKey, CachedValue>>>,
     ttl_cache: Arc<DashMap<CacheKey, CachedValue>>,
     ttl: Duration,
+    remote_ttl: Option<Duration>,
     enabled: bool,
+    hits: AtomicU64,
+    misses: AtomicU64,
 }
 
 impl ResolutionCache {
@@ -164,7 +466,10 @@ impl ResolutionCache {
             hot_cache: Arc::new(RwLock::new(LruCache::new(hot_size))),
             ttl_cache: Arc::new(DashMap::new()),
             ttl: config.ttl,
+            remote_ttl: config.remote_ttl,
             enabled: config.enabled,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
         }
     }
 
@@ -177,25 +482,43 @@ impl ResolutionCache {
         let ttl = self.ttl;
 
         if let Some(cached) = self.ttl_cache.get(key) {
-            if now.duration_since(cached.cached_at) < ttl {
+            if cached.is_fresh(now, ttl) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
                 return Some(Arc::clone(&cached.value));
             }
         }
 
         self.ttl_cache
-            .remove_if(key, |_, cached| now.duration_since(cached.cached_at) >= ttl);
+            .remove_if(key, |_, cached| !cached.is_fresh(now, ttl));
 
         let mut hot = self.hot_cache.write();
         if let Some(cached) = hot.get(key) {
-            if now.duration_since(cached.cached_at) < ttl {
+            if cached.is_fresh(now, ttl) {
+                self.hits.fetch_add(1, Ordering::Relaxed);
                 return Some(Arc::clone(&cached.value));
             }
         }
 
+        self.misses.fetch_add(1, Ordering::Relaxed);
         None
     }
 
+    /// Caches `value`. Remote-sourced values use `CacheConfig::remote_ttl`
+    /// when it is set; everything else uses the cache-wide TTL.
     pub fn insert(&self, key: CacheKey, value: Arc<ResolvedVariable>) {
+        let ttl = match value.source {
+            super::source::VariableSource::Remote { .. } => self.remote_ttl,
+            _ => None,
+        };
+        self.insert_entry(key, value, ttl);
+    }
+
+    /// Caches `value` with a TTL that overrides the cache-wide one.
+    pub fn insert_with_ttl(&self, key: CacheKey, value: Arc<ResolvedVariable>, ttl: Duration) {
+        self.insert_entry(key, value, Some(ttl));
+    }
+
+    fn insert_entry(&self, key: CacheKey, value: Arc<ResolvedVariable>, ttl: Option<Duration>) {
         if !self.enabled {
             return;
         }
@@ -203,6 +526,7 @@ impl ResolutionCache {
         let cached = CachedValue {
             value,
             cached_at: Instant::now(),
+            ttl,
         };
 
         self.ttl_cache.insert(key.clone(), cached.clone());
@@ -221,12 +545,27 @@ impl ResolutionCache {
         hot.pop(key);
     }
 
+    /// Drops every cached value. The hit and miss counters are kept; see
+    /// [`reset_stats`](Self::reset_stats).
     pub fn clear(&self) {
         self.ttl_cache.clear();
         let mut hot = self.hot_cache.write();
         hot.clear();
     }
 
+    /// Zeroes the hit and miss counters.
+    pub fn reset_stats(&self) {
+        self.hits.store(0, Ordering::Relaxed);
+        self.misses.store(0, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> ResolutionCacheStats {
+        ResolutionCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
     pub fn len(&self) -> usize {
         if !self.enabled {
             return 0;
@@ -238,6 +577,15 @@ impl ResolutionCache {
         !self.enabled || self.len() == 0
     }
 
+    /// Number of distinct keys cached. [`len`](Self::len) counts a key once
+    /// for each tier holding it.
+    pub fn key_count(&self) -> usize {
+        if !self.enabled {
+            return 0;
+        }
+        self.ttl_cache.len()
+    }
+
     pub fn cleanup_expired(&self) {
         if !self.enabled {
             return;
@@ -245,12 +593,12 @@ impl ResolutionCache {
 
         let now = Instant::now();
         self.ttl_cache
-            .retain(|_, cached| now.duration_since(cached.cached_at) < self.ttl);
+            .retain(|_, cached| cached.is_fresh(now, self.ttl));
 
         let mut hot = self.hot_cache.write();
         let keys_to_remove: Vec<CacheKey> = hot
             .iter()
-            .filter(|(_, cached)| now.duration_since(cached.cached_at) >= self.ttl)
+            .filter(|(_, cached)| !cached.is_fresh(now, self.ttl))
             .map(|(k, _)| k.clone())
             .collect();
 
@@ -260,12 +608,231 @@ impl ResolutionCache {
     }
 }
 
+/// Precedence tier of a source type, higher winning. Earlier entries of
+/// `precedence` rank higher; types it doesn't list (memory) rank lowest.
+fn source_tier(
+    source_type: crate::source::SourceType,
+    precedence: &[super::config::SourcePrecedence],
+) -> usize {
+    let listed = match source_type {
+        crate::source::SourceType::Shell => super::config::SourcePrecedence::Shell,
+        crate::source::SourceType::File => super::config::SourcePrecedence::File,
+        crate::source::SourceType::Remote => super::config::SourcePrecedence::Remote,
+        crate::source::SourceType::Memory => return 0,
+    };
+    precedence
+        .iter()
+        .position(|p| *p == listed)
+        .map_or(0, |index| precedence.len() - index)
+}
+
+/// The snapshots whose source type `precedence` lists; memory snapshots are
+/// always kept. An empty list keeps nothing.
+fn filter_by_precedence<'a>(
+    snapshots: &[&'a crate::source::SourceSnapshot],
+    precedence: &[super::config::SourcePrecedence],
+) -> Vec<&'a crate::source::SourceSnapshot> {
+    if precedence.is_empty() {
+        return Vec::new();
+    }
+
+    snapshots
+        .iter()
+        .filter(|snapshot| {
+            let source_type = match snapshot.source_type {
+                crate::source::SourceType::File => super::config::SourcePrecedence::File,
+                crate::source::SourceType::Shell => super::config::SourcePrecedence::Shell,
+                crate::source::SourceType::Remote => super::config::SourcePrecedence::Remote,
+                crate::source::SourceType::Memory => return true,
+            };
+
+            precedence.contains(&source_type)
+        })
+        .copied()
+        .collect()
+}
+
+/// Path of the env file behind a file snapshot.
+fn snapshot_file_path(snapshot: &crate::source::SourceSnapshot) -> Option<&std::path::Path> {
+    if snapshot.source_type != crate::source::SourceType::File {
+        return None;
+    }
+    let id = snapshot.source_id.as_str();
+    Some(std::path::Path::new(
+        id.strip_prefix(crate::source::SourceType::File.id_prefix())
+            .unwrap_or(id),
+    ))
+}
+
+/// Whether `snapshot` may supply references for `variable` under `scope`.
+fn snapshot_in_scope(
+    snapshot: &crate::source::SourceSnapshot,
+    variable: &crate::source::ParsedVariable,
+    context: &crate::workspace::WorkspaceContext,
+    scope: crate::config::InterpolationScope,
+) -> bool {
+    use crate::config::InterpolationScope;
+
+    let crate::source::VariableSource::File {
+        path: variable_path,
+        ..
+    } = &variable.source
+    else {
+        return true;
+    };
+    if scope == InterpolationScope::Global {
+        return true;
+    }
+    let Some(snapshot_path) = snapshot_file_path(snapshot) else {
+        return false;
+    };
+
+    match scope {
+        InterpolationScope::Global => true,
+        InterpolationScope::File => snapshot_path == variable_path,
+        InterpolationScope::Package if variable_path.starts_with(&context.package_root) => {
+            snapshot_path.starts_with(&context.package_root)
+        }
+        InterpolationScope::Package => snapshot_path.parent() == variable_path.parent(),
+    }
+}
+
+/// Name of the snapshot holding a per-call overlay, see
+/// [`ResolutionEngine::resolve_with_overlay`].
+const OVERLAY_SOURCE_NAME: &str = "overlay";
+
+/// Per-call changes to how [`ResolutionEngine::resolve_among`] ranks sources.
+#[derive(Default)]
+struct CallOverrides<'a> {
+    /// Replaces `resolution.precedence`, and confines interpolation to the
+    /// sources it admits, highest first.
+    precedence: Option<&'a [super::config::SourcePrecedence]>,
+    /// Id of an overlay snapshot that outranks every other snapshot.
+    overlay: Option<&'a crate::source::SourceId>,
+}
+
+/// Opens a `${pkg:<name>.<KEY>}` cross-package reference.
+const PACKAGE_REFERENCE_PREFIX: &str = "${pkg:";
+
+/// Whether `value` refers to `key` without a fallback, as `${KEY}` or as
+/// `$KEY` not followed by more identifier characters.
+pub(crate) fn references_plainly(value: &str, key: &str) -> bool {
+    if value.contains(&format!("${{{}}}", key)) {
+        return true;
+    }
+    value.match_indices('$').any(|(idx, _)| {
+        value[idx + 1..]
+            .strip_prefix(key)
+            .is_some_and(|rest| !rest.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_'))
+    })
+}
+
+/// `template` with a `\` before each plain reference to one of `keys`, so
+/// germi writes those references out as they stand instead of failing on
+/// them.
+fn escape_references(template: &str, keys: &[CompactString]) -> String {
+    let mut escaped = String::with_capacity(template.len() + keys.len());
+    let mut last = 0;
+    let mut scanner = germi::scanner::Scanner::new(template);
+    while let Ok(Some((token, range))) = scanner.scan_next() {
+        if let germi::scanner::Token::Variable {
+            name,
+            default: None,
+            ..
+        } = token
+        {
+            if keys.iter().any(|key| key == name) {
+                escaped.push_str(&template[last..range.start]);
+                escaped.push('\\');
+                last = range.start;
+            }
+        }
+    }
+    escaped.push_str(&template[last..]);
+    escaped
+}
+
+/// Interpolation state shared by every variable resolved in one pass over a
+/// fixed set of snapshots: a single germi instance holding each referenced
+/// value, interpolated once, instead of a fresh instance per value.
+struct InterpolationPass {
+    germi: germi::Germi,
+    resolved: HashMap<CompactString, CompactString>,
+    /// Substitution depth reached by each entry in `resolved`.
+    depths: HashMap<CompactString, u32>,
+    /// Leave references past `max_depth` unexpanded instead of failing, so
+    /// one runaway chain can't fail a bulk resolution of every other key.
+    truncate_at_max_depth: bool,
+}
+
+impl InterpolationPass {
+    fn new(max_depth: u32) -> Self {
+        Self {
+            germi: germi::Germi::with_config(germi::Config {
+                max_depth: max_depth as usize,
+                ..Default::default()
+            }),
+            resolved: HashMap::new(),
+            depths: HashMap::new(),
+            truncate_at_max_depth: false,
+        }
+    }
+}
+
+/// Picks the winning definition when sources of equal precedence define the
+/// same key. Receives the key and the candidates as `(source, raw value)`,
+/// lowest precedence first like sources everywhere else, so the last
+/// candidate is the one that wins by default. Returns the winner's index into
+/// the candidates; an out-of-range index keeps the default.
+pub type ConflictResolver =
+    Arc<dyn Fn(&str, &[(&crate::source::SourceId, &str)]) -> usize + Send + Sync>;
+
+/// Snapshots with fewer variables than this are scanned rather than indexed.
+const KEY_INDEX_THRESHOLD: usize = 64;
+
+/// Position of each key's last definition in a snapshot's variables.
+type KeyIndex = Arc<HashMap<CompactString, usize>>;
+
+/// A snapshot's variables together with their key index.
+type IndexedVariables = (Arc<[super::source::ParsedVariable]>, KeyIndex);
+
+#[derive(Default)]
+struct GraphState {
+    built: HashMap<crate::source::SourceId, BuiltFrom>,
+    cycle: Option<CompactString>,
+}
+
+/// What a source's graph edges were built from: its version, and its
+/// variables for telling unversioned snapshots apart.
+type BuiltFrom = (Option<u64>, Arc<[super::source::ParsedVariable]>);
+
 pub struct ResolutionEngine {
     resolution_config: parking_lot::RwLock<super::config::ResolutionConfig>,
     interpolation_config: parking_lot::RwLock<super::config::InterpolationConfig>,
     cache: Arc<ResolutionCache>,
     graph: Arc<parking_lot::RwLock<DependencyGraph>>,
-    graph_version: Arc<AtomicU64>,
+    /// Source snapshots the graph was last built from, and the cycle found
+    /// then, if any.
+    graph_state: parking_lot::Mutex<GraphState>,
+    /// Number of dependency edges built so far; rebuilds only add the edges
+    /// of sources that changed.
+    edge_builds: AtomicU64,
+    /// Temporary override layers, lowest first. They outrank every source.
+    overrides: parking_lot::RwLock<Vec<crate::source::SourceSnapshot>>,
+    next_override: AtomicU64,
+    /// Package roots by name and workspace-relative path, for `${pkg:...}`.
+    packages: parking_lot::RwLock<HashMap<CompactString, std::path::PathBuf>>,
+    /// Defaults declared by the schema file. Every source outranks them.
+    schema_defaults: parking_lot::RwLock<Option<crate::source::SourceSnapshot>>,
+    schema_descriptions: parking_lot::RwLock<HashMap<CompactString, CompactString>>,
+    #[cfg(feature = "file")]
+    schema: parking_lot::RwLock<Option<crate::schema::EnvSchema>>,
+    /// Per-key timings from the latest resolution, when profiling is on.
+    profile: parking_lot::Mutex<indexmap::IndexMap<CompactString, Duration>>,
+    conflict_resolver: parking_lot::RwLock<Option<ConflictResolver>>,
+    /// Key indexes of large snapshots, reused for as long as a source keeps
+    /// returning the same variables. Pruned on every load.
+    key_indexes: DashMap<crate::source::SourceId, IndexedVariables>,
 }
 
 impl ResolutionEngine {
@@ -279,10 +846,179 @@ impl ResolutionEngine {
             interpolation_config: parking_lot::RwLock::new(interpolation.clone()),
             cache: Arc::new(ResolutionCache::new(cache)),
             graph: Arc::new(parking_lot::RwLock::new(DependencyGraph::new())),
-            graph_version: Arc::new(AtomicU64::new(0)),
+            graph_state: parking_lot::Mutex::new(GraphState::default()),
+            edge_builds: AtomicU64::new(0),
+            overrides: parking_lot::RwLock::new(Vec::new()),
+            next_override: AtomicU64::new(1),
+            packages: parking_lot::RwLock::new(HashMap::new()),
+            schema_defaults: parking_lot::RwLock::new(None),
+            schema_descriptions: parking_lot::RwLock::new(HashMap::new()),
+            #[cfg(feature = "file")]
+            schema: parking_lot::RwLock::new(None),
+            profile: parking_lot::Mutex::new(indexmap::IndexMap::new()),
+            conflict_resolver: parking_lot::RwLock::new(None),
+            key_indexes: DashMap::new(),
+        }
+    }
+
+    /// Lets `resolver` choose between equal-precedence sources defining the
+    /// same key. Without one the source with the greatest id wins.
+    pub fn set_conflict_resolver(&self, resolver: Option<ConflictResolver>) {
+        *self.conflict_resolver.write() = resolver;
+        self.cache.clear();
+    }
+
+    /// Falls back to `schema`'s defaults for keys no source defines, and
+    /// describes variables that carry no description of their own.
+    #[cfg(feature = "file")]
+    pub fn set_schema(&self, schema: &crate::schema::EnvSchema) {
+        *self.schema_defaults.write() = Some(schema.defaults_snapshot());
+        *self.schema_descriptions.write() = schema.descriptions().clone();
+        *self.schema.write() = Some(schema.clone());
+        self.cache.clear();
+    }
+
+    /// Reloads the schema set with [`Self::set_schema`] if its file changed
+    /// since it was loaded, dropping cached values. Returns whether it did.
+    #[cfg(feature = "file")]
+    pub fn reload_schema_if_changed(&self) -> Result<bool> {
+        let path = match &*self.schema.read() {
+            Some(schema) if schema.has_changed() => schema.path().to_path_buf(),
+            _ => return Ok(false),
+        };
+        self.set_schema(&crate::schema::EnvSchema::load(path)?);
+        Ok(true)
+    }
+
+    /// Whether the schema file declares a default for `key`.
+    pub(crate) fn has_schema_default(&self, key: &str) -> bool {
+        self.schema_defaults
+            .read()
+            .as_ref()
+            .is_some_and(|defaults| defaults.variables.iter().any(|v| v.key == key))
+    }
+
+    /// Records the workspace packages that `${pkg:<name>.<KEY>}` references
+    /// can name, by package name or workspace-relative path.
+    pub fn set_packages(&self, packages: &[crate::workspace::PackageInfo]) {
+        let mut roots = HashMap::new();
+        for package in packages {
+            roots.insert(package.relative_path.clone(), package.root.clone());
+            if let Some(name) = &package.name {
+                roots.insert(name.clone(), package.root.clone());
+            }
+        }
+        *self.packages.write() = roots;
+        self.cache.clear();
+    }
+
+    /// Pushes an in-memory layer that takes precedence over every source and
+    /// earlier layer. Returns an id for [`Self::remove_override_layer`].
+    pub fn push_override_layer(&self, variables: &[(&str, &str)]) -> u64 {
+        let layer = self.next_override.fetch_add(1, Ordering::SeqCst);
+        let variables: Vec<_> = variables
+            .iter()
+            .map(|(key, value)| {
+                crate::source::ParsedVariable::simple(
+                    *key,
+                    *value,
+                    crate::source::VariableSource::Memory,
+                )
+            })
+            .collect();
+
+        self.overrides.write().push(crate::source::SourceSnapshot {
+            source_id: crate::source::SourceId::canonical(
+                crate::source::SourceType::Memory,
+                format_args!("override:{}", layer),
+            ),
+            source_type: crate::source::SourceType::Memory,
+            variables: variables.into(),
+            timestamp: std::time::Instant::now(),
+            version: Some(layer),
+        });
+        self.cache.clear();
+        layer
+    }
+
+    pub fn remove_override_layer(&self, layer: u64) {
+        let source_id = crate::source::SourceId::canonical(
+            crate::source::SourceType::Memory,
+            format_args!("override:{}", layer),
+        );
+        self.overrides
+            .write()
+            .retain(|snapshot| snapshot.source_id != source_id);
+        self.cache.clear();
+    }
+
+    /// Loads every source, preceded by the override layers (topmost first) so
+    /// they also win interpolation lookups, which take the first definition.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    async fn load_snapshots(
+        &self,
+        registry: &super::source::SourceRegistry,
+    ) -> Result<Vec<crate::source::SourceSnapshot>> {
+        let loaded = registry.load_all().await.map_err(AbundantisError::Source)?;
+        let overrides = self.overrides.read();
+        let schema_defaults = self.schema_defaults.read();
+        let snapshots = if overrides.is_empty() && schema_defaults.is_none() {
+            loaded
+        } else {
+            let mut snapshots: Vec<_> = overrides.iter().rev().cloned().collect();
+            snapshots.extend(loaded);
+            snapshots.extend(schema_defaults.iter().cloned());
+            snapshots
+        };
+        self.prune_key_indexes(&snapshots);
+        Ok(snapshots)
+    }
+
+    /// 0 for schema defaults, 1 for regular sources, 2 upwards for override
+    /// layers counting from the bottom, and the top rank for `overlay`, a
+    /// call's overlay snapshot.
+    fn layer_rank(
+        &self,
+        snapshot: &crate::source::SourceSnapshot,
+        overlay: Option<&crate::source::SourceId>,
+    ) -> usize {
+        if overlay == Some(&snapshot.source_id) {
+            return usize::MAX;
+        }
+        if let Some(index) = self
+            .overrides
+            .read()
+            .iter()
+            .position(|layer| layer.source_id == snapshot.source_id)
+        {
+            return index + 2;
+        }
+
+        if self.is_schema_defaults(snapshot) {
+            0
+        } else {
+            1
         }
     }
 
+    fn is_schema_defaults(&self, snapshot: &crate::source::SourceSnapshot) -> bool {
+        self.schema_defaults
+            .read()
+            .as_ref()
+            .is_some_and(|defaults| {
+                defaults.source_id == snapshot.source_id
+                    && Arc::ptr_eq(&defaults.variables, &snapshot.variables)
+            })
+    }
+
+    fn description_for(&self, variable: &super::source::ParsedVariable) -> Option<CompactString> {
+        variable
+            .description
+            .clone()
+            .or_else(|| self.schema_descriptions.read().get(&variable.key).cloned())
+    }
+
     pub fn update_resolution_config(&self, config: super::config::ResolutionConfig) {
         *self.resolution_config.write() = config;
         self.cache.clear();
@@ -303,85 +1039,218 @@ impl ResolutionEngine {
         self.resolution_config.read().precedence.clone()
     }
 
-    fn snapshots_version(&self, snapshots: &[crate::source::SourceSnapshot]) -> u64 {
-        snapshots.iter().filter_map(|s| s.version).sum()
+    /// Time spent resolving each key during the latest `resolve`,
+    /// `all_variables` or warm-up call, in resolution order. Empty unless
+    /// `resolution.profile` is on; cache hits are not timed.
+    pub fn last_profile(&self) -> Vec<(CompactString, Duration)> {
+        self.profile
+            .lock()
+            .iter()
+            .map(|(key, duration)| (key.clone(), *duration))
+            .collect()
     }
 
-    fn maybe_rebuild_graph(&self, snapshots: &[crate::source::SourceSnapshot]) -> Result<()> {
-        let current_version = self.snapshots_version(snapshots);
-        let last_version = self.graph_version.load(Ordering::SeqCst);
-
-        if current_version != last_version {
-            self.build_dependency_graph(snapshots)?;
-            self.graph_version.store(current_version, Ordering::SeqCst);
+    fn begin_profile(&self) {
+        if self.resolution_config.read().profile {
+            self.profile.lock().clear();
         }
-        Ok(())
     }
 
-    fn filter_snapshots_ref<'a>(
+    /// Resolves a variable requested directly rather than through a
+    /// reference, recording how long it took when profiling.
+    fn resolve_top_level(
         &self,
-        snapshots: &'a [crate::source::SourceSnapshot],
-        file_source_filter: Option<&HashSet<crate::source::SourceId>>,
-    ) -> Vec<&'a crate::source::SourceSnapshot> {
-        match file_source_filter {
-            Some(filter) if !filter.is_empty() => snapshots
-                .iter()
-                .filter(|snapshot| {
-                    let source_id_str = snapshot.source_id.as_str();
-                    let is_file_source =
-                        source_id_str.starts_with("file:") && source_id_str.len() > 5;
-                    if is_file_source {
-                        filter.contains(&snapshot.source_id)
-                    } else {
-                        true
-                    }
-                })
-                .collect(),
-
-            Some(_) => snapshots
-                .iter()
-                .filter(|snapshot| {
-                    let source_id_str = snapshot.source_id.as_str();
-                    let is_file_source =
-                        source_id_str.starts_with("file:") && source_id_str.len() > 5;
-                    !is_file_source
-                })
-                .collect(),
-
-            None => snapshots.iter().collect(),
+        variable: &super::source::ParsedVariable,
+        all_snapshots: &[crate::source::SourceSnapshot],
+        context: &super::workspace::WorkspaceContext,
+        pass: &mut InterpolationPass,
+    ) -> Result<Arc<ResolvedVariable>> {
+        if !self.resolution_config.read().profile {
+            return self.resolve_variable(
+                variable,
+                all_snapshots,
+                context,
+                0,
+                &mut Vec::new(),
+                pass,
+            );
         }
+
+        let started = Instant::now();
+        let resolved =
+            self.resolve_variable(variable, all_snapshots, context, 0, &mut Vec::new(), pass);
+        *self.profile.lock().entry(variable.key.clone()).or_default() += started.elapsed();
+        resolved
     }
 
-    fn filter_by_source_type<'a>(
+    /// Resolves `winner`, the winning definition of its key among `sorted`
+    /// (lowest precedence first). Under an `AppendList` merge strategy every
+    /// definition in `sorted` but a schema default is resolved and joined,
+    /// highest precedence first; the result reports the winner's source,
+    /// wherever a conflict resolver placed it.
+    fn resolve_merged(
         &self,
-        snapshots: &[&'a crate::source::SourceSnapshot],
-    ) -> Vec<&'a crate::source::SourceSnapshot> {
-        let config = self.resolution_config.read();
-        let precedence = &config.precedence;
+        winner: &super::source::ParsedVariable,
+        sorted: &[&crate::source::SourceSnapshot],
+        all_snapshots: &[crate::source::SourceSnapshot],
+        context: &super::workspace::WorkspaceContext,
+        pass: &mut InterpolationPass,
+    ) -> Result<Arc<ResolvedVariable>> {
+        let strategy = self
+            .resolution_config
+            .read()
+            .merge
+            .get(&winner.key)
+            .cloned();
+        let Some(super::config::MergeStrategy::AppendList { separator }) = strategy else {
+            return self.resolve_top_level(winner, all_snapshots, context, pass);
+        };
 
-        if precedence.is_empty() {
-            return Vec::new();
+        // Schema defaults only stand in for a missing value, they aren't
+        // part of the list.
+        let mut parts = Vec::new();
+        let mut winner_part = 0;
+        for snapshot in sorted.iter().rev() {
+            let Some(variable) = self.lookup(snapshot, &winner.key) else {
+                continue;
+            };
+            let is_winner = std::ptr::eq(variable, winner);
+            if is_winner {
+                winner_part = parts.len();
+            } else if self.is_schema_defaults(snapshot) {
+                continue;
+            }
+            parts.push(self.resolve_top_level(variable, all_snapshots, context, pass)?);
+        }
+        if parts.len() <= 1 {
+            return match parts.pop() {
+                Some(part) => Ok(part),
+                None => self.resolve_top_level(winner, all_snapshots, context, pass),
+            };
         }
 
-        snapshots
-            .iter()
-            .filter(|snapshot| {
-                let source_id_str = snapshot.source_id.as_str();
-
-                let source_type = if source_id_str.starts_with("file:") {
-                    crate::config::SourcePrecedence::File
-                } else if source_id_str == "shell" || source_id_str.starts_with("shell:") {
-                    crate::config::SourcePrecedence::Shell
-                } else if source_id_str.starts_with("external:") {
-                    crate::config::SourcePrecedence::Remote
-                } else {
-                    return true;
-                };
+        let join = |value: fn(&ResolvedVariable) -> &str| {
+            CompactString::from(
+                parts
+                    .iter()
+                    .map(|p| value(p))
+                    .collect::<Vec<_>>()
+                    .join(&separator),
+            )
+        };
+        Ok(Arc::new(ResolvedVariable {
+            key: winner.key.clone(),
+            raw_value: join(|p| &p.raw_value),
+            resolved_value: join(|p| &p.resolved_value),
+            source: parts[winner_part].source.clone(),
+            description: parts[winner_part].description.clone(),
+            has_warnings: parts.iter().any(|p| p.has_warnings),
+            interpolation_depth: parts
+                .iter()
+                .map(|p| p.interpolation_depth)
+                .max()
+                .unwrap_or(0),
+        }))
+    }
 
-                precedence.contains(&source_type)
-            })
-            .copied()
-            .collect()
+    fn interpolation_pass(&self) -> InterpolationPass {
+        InterpolationPass::new(self.interpolation_config.read().max_depth)
+    }
+
+    /// A pass for resolving many keys at once, where exceeding `max_depth`
+    /// truncates the value rather than failing the whole batch.
+    fn bulk_interpolation_pass(&self) -> InterpolationPass {
+        InterpolationPass {
+            truncate_at_max_depth: true,
+            ..self.interpolation_pass()
+        }
+    }
+
+    /// Brings the dependency graph up to date with `snapshots`, rebuilding
+    /// only the edges of sources whose version changed since the last call.
+    /// Sources without a version are rebuilt whenever they hand out different
+    /// variables than last time.
+    fn maybe_rebuild_graph(&self, snapshots: &[crate::source::SourceSnapshot]) -> Result<()> {
+        let mut state = self.graph_state.lock();
+        let mut changed = false;
+
+        {
+            let mut graph = self.graph.write();
+            state.built.retain(|id, _| {
+                let present = snapshots.iter().any(|s| &s.source_id == id);
+                if !present {
+                    graph.remove_source(id);
+                    changed = true;
+                }
+                present
+            });
+
+            for snapshot in snapshots {
+                let unchanged =
+                    state
+                        .built
+                        .get(&snapshot.source_id)
+                        .is_some_and(|(version, variables)| match snapshot.version {
+                            Some(_) => *version == snapshot.version,
+                            None => {
+                                version.is_none() && Arc::ptr_eq(variables, &snapshot.variables)
+                            }
+                        });
+                if unchanged {
+                    continue;
+                }
+                graph.replace_source_edges(&snapshot.source_id, self.build_source_edges(snapshot));
+                state.built.insert(
+                    snapshot.source_id.clone(),
+                    (snapshot.version, Arc::clone(&snapshot.variables)),
+                );
+                changed = true;
+            }
+        }
+
+        if changed {
+            state.cycle = self.find_dependency_cycle(snapshots);
+        }
+
+        match &state.cycle {
+            Some(chain) => Err(AbundantisError::CircularDependency {
+                chain: chain.to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn filter_snapshots_ref<'a>(
+        &self,
+        snapshots: &'a [crate::source::SourceSnapshot],
+        file_source_filter: Option<&HashSet<crate::source::SourceId>>,
+    ) -> Vec<&'a crate::source::SourceSnapshot> {
+        match file_source_filter {
+            Some(filter) if !filter.is_empty() => snapshots
+                .iter()
+                .filter(|snapshot| {
+                    if snapshot.source_type == crate::source::SourceType::File {
+                        filter.contains(&snapshot.source_id)
+                    } else {
+                        true
+                    }
+                })
+                .collect(),
+
+            Some(_) => snapshots
+                .iter()
+                .filter(|snapshot| snapshot.source_type != crate::source::SourceType::File)
+                .collect(),
+
+            None => snapshots.iter().collect(),
+        }
+    }
+
+    fn filter_by_source_type<'a>(
+        &self,
+        snapshots: &[&'a crate::source::SourceSnapshot],
+    ) -> Vec<&'a crate::source::SourceSnapshot> {
+        filter_by_precedence(snapshots, &self.resolution_config.read().precedence)
     }
 
     fn resolve_inner(
@@ -393,25 +1262,21 @@ impl ResolutionEngine {
         let sorted_snapshots = self.sort_snapshots_by_file_order(snapshots);
 
         let mut resolved = None;
-
-        for snapshot in &sorted_snapshots {
-            if let Some(variable) = snapshot.variables.iter().find(|v| v.key.as_str() == key) {
-                resolved = Some(self.resolve_variable(
-                    variable,
-                    snapshots,
-                    context,
-                    0,
-                    &mut Vec::new(),
-                )?);
-            }
+        let mut pass = self.interpolation_pass();
+        self.begin_profile();
+
+        if let Some(variable) = self.winning_definition(key, &sorted_snapshots) {
+            resolved = Some(self.resolve_merged(
+                variable,
+                &sorted_snapshots,
+                snapshots,
+                context,
+                &mut pass,
+            )?);
         }
 
         if let Some(ref var) = resolved {
-            let context_hash = self.hash_context(context);
-            let cache_key = CacheKey {
-                key: CompactString::new(key),
-                context_hash,
-            };
+            let cache_key = CacheKey::new(key, self.hash_context(context));
             self.cache.insert(cache_key, Arc::clone(var));
         }
 
@@ -422,34 +1287,172 @@ impl ResolutionEngine {
         &self,
         snapshots: &'a [crate::source::SourceSnapshot],
     ) -> Vec<&'a crate::source::SourceSnapshot> {
-        let config = self.resolution_config.read();
-        let file_order = &config.files.order;
-
-        let mut sorted: Vec<_> = snapshots.iter().collect();
-        sorted.sort_by(|a, b| {
-            let a_order = self.get_file_order_index(&a.source_id, file_order);
-            let b_order = self.get_file_order_index(&b.source_id, file_order);
-            a_order.cmp(&b_order)
+        let refs: Vec<_> = snapshots.iter().collect();
+        self.sort_snapshot_refs_by_file_order(&refs)
+    }
+
+    /// Orders snapshots from lowest to highest precedence. Later snapshots
+    /// override earlier ones: schema defaults < memory < source types in
+    /// reverse `resolution.precedence` order < override layers, so the
+    /// default `[Shell, File]` lets the shell win over files. File snapshots
+    /// are further ordered by `resolution.files.order`. Ties are broken by source
+    /// id so the result does not depend on registry iteration order.
+    fn sort_snapshot_refs_by_file_order<'a>(
+        &self,
+        snapshots: &[&'a crate::source::SourceSnapshot],
+    ) -> Vec<&'a crate::source::SourceSnapshot> {
+        self.sort_snapshot_refs_with(snapshots, &self.resolution_config.read(), None)
+    }
+
+    fn sort_snapshot_refs_with<'a>(
+        &self,
+        snapshots: &[&'a crate::source::SourceSnapshot],
+        config: &super::config::ResolutionConfig,
+        overlay: Option<&crate::source::SourceId>,
+    ) -> Vec<&'a crate::source::SourceSnapshot> {
+        let mut sorted: Vec<_> = snapshots.to_vec();
+        sorted.sort_by_cached_key(|snapshot| {
+            (
+                self.precedence_rank(snapshot, config, overlay),
+                snapshot.source_id.as_str().to_owned(),
+            )
         });
 
         sorted
     }
 
+    /// Precedence of `snapshot`, higher winning. Snapshots of equal rank are
+    /// only told apart by source id, or by the conflict resolver if set.
+    fn precedence_rank(
+        &self,
+        snapshot: &crate::source::SourceSnapshot,
+        config: &super::config::ResolutionConfig,
+        overlay: Option<&crate::source::SourceId>,
+    ) -> (usize, usize, usize) {
+        (
+            self.layer_rank(snapshot, overlay),
+            source_tier(snapshot.source_type, &config.precedence),
+            self.get_file_order_index(snapshot, &config.files.order),
+        )
+    }
+
+    /// The last definition of `key` in `snapshot`. Large snapshots are looked
+    /// up through a key index built on first use.
+    fn lookup<'a>(
+        &self,
+        snapshot: &'a crate::source::SourceSnapshot,
+        key: &str,
+    ) -> Option<&'a super::source::ParsedVariable> {
+        if snapshot.variables.len() < KEY_INDEX_THRESHOLD {
+            return snapshot.variables.iter().rfind(|v| v.key.as_str() == key);
+        }
+
+        let index = self.key_index(snapshot);
+        index
+            .get(key)
+            .map(|&position| &snapshot.variables[position])
+    }
+
+    fn key_index(&self, snapshot: &crate::source::SourceSnapshot) -> KeyIndex {
+        if let Some(entry) = self.key_indexes.get(&snapshot.source_id) {
+            let (variables, index) = entry.value();
+            if Arc::ptr_eq(variables, &snapshot.variables) {
+                return Arc::clone(index);
+            }
+        }
+
+        let index: KeyIndex = Arc::new(
+            snapshot
+                .variables
+                .iter()
+                .enumerate()
+                .map(|(position, variable)| (variable.key.clone(), position))
+                .collect(),
+        );
+        self.key_indexes.insert(
+            snapshot.source_id.clone(),
+            (Arc::clone(&snapshot.variables), Arc::clone(&index)),
+        );
+        index
+    }
+
+    /// Drops the key indexes of variables no snapshot in `snapshots` holds
+    /// any more, from sources that changed or went away.
+    fn prune_key_indexes(&self, snapshots: &[crate::source::SourceSnapshot]) {
+        self.key_indexes.retain(|id, (variables, _)| {
+            snapshots
+                .iter()
+                .any(|s| &s.source_id == id && Arc::ptr_eq(&s.variables, variables))
+        });
+    }
+
+    /// The definition of `key` that wins among `sorted` (lowest precedence
+    /// first): the last one, unless several equal-rank sources define the key
+    /// and a conflict resolver picks another.
+    fn winning_definition<'a>(
+        &self,
+        key: &str,
+        sorted: &[&'a crate::source::SourceSnapshot],
+    ) -> Option<&'a super::source::ParsedVariable> {
+        self.winning_definition_with(key, sorted, &self.resolution_config.read(), None)
+    }
+
+    fn winning_definition_with<'a>(
+        &self,
+        key: &str,
+        sorted: &[&'a crate::source::SourceSnapshot],
+        config: &super::config::ResolutionConfig,
+        overlay: Option<&crate::source::SourceId>,
+    ) -> Option<&'a super::source::ParsedVariable> {
+        let mut definitions = sorted.iter().rev().filter_map(|snapshot| {
+            let variable = self.lookup(snapshot, key)?;
+            Some((*snapshot, variable))
+        });
+        let (top_snapshot, top) = definitions.next()?;
+
+        let Some(resolver) = self.conflict_resolver.read().clone() else {
+            return Some(top);
+        };
+        let rank = self.precedence_rank(top_snapshot, config, overlay);
+        let mut tied: Vec<_> = std::iter::once((top_snapshot, top))
+            .chain(definitions.take_while(|(snapshot, _)| {
+                self.precedence_rank(snapshot, config, overlay) == rank
+            }))
+            .collect();
+        tied.reverse();
+        if tied.len() == 1 {
+            return Some(top);
+        }
+
+        let candidates: Vec<_> = tied
+            .iter()
+            .map(|(snapshot, variable)| (&snapshot.source_id, variable.raw_value.as_str()))
+            .collect();
+        let index = resolver(key, &candidates);
+        match tied.get(index) {
+            Some((_, variable)) => Some(variable),
+            None => {
+                tracing::warn!(
+                    key = %key,
+                    index,
+                    candidates = tied.len(),
+                    "Conflict resolver returned an out-of-range index, using the default"
+                );
+                Some(top)
+            }
+        }
+    }
+
     fn get_file_order_index(
         &self,
-        source_id: &crate::source::SourceId,
+        snapshot: &crate::source::SourceSnapshot,
         file_order: &[CompactString],
     ) -> usize {
-        let source_str = source_id.as_str();
-        if !source_str.starts_with("file:") {
+        let Some(path) = snapshot_file_path(snapshot) else {
             return 0;
-        }
+        };
 
-        let path = &source_str[5..];
-        let filename = std::path::Path::new(path)
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("");
+        let filename = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
 
         for (i, pattern) in file_order.iter().enumerate() {
             if filename == pattern.as_str() || path.ends_with(pattern.as_str()) {
@@ -460,23 +1463,6 @@ impl ResolutionEngine {
         file_order.len() + 1
     }
 
-    fn sort_snapshot_refs_by_file_order<'a>(
-        &self,
-        snapshots: &[&'a crate::source::SourceSnapshot],
-    ) -> Vec<&'a crate::source::SourceSnapshot> {
-        let config = self.resolution_config.read();
-        let file_order = &config.files.order;
-
-        let mut sorted: Vec<_> = snapshots.to_vec();
-        sorted.sort_by(|a, b| {
-            let a_order = self.get_file_order_index(&a.source_id, file_order);
-            let b_order = self.get_file_order_index(&b.source_id, file_order);
-            a_order.cmp(&b_order)
-        });
-
-        sorted
-    }
-
     fn all_variables_inner(
         &self,
         context: &super::workspace::WorkspaceContext,
@@ -489,23 +1475,30 @@ impl ResolutionEngine {
 
         let mut seen_keys = std::collections::HashSet::new();
         let mut results = Vec::new();
-
-        for snapshot in sorted {
-            for variable in snapshot.variables.iter() {
+        let mut pass = self.bulk_interpolation_pass();
+        self.begin_profile();
+        let has_resolver = self.conflict_resolver.read().is_some();
+
+        // Walk from highest precedence down so each key's first sighting is
+        // the definition `resolve` would pick.
+        for snapshot in sorted.iter().rev() {
+            for variable in snapshot.variables.iter().rev() {
                 if !seen_keys.contains(&variable.key) {
-                    let resolved = self.resolve_variable(
-                        variable,
-                        all_snapshots,
-                        context,
-                        0,
-                        &mut Vec::new(),
-                    )?;
+                    let variable = if has_resolver {
+                        self.winning_definition(&variable.key, &sorted)
+                            .unwrap_or(variable)
+                    } else {
+                        variable
+                    };
+                    let resolved =
+                        self.resolve_merged(variable, &sorted, all_snapshots, context, &mut pass)?;
                     results.push(resolved);
                     seen_keys.insert(variable.key.clone());
                 }
             }
         }
 
+        results.reverse();
         Ok(results)
     }
 
@@ -517,17 +1510,13 @@ impl ResolutionEngine {
         context: &super::workspace::WorkspaceContext,
         registry: &super::source::SourceRegistry,
     ) -> Result<Option<Arc<ResolvedVariable>>> {
-        let context_hash = self.hash_context(context);
-        let cache_key = CacheKey {
-            key: CompactString::new(key),
-            context_hash,
-        };
+        let cache_key = CacheKey::new(key, self.hash_context(context));
 
         if let Some(cached) = self.cache.get(&cache_key) {
             return Ok(Some(cached));
         }
 
-        let snapshots = registry.load_all().await.map_err(AbundantisError::Source)?;
+        let snapshots = self.load_snapshots(registry).await?;
 
         if self.resolution_config.read().type_check {
             self.maybe_rebuild_graph(&snapshots)?;
@@ -543,6 +1532,7 @@ impl ResolutionEngine {
         context: &super::workspace::WorkspaceContext,
         depth: u32,
         visited: &mut Vec<CompactString>,
+        pass: &mut InterpolationPass,
     ) -> Result<Arc<ResolvedVariable>> {
         let key = variable.key.clone();
         let interpolation_config = self.interpolation_config.read();
@@ -554,7 +1544,7 @@ impl ResolutionEngine {
                 raw_value: variable.raw_value.clone(),
                 resolved_value: variable.raw_value.clone(),
                 source: variable.source.clone(),
-                description: variable.description.clone(),
+                description: self.description_for(variable),
                 has_warnings: false,
                 interpolation_depth: 0,
             }));
@@ -564,6 +1554,7 @@ impl ResolutionEngine {
             return Err(AbundantisError::MaxDepthExceeded {
                 key: key.as_str().to_string(),
                 depth,
+                chain: key.as_str().to_string(),
             });
         }
 
@@ -579,70 +1570,200 @@ impl ResolutionEngine {
 
         visited.push(key.clone());
 
-        let resolved_value = self.interpolate_value_lazy(
-            &variable.raw_value,
-            all_snapshots,
-            context,
-            depth + 1,
-            visited,
-        );
+        let scope = self.resolution_config.read().interpolation_scope;
+        let scoped_snapshots: Vec<_> = all_snapshots
+            .iter()
+            .filter(|snapshot| snapshot_in_scope(snapshot, variable, context, scope))
+            .collect();
+
+        // Narrower scopes see a different snapshot set per variable, so their
+        // values can't be shared with the rest of the pass.
+        let mut scoped_pass;
+        let pass = if scoped_snapshots.len() == all_snapshots.len() {
+            pass
+        } else {
+            scoped_pass = InterpolationPass {
+                truncate_at_max_depth: pass.truncate_at_max_depth,
+                ..self.interpolation_pass()
+            };
+            &mut scoped_pass
+        };
 
+        let interpolated =
+            self.interpolate_variable(variable, &scoped_snapshots, depth + 1, visited, pass);
+        // Popped before propagating errors so `visited` only ever holds the
+        // chain currently being expanded, never a branch that already ended.
         visited.pop();
+        let (resolved_value, _, interpolation_depth) = interpolated?;
 
         Ok(Arc::new(ResolvedVariable {
             key,
             raw_value: variable.raw_value.clone(),
             resolved_value,
             source: variable.source.clone(),
-            description: variable.description.clone(),
+            description: self.description_for(variable),
             has_warnings: false,
-            interpolation_depth: depth,
+            interpolation_depth,
         }))
     }
 
+    /// [`interpolate_value_lazy`](Self::interpolate_value_lazy) for
+    /// `variable`'s raw value; literal variables come back verbatim.
+    fn interpolate_variable(
+        &self,
+        variable: &crate::source::ParsedVariable,
+        all_snapshots: &[&crate::source::SourceSnapshot],
+        depth: u32,
+        visited: &mut Vec<CompactString>,
+        pass: &mut InterpolationPass,
+    ) -> Result<(CompactString, bool, u32)> {
+        if variable.is_literal {
+            return Ok((variable.raw_value.clone(), true, 0));
+        }
+        self.interpolate_value_lazy(&variable.raw_value, all_snapshots, depth, visited, pass)
+    }
+
+    /// Interpolates `value` against `all_snapshots`, reusing values already
+    /// resolved in `pass`. `visited` holds the chain of keys being expanded.
+    /// The returned flag is false when a reference was cut short by a cycle;
+    /// such values depend on the path taken to reach them and are kept out of
+    /// the shared pass. The returned depth is the longest chain of
+    /// substitutions behind the value, 0 when nothing was substituted.
     fn interpolate_value_lazy(
         &self,
         value: &str,
-        all_snapshots: &[crate::source::SourceSnapshot],
-        _context: &super::workspace::WorkspaceContext,
+        all_snapshots: &[&crate::source::SourceSnapshot],
         depth: u32,
         visited: &mut Vec<CompactString>,
-    ) -> CompactString {
+        pass: &mut InterpolationPass,
+    ) -> Result<(CompactString, bool, u32)> {
+        use crate::config::OnUndefined;
+
+        let on_undefined = self.resolution_config.read().on_undefined;
         let interpolation_config = self.interpolation_config.read();
         let max_depth = interpolation_config.max_depth;
 
-        if depth >= max_depth || !interpolation_config.enabled {
-            return CompactString::new(value);
+        if !interpolation_config.enabled {
+            return Ok((CompactString::new(value), true, 0));
         }
 
-        let mut germi = germi::Germi::with_config(germi::Config {
-            max_depth: (max_depth - depth) as usize,
-            ..Default::default()
-        });
+        let mut reached = 0;
+        let substituted;
+        let value = if interpolation_config.features.cross_package
+            && value.contains(PACKAGE_REFERENCE_PREFIX)
+        {
+            substituted =
+                self.substitute_package_references(value, all_snapshots, depth, visited)?;
+            if substituted != value {
+                reached = 1;
+            }
+            substituted.as_str()
+        } else {
+            value
+        };
 
         let references = self.find_variable_references(value);
-        for ref_key in references {
-            if visited.contains(&ref_key) {
+        if references.is_empty() {
+            return Ok((CompactString::new(value), true, reached));
+        }
+
+        if depth >= max_depth && pass.truncate_at_max_depth {
+            return Ok((CompactString::new(value), false, reached));
+        }
+        if depth >= max_depth {
+            return Err(AbundantisError::MaxDepthExceeded {
+                key: visited.last().map(|k| k.to_string()).unwrap_or_default(),
+                depth,
+                chain: visited
+                    .iter()
+                    .map(|k| k.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" -> "),
+            });
+        }
+
+        let mut complete = true;
+        let mut partial = Vec::new();
+        let mut emptied = Vec::new();
+        reached = reached.max(1);
+        'references: for ref_key in &references {
+            if visited.contains(ref_key) {
+                complete = false;
+                continue;
+            }
+            if let Some(ref_depth) = pass.depths.get(ref_key) {
+                reached = reached.max(ref_depth + 1);
                 continue;
             }
 
             for snapshot in all_snapshots {
-                if let Some(variable) = snapshot.variables.iter().find(|v| v.key == ref_key) {
-                    let resolved_value = self.interpolate_value_lazy(
-                        &variable.raw_value,
+                if let Some(variable) = self.lookup(snapshot, ref_key) {
+                    visited.push(ref_key.clone());
+                    let resolved = self.interpolate_variable(
+                        variable,
                         all_snapshots,
-                        _context,
                         depth + 1,
                         visited,
+                        pass,
                     );
-                    germi.add_variable(variable.key.as_str(), resolved_value.as_str());
-                    break;
+                    visited.pop();
+                    let (resolved_value, resolved_complete, ref_depth) = resolved?;
+                    reached = reached.max(ref_depth + 1);
+                    if resolved_complete {
+                        pass.germi
+                            .add_variable(variable.key.as_str(), resolved_value.as_str());
+                        pass.depths.insert(variable.key.clone(), ref_depth);
+                        pass.resolved.insert(variable.key.clone(), resolved_value);
+                    } else {
+                        complete = false;
+                        partial.push((variable.key.clone(), resolved_value));
+                    }
+                    continue 'references;
+                }
+            }
+
+            if !references_plainly(value, ref_key) {
+                continue;
+            }
+            match on_undefined {
+                OnUndefined::Keep => {}
+                OnUndefined::Error => {
+                    return Err(AbundantisError::UndefinedVariable {
+                        key: ref_key.to_string(),
+                    });
+                }
+                OnUndefined::Empty => {
+                    pass.germi.add_variable(ref_key.as_str(), "");
+                    emptied.push(ref_key);
                 }
             }
         }
 
+        let scratch;
+        let germi = if complete {
+            &pass.germi
+        } else {
+            let mut germi = germi::Germi::with_config(germi::Config {
+                max_depth: (max_depth - depth) as usize,
+                ..Default::default()
+            });
+            for ref_key in &references {
+                if let Some(resolved_value) = pass.resolved.get(ref_key) {
+                    germi.add_variable(ref_key.as_str(), resolved_value.as_str());
+                }
+            }
+            for (key, resolved_value) in &partial {
+                germi.add_variable(key.as_str(), resolved_value.as_str());
+            }
+            for key in &emptied {
+                germi.add_variable(key.as_str(), "");
+            }
+            scratch = germi;
+            &scratch
+        };
+
         match germi.interpolate(value) {
-            Ok(interpolated) => CompactString::new(interpolated.as_ref()),
+            Ok(interpolated) => Ok((CompactString::new(interpolated.as_ref()), complete, reached)),
             Err(e) => {
                 tracing::warn!(
                     value = %value,
@@ -650,9 +1771,94 @@ impl ResolutionEngine {
                     error = %e,
                     "Interpolation failed, returning original value"
                 );
-                CompactString::new(value)
+                Ok((CompactString::new(value), complete, 0))
+            }
+        }
+    }
+
+    /// Replaces each `${pkg:<name>.<KEY>}` in `value` with `KEY` as defined by
+    /// package `name`'s own env files, interpolated within that package.
+    /// References to unknown packages or keys, and cyclic ones, are left as is.
+    fn substitute_package_references(
+        &self,
+        value: &str,
+        all_snapshots: &[&crate::source::SourceSnapshot],
+        depth: u32,
+        visited: &mut Vec<CompactString>,
+    ) -> Result<String> {
+        let mut result = String::with_capacity(value.len());
+        let mut rest = value;
+
+        while let Some(start) = rest.find(PACKAGE_REFERENCE_PREFIX) {
+            let reference_start = start + PACKAGE_REFERENCE_PREFIX.len();
+            let Some(length) = rest[reference_start..].find('}') else {
+                break;
+            };
+            let reference = &rest[reference_start..reference_start + length];
+            result.push_str(&rest[..start]);
+
+            let resolved = match reference.rsplit_once('.') {
+                Some((package, key)) => {
+                    self.resolve_package_reference(package, key, all_snapshots, depth, visited)?
+                }
+                None => None,
+            };
+            match resolved {
+                Some(resolved) => result.push_str(&resolved),
+                None => result.push_str(&rest[start..reference_start + length + 1]),
             }
+
+            rest = &rest[reference_start + length + 1..];
+        }
+
+        result.push_str(rest);
+        Ok(result)
+    }
+
+    fn resolve_package_reference(
+        &self,
+        package: &str,
+        key: &str,
+        all_snapshots: &[&crate::source::SourceSnapshot],
+        depth: u32,
+        visited: &mut Vec<CompactString>,
+    ) -> Result<Option<CompactString>> {
+        let chain_key = compact_str::format_compact!("pkg:{}.{}", package, key);
+        if visited.contains(&chain_key) {
+            return Ok(None);
         }
+        let Some(root) = self.packages.read().get(package).cloned() else {
+            return Ok(None);
+        };
+
+        let package_snapshots: Vec<_> = all_snapshots
+            .iter()
+            .filter(|snapshot| {
+                snapshot_file_path(snapshot).and_then(std::path::Path::parent)
+                    == Some(root.as_path())
+            })
+            .copied()
+            .collect();
+        let Some(variable) = self
+            .sort_snapshot_refs_by_file_order(&package_snapshots)
+            .into_iter()
+            .rev()
+            .find_map(|snapshot| self.lookup(snapshot, key))
+        else {
+            return Ok(None);
+        };
+
+        visited.push(chain_key);
+        let resolved = self.interpolate_variable(
+            variable,
+            &package_snapshots,
+            depth + 1,
+            visited,
+            &mut self.interpolation_pass(),
+        );
+        visited.pop();
+
+        Ok(Some(resolved?.0))
     }
 
     #[cfg_attr(feature = "async", must_be_async)]
@@ -662,7 +1868,7 @@ impl ResolutionEngine {
         context: &super::workspace::WorkspaceContext,
         registry: &super::source::SourceRegistry,
     ) -> Result<Vec<Arc<ResolvedVariable>>> {
-        let snapshots = registry.load_all().await.map_err(AbundantisError::Source)?;
+        let snapshots = self.load_snapshots(registry).await?;
 
         if self.resolution_config.read().type_check {
             self.maybe_rebuild_graph(&snapshots)?;
@@ -680,46 +1886,248 @@ impl ResolutionEngine {
         registry: &super::source::SourceRegistry,
         file_source_filter: Option<&HashSet<super::source::SourceId>>,
     ) -> Result<Option<Arc<ResolvedVariable>>> {
-        let context_hash = self.hash_context(context);
-        let cache_key = CacheKey {
-            key: CompactString::new(key),
-            context_hash,
-        };
+        let cache_key =
+            CacheKey::new(key, self.hash_context(context)).with_filter(file_source_filter);
 
         if let Some(cached) = self.cache.get(&cache_key) {
             return Ok(Some(cached));
         }
 
-        let snapshots = registry.load_all().await.map_err(AbundantisError::Source)?;
-        let filtered_refs = self.filter_snapshots_ref(&snapshots, file_source_filter);
-
-        let type_filtered = self.filter_by_source_type(&filtered_refs);
-
+        let snapshots = self.load_snapshots(registry).await?;
         if self.resolution_config.read().type_check {
             self.maybe_rebuild_graph(&snapshots)?;
         }
 
-        let sorted_filtered = self.sort_snapshot_refs_by_file_order(&type_filtered);
+        self.resolve_among(
+            key,
+            context,
+            &snapshots,
+            file_source_filter,
+            CallOverrides::default(),
+            cache_key,
+        )
+    }
 
-        let mut resolved = None;
+    /// Like `resolve_with_filter`, but ranks and filters source types by
+    /// `precedence` instead of the configured order, for this call only.
+    /// Results are cached apart from those of the configured precedence.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn resolve_with_precedence(
+        &self,
+        key: &str,
+        context: &super::workspace::WorkspaceContext,
+        registry: &super::source::SourceRegistry,
+        file_source_filter: Option<&HashSet<super::source::SourceId>>,
+        precedence: &[super::config::SourcePrecedence],
+    ) -> Result<Option<Arc<ResolvedVariable>>> {
+        let cache_key = CacheKey::new(key, self.hash_scoped_context(context, precedence))
+            .with_filter(file_source_filter);
 
-        for snapshot in sorted_filtered {
-            if let Some(variable) = snapshot.variables.iter().find(|v| v.key.as_str() == key) {
-                resolved = Some(self.resolve_variable(
-                    variable,
-                    &snapshots,
-                    context,
-                    0,
-                    &mut Vec::new(),
-                )?);
-            }
+        if let Some(cached) = self.cache.get(&cache_key) {
+            return Ok(Some(cached));
         }
 
-        if let Some(ref var) = resolved {
-            self.cache.insert(cache_key, Arc::clone(var));
+        let snapshots = self.load_snapshots(registry).await?;
+        if self.resolution_config.read().type_check {
+            self.maybe_rebuild_graph(&snapshots)?;
         }
 
-        Ok(resolved)
+        self.resolve_among(
+            key,
+            context,
+            &snapshots,
+            file_source_filter,
+            CallOverrides {
+                precedence: Some(precedence),
+                overlay: None,
+            },
+            cache_key,
+        )
+    }
+
+    /// Resolves `key` among the file sources in `file_source_filter` and the
+    /// source types the precedence lists, caching the result under
+    /// `cache_key`. References see every snapshot in `snapshots` unless
+    /// `overrides` narrows the precedence.
+    fn resolve_among(
+        &self,
+        key: &str,
+        context: &super::workspace::WorkspaceContext,
+        snapshots: &[crate::source::SourceSnapshot],
+        file_source_filter: Option<&HashSet<super::source::SourceId>>,
+        overrides: CallOverrides<'_>,
+        cache_key: CacheKey,
+    ) -> Result<Option<Arc<ResolvedVariable>>> {
+        let mut config = self.resolution_config.read().clone();
+        if let Some(precedence) = overrides.precedence {
+            config.precedence = precedence.to_vec();
+        }
+
+        let filtered_refs = self.filter_snapshots_ref(snapshots, file_source_filter);
+        let type_filtered = filter_by_precedence(&filtered_refs, &config.precedence);
+        let sorted_filtered =
+            self.sort_snapshot_refs_with(&type_filtered, &config, overrides.overlay);
+
+        let mut pass = self.interpolation_pass();
+        self.begin_profile();
+        let Some(variable) =
+            self.winning_definition_with(key, &sorted_filtered, &config, overrides.overlay)
+        else {
+            return Ok(None);
+        };
+
+        let scoped;
+        let interpolated_against = if overrides.precedence.is_some() {
+            scoped = sorted_filtered
+                .iter()
+                .rev()
+                .map(|snapshot| (*snapshot).clone())
+                .collect::<Vec<_>>();
+            scoped.as_slice()
+        } else {
+            snapshots
+        };
+
+        let resolved = self.resolve_merged(
+            variable,
+            &sorted_filtered,
+            interpolated_against,
+            context,
+            &mut pass,
+        )?;
+
+        self.cache.insert(cache_key, Arc::clone(&resolved));
+        Ok(Some(resolved))
+    }
+
+    /// Like `resolve_with_filter`, but with `overlay` taking precedence over
+    /// every source and override layer, in interpolation too, for this call
+    /// only. Results are cached apart from those without the overlay.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn resolve_with_overlay(
+        &self,
+        key: &str,
+        context: &super::workspace::WorkspaceContext,
+        registry: &super::source::SourceRegistry,
+        file_source_filter: Option<&HashSet<super::source::SourceId>>,
+        overlay: &HashMap<CompactString, CompactString>,
+    ) -> Result<Option<Arc<ResolvedVariable>>> {
+        if overlay.is_empty() {
+            return self
+                .resolve_with_filter(key, context, registry, file_source_filter)
+                .await;
+        }
+
+        let cache_key = CacheKey::new(key, self.hash_overlay_context(context, overlay))
+            .with_filter(file_source_filter);
+
+        if let Some(cached) = self.cache.get(&cache_key) {
+            return Ok(Some(cached));
+        }
+
+        let loaded = self.load_snapshots(registry).await?;
+        if self.resolution_config.read().type_check {
+            self.maybe_rebuild_graph(&loaded)?;
+        }
+
+        let mut variables: Vec<_> = overlay
+            .iter()
+            .map(|(key, value)| {
+                crate::source::ParsedVariable::simple(
+                    key.clone(),
+                    value.clone(),
+                    crate::source::VariableSource::Memory,
+                )
+            })
+            .collect();
+        variables.sort_by(|a, b| a.key.cmp(&b.key));
+
+        // First in line so interpolation lookups find the overlay first.
+        let overlay_id = crate::source::SourceId::canonical(
+            crate::source::SourceType::Memory,
+            OVERLAY_SOURCE_NAME,
+        );
+        let mut snapshots = vec![crate::source::SourceSnapshot {
+            source_id: overlay_id.clone(),
+            source_type: crate::source::SourceType::Memory,
+            variables: variables.into(),
+            timestamp: Instant::now(),
+            version: None,
+        }];
+        snapshots.extend(loaded);
+
+        self.resolve_among(
+            key,
+            context,
+            &snapshots,
+            file_source_filter,
+            CallOverrides {
+                precedence: None,
+                overlay: Some(&overlay_id),
+            },
+            cache_key,
+        )
+    }
+
+    /// Resolves `keys` against a single load of the registry, returning one
+    /// entry per key in the same order. Each entry is what
+    /// `resolve_with_filter` would return for that key; when every key is
+    /// already cached the registry isn't loaded at all.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn resolve_batch(
+        &self,
+        keys: &[&str],
+        context: &super::workspace::WorkspaceContext,
+        registry: &super::source::SourceRegistry,
+        file_source_filter: Option<&HashSet<super::source::SourceId>>,
+    ) -> Result<Vec<Option<Arc<ResolvedVariable>>>> {
+        let context_hash = self.hash_context(context);
+        let mut results: Vec<_> = keys
+            .iter()
+            .map(|key| {
+                self.cache
+                    .get(&CacheKey::new(*key, context_hash).with_filter(file_source_filter))
+            })
+            .collect();
+        if results.iter().all(Option::is_some) {
+            return Ok(results);
+        }
+
+        let snapshots = self.load_snapshots(registry).await?;
+        let filtered_refs = self.filter_snapshots_ref(&snapshots, file_source_filter);
+        let type_filtered = self.filter_by_source_type(&filtered_refs);
+
+        if self.resolution_config.read().type_check {
+            self.maybe_rebuild_graph(&snapshots)?;
+        }
+
+        let sorted_filtered = self.sort_snapshot_refs_by_file_order(&type_filtered);
+        let mut pass = self.bulk_interpolation_pass();
+        self.begin_profile();
+
+        for (key, result) in keys.iter().zip(results.iter_mut()) {
+            if result.is_some() {
+                continue;
+            }
+
+            if let Some(variable) = self.winning_definition(key, &sorted_filtered) {
+                let resolved = self.resolve_merged(
+                    variable,
+                    &sorted_filtered,
+                    &snapshots,
+                    context,
+                    &mut pass,
+                )?;
+                let cache_key = CacheKey::new(*key, context_hash).with_filter(file_source_filter);
+                self.cache.insert(cache_key, Arc::clone(&resolved));
+                *result = Some(resolved);
+            }
+        }
+
+        Ok(results)
     }
 
     #[cfg_attr(feature = "async", must_be_async)]
@@ -730,7 +2138,7 @@ impl ResolutionEngine {
         registry: &super::source::SourceRegistry,
         file_source_filter: Option<&HashSet<super::source::SourceId>>,
     ) -> Result<Vec<Arc<ResolvedVariable>>> {
-        let snapshots = registry.load_all().await.map_err(AbundantisError::Source)?;
+        let snapshots = self.load_snapshots(registry).await?;
 
         let filtered_refs = self.filter_snapshots_ref(&snapshots, file_source_filter);
 
@@ -741,6 +2149,71 @@ impl ResolutionEngine {
         self.all_variables_inner(context, &snapshots, &filtered_refs)
     }
 
+    /// Resolves every variable visible through `file_source_filter` and stores
+    /// the winners in the cache under `context`, so later lookups with the same
+    /// context and filter are cache hits. Returns the number of keys cached.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn warm_with_filter(
+        &self,
+        context: &super::workspace::WorkspaceContext,
+        registry: &super::source::SourceRegistry,
+        file_source_filter: Option<&HashSet<super::source::SourceId>>,
+    ) -> Result<usize> {
+        let snapshots = self.load_snapshots(registry).await?;
+        let filtered_refs = self.filter_snapshots_ref(&snapshots, file_source_filter);
+        let type_filtered = self.filter_by_source_type(&filtered_refs);
+
+        if self.resolution_config.read().type_check {
+            self.maybe_rebuild_graph(&snapshots)?;
+        }
+
+        let sorted = self.sort_snapshot_refs_by_file_order(&type_filtered);
+        let mut winners: HashMap<&str, &super::source::ParsedVariable> = HashMap::new();
+        for snapshot in &sorted {
+            for variable in snapshot.variables.iter() {
+                winners.insert(variable.key.as_str(), variable);
+            }
+        }
+        if self.conflict_resolver.read().is_some() {
+            for (key, variable) in winners.iter_mut() {
+                if let Some(winner) = self.winning_definition(key, &sorted) {
+                    *variable = winner;
+                }
+            }
+        }
+
+        let context_hash = self.hash_context(context);
+        let mut pass = self.bulk_interpolation_pass();
+        self.begin_profile();
+        for variable in winners.values() {
+            let resolved =
+                self.resolve_merged(variable, &sorted, &snapshots, context, &mut pass)?;
+            let cache_key =
+                CacheKey::new(variable.key.clone(), context_hash).with_filter(file_source_filter);
+            self.cache.insert(cache_key, resolved);
+        }
+
+        Ok(winners.len())
+    }
+
+    /// Finds the winning variable for `key` without interpolating it.
+    #[cfg_attr(feature = "async", must_be_async)]
+    #[cfg_attr(not(feature = "async"), must_be_sync)]
+    pub async fn resolve_raw_with_filter(
+        &self,
+        key: &str,
+        registry: &super::source::SourceRegistry,
+        file_source_filter: Option<&HashSet<super::source::SourceId>>,
+    ) -> Result<Option<super::source::ParsedVariable>> {
+        let snapshots = self.load_snapshots(registry).await?;
+        let filtered_refs = self.filter_snapshots_ref(&snapshots, file_source_filter);
+        let type_filtered = self.filter_by_source_type(&filtered_refs);
+        let sorted_filtered = self.sort_snapshot_refs_by_file_order(&type_filtered);
+
+        Ok(self.winning_definition(key, &sorted_filtered).cloned())
+    }
+
     fn hash_context(&self, context: &super::workspace::WorkspaceContext) -> u64 {
         use ahash::AHasher;
         use std::hash::{Hash, Hasher};
@@ -755,19 +2228,62 @@ impl ResolutionEngine {
         hasher.finish()
     }
 
-    fn build_dependency_graph(&self, snapshots: &[crate::source::SourceSnapshot]) -> Result<()> {
-        let mut graph = self.graph.write();
-        graph.clear();
+    /// Cache namespace for values resolved under a non-default `precedence`.
+    fn hash_scoped_context(
+        &self,
+        context: &super::workspace::WorkspaceContext,
+        precedence: &[super::config::SourcePrecedence],
+    ) -> u64 {
+        use ahash::AHasher;
+        use std::hash::{Hash, Hasher};
 
-        for snapshot in snapshots {
-            for variable in snapshot.variables.iter() {
-                let references = self.find_variable_references(&variable.raw_value);
-                for ref_key in references {
-                    graph.add_edge(variable.key.clone(), ref_key, Some((0, 0)));
-                }
+        let mut hasher = AHasher::default();
+        self.hash_context(context).hash(&mut hasher);
+        "precedence".hash(&mut hasher);
+        precedence.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Cache namespace for values resolved under `overlay`.
+    fn hash_overlay_context(
+        &self,
+        context: &super::workspace::WorkspaceContext,
+        overlay: &HashMap<CompactString, CompactString>,
+    ) -> u64 {
+        use ahash::AHasher;
+        use std::hash::{Hash, Hasher};
+
+        let mut entries: Vec<_> = overlay.iter().collect();
+        entries.sort();
+
+        let mut hasher = AHasher::default();
+        self.hash_context(context).hash(&mut hasher);
+        "overlay".hash(&mut hasher);
+        entries.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn build_source_edges(&self, snapshot: &crate::source::SourceSnapshot) -> Vec<DependencyEdge> {
+        let mut edges = Vec::new();
+        for variable in snapshot.variables.iter().filter(|v| !v.is_literal) {
+            for ref_key in self.find_variable_references(&variable.raw_value) {
+                edges.push(DependencyEdge {
+                    from: variable.key.clone(),
+                    to: ref_key,
+                    span: Some((0, 0)),
+                });
             }
         }
+        self.edge_builds
+            .fetch_add(edges.len() as u64, Ordering::Relaxed);
+        edges
+    }
 
+    fn find_dependency_cycle(
+        &self,
+        snapshots: &[crate::source::SourceSnapshot],
+    ) -> Option<CompactString> {
+        let graph = self.graph.read();
         let mut visited = HashMap::new();
         let mut path = Vec::new();
         for snapshot in snapshots {
@@ -780,14 +2296,68 @@ impl ResolutionEngine {
                         .map(|k| k.as_str())
                         .collect::<Vec<_>>()
                         .join(" -> ");
-                    return Err(AbundantisError::CircularDependency {
-                        chain: format!("{} -> {}", chain, variable.key),
+                    return Some(compact_str::format_compact!(
+                        "{} -> {}",
+                        chain,
+                        variable.key
+                    ));
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Substitutes the references in `template` with the values of
+    /// `variables`. References to anything else follow
+    /// [`ResolutionConfig::on_undefined`](super::config::ResolutionConfig::on_undefined).
+    pub fn render_template(
+        &self,
+        template: &str,
+        variables: &[Arc<ResolvedVariable>],
+    ) -> Result<String> {
+        use crate::config::OnUndefined;
+
+        let on_undefined = self.resolution_config.read().on_undefined;
+        let mut germi = germi::Germi::with_config(germi::Config {
+            max_depth: self.interpolation_config.read().max_depth as usize,
+            ..Default::default()
+        });
+        for variable in variables {
+            germi.add_variable(variable.key.as_str(), variable.resolved_value.as_str());
+        }
+
+        let mut kept = Vec::new();
+        for ref_key in self.find_variable_references(template) {
+            if variables.iter().any(|v| v.key == ref_key) || !references_plainly(template, &ref_key)
+            {
+                continue;
+            }
+            match on_undefined {
+                OnUndefined::Keep => kept.push(ref_key),
+                OnUndefined::Error => {
+                    return Err(AbundantisError::UndefinedVariable {
+                        key: ref_key.to_string(),
                     });
                 }
+                OnUndefined::Empty => germi.add_variable(ref_key.as_str(), ""),
             }
         }
 
-        Ok(())
+        let escaped;
+        let template = if kept.is_empty() {
+            template
+        } else {
+            escaped = escape_references(template, &kept);
+            escaped.as_str()
+        };
+        germi
+            .interpolate(template)
+            .map(|rendered| rendered.into_owned())
+            .map_err(|e| AbundantisError::Config {
+                message: format!("Failed to render template: {}", e),
+                path: None,
+            })
     }
 
     fn find_variable_references(&self, value: &str) -> Vec<CompactString> {
@@ -816,6 +2386,7 @@ mod tests {
             enabled: true,
             hot_cache_size: 100,
             ttl: Duration::from_secs(60),
+            remote_ttl: None,
         };
 
         let cache = ResolutionCache::new(&config);
@@ -824,6 +2395,7 @@ mod tests {
         let key = CacheKey {
             key: CompactString::new("TEST"),
             context_hash: 123,
+            filter_hash: 0,
         };
 
         let var = Arc::new(ResolvedVariable {
@@ -842,6 +2414,1169 @@ mod tests {
 
         let retrieved = cache.get(&key).unwrap();
         assert_eq!(retrieved.key.as_str(), "TEST");
+
+        cache.clear();
+        assert!(cache.get(&key).is_none());
+        let expected = ResolutionCacheStats { hits: 1, misses: 1 };
+        assert_eq!(cache.stats(), expected);
+
+        cache.reset_stats();
+        assert_eq!(cache.stats(), ResolutionCacheStats::default());
+    }
+
+    #[test]
+    fn test_per_entry_ttl_expires_first() {
+        let config = super::super::config::CacheConfig {
+            ttl: Duration::from_secs(60),
+            remote_ttl: Some(Duration::from_millis(20)),
+            ..Default::default()
+        };
+        let cache = ResolutionCache::new(&config);
+
+        let var = |key: &str, source| {
+            Arc::new(ResolvedVariable {
+                key: CompactString::new(key),
+                raw_value: CompactString::new("value"),
+                resolved_value: CompactString::new("value"),
+                source,
+                description: None,
+                has_warnings: false,
+                interpolation_depth: 0,
+            })
+        };
+        let file_key = CacheKey::new("FILE_VALUE", 1);
+        let remote_key = CacheKey::new("REMOTE_VALUE", 1);
+        let short_key = CacheKey::new("SHORT_VALUE", 1);
+
+        cache.insert(
+            file_key.clone(),
+            var("FILE_VALUE", super::super::source::VariableSource::Memory),
+        );
+        cache.insert(
+            remote_key.clone(),
+            var(
+                "REMOTE_VALUE",
+                super::super::source::VariableSource::Remote {
+                    provider: CompactString::new("vault"),
+                    path: None,
+                },
+            ),
+        );
+        cache.insert_with_ttl(
+            short_key.clone(),
+            var("SHORT_VALUE", super::super::source::VariableSource::Memory),
+            Duration::from_millis(20),
+        );
+        assert!(cache.get(&remote_key).is_some());
+        assert!(cache.get(&short_key).is_some());
+
+        std::thread::sleep(Duration::from_millis(40));
+
+        assert!(cache.get(&file_key).is_some());
+        assert!(cache.get(&remote_key).is_none());
+        assert!(cache.get(&short_key).is_none());
+    }
+
+    #[test]
+    fn test_filters_classify_by_source_type() {
+        let engine = ResolutionEngine::new(
+            &super::super::config::ResolutionConfig {
+                precedence: vec![super::super::config::SourcePrecedence::Shell],
+                ..Default::default()
+            },
+            &super::super::config::InterpolationConfig::default(),
+            &super::super::config::CacheConfig::default(),
+        );
+
+        let snapshot = |id: &str, source_type| crate::source::SourceSnapshot {
+            source_id: crate::source::SourceId::new(id),
+            source_type,
+            variables: Vec::new().into(),
+            timestamp: Instant::now(),
+            version: None,
+        };
+        // A custom in-memory source whose id happens to look like a file.
+        let snapshots = vec![
+            snapshot("file:/repo/.env", crate::source::SourceType::File),
+            snapshot("file:custom-cache", crate::source::SourceType::Memory),
+        ];
+        let active: HashSet<_> = [crate::source::SourceId::new("file:/other/.env")].into();
+
+        let filtered = engine.filter_snapshots_ref(&snapshots, Some(&active));
+        let ids: Vec<_> = filtered.iter().map(|s| s.source_id.as_str()).collect();
+        assert_eq!(ids, vec!["file:custom-cache"]);
+
+        let all: Vec<_> = snapshots.iter().collect();
+        let by_type = engine.filter_by_source_type(&all);
+        let ids: Vec<_> = by_type.iter().map(|s| s.source_id.as_str()).collect();
+        assert_eq!(ids, vec!["file:custom-cache"]);
+    }
+
+    #[test]
+    fn test_snapshot_order_by_source_type() {
+        let engine = ResolutionEngine::new(
+            &super::super::config::ResolutionConfig::default(),
+            &super::super::config::InterpolationConfig::default(),
+            &super::super::config::CacheConfig::default(),
+        );
+
+        use crate::source::SourceType;
+
+        let snapshot = |id: &str, source_type| crate::source::SourceSnapshot {
+            source_id: crate::source::SourceId::new(id),
+            source_type,
+            variables: Vec::new().into(),
+            timestamp: Instant::now(),
+            version: None,
+        };
+        let snapshots = vec![
+            snapshot("file:/repo/.env.local", SourceType::File),
+            snapshot("remote:vault", SourceType::Remote),
+            snapshot("memory:default", SourceType::Memory),
+            snapshot("file:/repo/.env", SourceType::File),
+            snapshot("shell:process", SourceType::Shell),
+            snapshot("remote:doppler", SourceType::Remote),
+        ];
+
+        let sorted: Vec<_> = engine
+            .sort_snapshots_by_file_order(&snapshots)
+            .iter()
+            .map(|s| s.source_id.as_str())
+            .collect();
+
+        assert_eq!(
+            sorted,
+            vec![
+                "memory:default",
+                "remote:doppler",
+                "remote:vault",
+                "file:/repo/.env",
+                "file:/repo/.env.local",
+                "shell:process",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_interpolates_thousands_of_chained_variables() {
+        // Each `BENCH_<i>` references `BENCH_<i / 2>`, so references fan in
+        // heavily. Resolving them shares one germi instance and memoises
+        // each referenced value for the pass instead of rebuilding the
+        // reference chain (and a germi instance per link) for every key.
+        const COUNT: usize = 5_000;
+
+        let engine = ResolutionEngine::new(
+            &super::super::config::ResolutionConfig::default(),
+            &super::super::config::InterpolationConfig::default(),
+            &super::super::config::CacheConfig::default(),
+        );
+
+        let mut variables = vec![crate::source::ParsedVariable {
+            key: CompactString::new("BENCH_BASE"),
+            raw_value: CompactString::new("svc"),
+            source: crate::source::VariableSource::Memory,
+            description: None,
+            is_commented: false,
+            is_literal: false,
+        }];
+        let mut expected = vec![String::from("svc")];
+        for i in 0..COUNT {
+            let (raw, value) = if i == 0 {
+                ("${BENCH_BASE}".to_string(), "svc".to_string())
+            } else {
+                (
+                    format!("${{BENCH_{}}}/{}", i / 2, i),
+                    format!("{}/{}", expected[i / 2 + 1], i),
+                )
+            };
+            variables.push(crate::source::ParsedVariable {
+                key: CompactString::new(format!("BENCH_{}", i)),
+                raw_value: CompactString::new(raw),
+                source: crate::source::VariableSource::Memory,
+                description: None,
+                is_commented: false,
+                is_literal: false,
+            });
+            expected.push(value);
+        }
+
+        let snapshots = vec![crate::source::SourceSnapshot {
+            source_id: crate::source::SourceId::new("memory:bench"),
+            source_type: crate::source::SourceType::Memory,
+            variables: variables.into(),
+            timestamp: Instant::now(),
+            version: None,
+        }];
+        let context = super::super::workspace::WorkspaceContext {
+            workspace_root: std::path::PathBuf::from("/repo"),
+            package_root: std::path::PathBuf::from("/repo"),
+            package_name: None,
+            env_files: Vec::new(),
+        };
+
+        let resolved = engine
+            .all_variables_inner(&context, &snapshots, &snapshots.iter().collect::<Vec<_>>())
+            .unwrap();
+
+        assert_eq!(resolved.len(), COUNT + 1);
+        for variable in &resolved {
+            let index = match variable.key.strip_prefix("BENCH_") {
+                Some("BASE") => 0,
+                Some(n) => n.parse::<usize>().unwrap() + 1,
+                None => unreachable!(),
+            };
+            assert_eq!(variable.resolved_value.as_str(), expected[index]);
+        }
+    }
+
+    #[test]
+    fn test_key_index_matches_linear_scan() {
+        const COUNT: usize = 10_000;
+
+        let engine = ResolutionEngine::new(
+            &super::super::config::ResolutionConfig::default(),
+            &super::super::config::InterpolationConfig::default(),
+            &super::super::config::CacheConfig::default(),
+        );
+        let variables: Vec<_> = (0..COUNT)
+            .map(|i| {
+                crate::source::ParsedVariable::simple(
+                    format!("INDEXED_{}", i),
+                    format!("value-{}", i),
+                    crate::source::VariableSource::Memory,
+                )
+            })
+            .collect();
+        let snapshots = vec![crate::source::SourceSnapshot {
+            source_id: crate::source::SourceId::new("memory:indexed"),
+            source_type: crate::source::SourceType::Memory,
+            variables: variables.into(),
+            timestamp: Instant::now(),
+            version: None,
+        }];
+        let snapshot = &snapshots[0];
+        let keys: Vec<String> = (0..100).map(|i| format!("INDEXED_{}", i * 97)).collect();
+
+        let linear: Vec<_> = keys
+            .iter()
+            .map(|key| {
+                snapshot
+                    .variables
+                    .iter()
+                    .rfind(|v| v.key == key.as_str())
+                    .map(|v| v.raw_value.as_str())
+            })
+            .collect();
+
+        let built = engine.key_index(snapshot);
+        let indexed: Vec<_> = keys
+            .iter()
+            .map(|key| engine.lookup(snapshot, key).map(|v| v.raw_value.as_str()))
+            .collect();
+        assert_eq!(indexed, linear);
+        // Built once and reused for as long as the variables stay the same.
+        assert!(Arc::ptr_eq(&built, &engine.key_index(snapshot)));
+        assert_eq!(engine.key_indexes.len(), 1);
+
+        let context = super::super::workspace::WorkspaceContext {
+            workspace_root: std::path::PathBuf::from("/repo"),
+            package_root: std::path::PathBuf::from("/repo"),
+            package_name: None,
+            env_files: Vec::new(),
+        };
+        for (i, key) in keys.iter().enumerate() {
+            let resolved = engine
+                .resolve_inner(key, &context, &snapshots)
+                .unwrap()
+                .unwrap();
+            assert_eq!(resolved.resolved_value, format!("value-{}", i * 97));
+        }
+        assert!(engine.lookup(snapshot, "INDEXED_MISSING").is_none());
+
+        engine.prune_key_indexes(&snapshots);
+        assert_eq!(engine.key_indexes.len(), 1);
+        engine.prune_key_indexes(&[]);
+        assert!(engine.key_indexes.is_empty());
+    }
+
+    #[test]
+    fn test_max_depth_error_reports_interpolation_chain() {
+        let engine = ResolutionEngine::new(
+            &super::super::config::ResolutionConfig::default(),
+            &super::super::config::InterpolationConfig {
+                max_depth: 3,
+                ..Default::default()
+            },
+            &super::super::config::CacheConfig::default(),
+        );
+
+        let snapshots = vec![crate::source::SourceSnapshot {
+            source_id: crate::source::SourceId::new("memory:default"),
+            source_type: crate::source::SourceType::Memory,
+            variables: [
+                ("DEPTH_A", "${DEPTH_B}"),
+                ("DEPTH_B", "${DEPTH_C}"),
+                ("DEPTH_C", "${DEPTH_D}"),
+                ("DEPTH_D", "${DEPTH_E}"),
+                ("DEPTH_E", "end"),
+            ]
+            .iter()
+            .map(|(key, value)| {
+                crate::source::ParsedVariable::simple(
+                    *key,
+                    *value,
+                    crate::source::VariableSource::Memory,
+                )
+            })
+            .collect::<Vec<_>>()
+            .into(),
+            timestamp: Instant::now(),
+            version: None,
+        }];
+        let context = super::super::workspace::WorkspaceContext {
+            workspace_root: std::path::PathBuf::from("/repo"),
+            package_root: std::path::PathBuf::from("/repo"),
+            package_name: None,
+            env_files: Vec::new(),
+        };
+
+        let shallow = engine
+            .resolve_inner("DEPTH_D", &context, &snapshots)
+            .unwrap()
+            .unwrap();
+        assert_eq!(shallow.resolved_value.as_str(), "end");
+
+        match engine.resolve_inner("DEPTH_A", &context, &snapshots) {
+            Err(AbundantisError::MaxDepthExceeded { key, depth, chain }) => {
+                assert_eq!(key, "DEPTH_C");
+                assert_eq!(depth, 3);
+                assert_eq!(chain, "DEPTH_A -> DEPTH_B -> DEPTH_C");
+            }
+            other => panic!("expected MaxDepthExceeded, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_bulk_resolution_truncates_past_max_depth() {
+        let engine = ResolutionEngine::new(
+            &super::super::config::ResolutionConfig::default(),
+            &super::super::config::InterpolationConfig {
+                max_depth: 3,
+                ..Default::default()
+            },
+            &super::super::config::CacheConfig::default(),
+        );
+
+        // Listed deepest first, so the bulk walk (last definition first)
+        // starts at the top of the chain.
+        let snapshots = vec![crate::source::SourceSnapshot {
+            source_id: crate::source::SourceId::new("memory"),
+            source_type: crate::source::SourceType::Memory,
+            variables: [
+                ("DEPTH_E", "end"),
+                ("DEPTH_D", "${DEPTH_E}"),
+                ("DEPTH_C", "${DEPTH_D}"),
+                ("DEPTH_B", "${DEPTH_C}"),
+                ("DEPTH_A", "${DEPTH_B}"),
+            ]
+            .iter()
+            .map(|(key, value)| {
+                crate::source::ParsedVariable::simple(
+                    *key,
+                    *value,
+                    crate::source::VariableSource::Memory,
+                )
+            })
+            .collect::<Vec<_>>()
+            .into(),
+            timestamp: Instant::now(),
+            version: None,
+        }];
+        let context = super::super::workspace::WorkspaceContext {
+            workspace_root: std::path::PathBuf::from("/repo"),
+            package_root: std::path::PathBuf::from("/repo"),
+            package_name: None,
+            env_files: Vec::new(),
+        };
+
+        assert!(matches!(
+            engine.resolve_inner("DEPTH_A", &context, &snapshots),
+            Err(AbundantisError::MaxDepthExceeded { .. })
+        ));
+
+        let refs: Vec<_> = snapshots.iter().collect();
+        let all = engine
+            .all_variables_inner(&context, &snapshots, &refs)
+            .unwrap();
+        let value = |key: &str| {
+            all.iter()
+                .find(|v| v.key == key)
+                .map(|v| v.resolved_value.to_string())
+        };
+        assert_eq!(all.len(), 5);
+        assert_eq!(value("DEPTH_E").as_deref(), Some("end"));
+        assert_eq!(value("DEPTH_D").as_deref(), Some("end"));
+        let truncated = value("DEPTH_A").unwrap();
+        assert!(truncated.contains("${DEPTH_"), "{}", truncated);
+    }
+
+    #[test]
+    fn test_repeated_reference_resolves_in_every_branch() {
+        let engine = Arc::new(ResolutionEngine::new(
+            &super::super::config::ResolutionConfig::default(),
+            &super::super::config::InterpolationConfig::default(),
+            &super::super::config::CacheConfig::default(),
+        ));
+
+        let parsed = crate::source::parse::parse_env(
+            "REPEAT_X=${REPEAT_A}-${REPEAT_A}\nREPEAT_A=${REPEAT_B}\nREPEAT_B=1\nREPEAT_Y=${REPEAT_X}+${REPEAT_A}\n",
+            std::path::Path::new("/repo/.env"),
+        );
+        let snapshots = Arc::new(vec![crate::source::SourceSnapshot {
+            source_id: crate::source::SourceId::new("file:/repo/.env"),
+            source_type: crate::source::SourceType::File,
+            variables: parsed.variables.into(),
+            timestamp: Instant::now(),
+            version: None,
+        }]);
+        let context = Arc::new(super::super::workspace::WorkspaceContext {
+            workspace_root: std::path::PathBuf::from("/repo"),
+            package_root: std::path::PathBuf::from("/repo"),
+            package_name: None,
+            env_files: vec![std::path::PathBuf::from("/repo/.env")],
+        });
+
+        let resolved = engine
+            .resolve_inner("REPEAT_X", &context, &snapshots)
+            .unwrap()
+            .unwrap();
+        assert_eq!(resolved.resolved_value.as_str(), "1-1");
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let engine = Arc::clone(&engine);
+                let snapshots = Arc::clone(&snapshots);
+                let context = Arc::clone(&context);
+                std::thread::spawn(move || {
+                    for _ in 0..50 {
+                        if i % 2 == 0 {
+                            engine.cache.clear();
+                        }
+                        let x = engine
+                            .resolve_inner("REPEAT_X", &context, &snapshots)
+                            .unwrap()
+                            .unwrap();
+                        let y = engine
+                            .resolve_inner("REPEAT_Y", &context, &snapshots)
+                            .unwrap()
+                            .unwrap();
+                        assert_eq!(x.resolved_value.as_str(), "1-1");
+                        assert_eq!(y.resolved_value.as_str(), "1-1+1");
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_forward_references_within_a_file() {
+        let engine = ResolutionEngine::new(
+            &super::super::config::ResolutionConfig::default(),
+            &super::super::config::InterpolationConfig::default(),
+            &super::super::config::CacheConfig::default(),
+        );
+
+        let parsed = crate::source::parse::parse_env(
+            "FORWARD_URL=${FORWARD_ORIGIN}/api\nFORWARD_ORIGIN=http://${FORWARD_HOST}\nFORWARD_A=${FORWARD_B}\nFORWARD_HOST=localhost\nFORWARD_B=1\nFORWARD_BACK=${FORWARD_B}\n",
+            std::path::Path::new("/repo/.env"),
+        );
+        let snapshots = vec![crate::source::SourceSnapshot {
+            source_id: crate::source::SourceId::new("file:/repo/.env"),
+            source_type: crate::source::SourceType::File,
+            variables: parsed.variables.into(),
+            timestamp: Instant::now(),
+            version: None,
+        }];
+        let context = super::super::workspace::WorkspaceContext {
+            workspace_root: std::path::PathBuf::from("/repo"),
+            package_root: std::path::PathBuf::from("/repo"),
+            package_name: None,
+            env_files: vec![std::path::PathBuf::from("/repo/.env")],
+        };
+        let expected = [
+            ("FORWARD_URL", "http://localhost/api"),
+            ("FORWARD_ORIGIN", "http://localhost"),
+            ("FORWARD_A", "1"),
+            ("FORWARD_HOST", "localhost"),
+            ("FORWARD_B", "1"),
+            ("FORWARD_BACK", "1"),
+        ];
+
+        for (key, value) in expected {
+            let resolved = engine
+                .resolve_inner(key, &context, &snapshots)
+                .unwrap()
+                .unwrap();
+            assert_eq!(resolved.resolved_value.as_str(), value, "{}", key);
+        }
+
+        engine.cache.clear();
+        let resolved = engine
+            .all_variables_inner(&context, &snapshots, &snapshots.iter().collect::<Vec<_>>())
+            .unwrap();
+        let values: Vec<_> = resolved
+            .iter()
+            .map(|v| (v.key.as_str(), v.resolved_value.as_str()))
+            .collect();
+        assert_eq!(values, expected);
+    }
+
+    #[test]
+    fn test_interpolation_depth_tracks_substitution_chain() {
+        let engine = ResolutionEngine::new(
+            &super::super::config::ResolutionConfig::default(),
+            &super::super::config::InterpolationConfig::default(),
+            &super::super::config::CacheConfig::default(),
+        );
+
+        let snapshots = vec![crate::source::SourceSnapshot {
+            source_id: crate::source::SourceId::new("memory:default"),
+            source_type: crate::source::SourceType::Memory,
+            variables: [
+                ("CHAIN_HOST", "localhost"),
+                ("CHAIN_ORIGIN", "http://${CHAIN_HOST}"),
+                ("CHAIN_URL", "${CHAIN_ORIGIN}/api"),
+            ]
+            .iter()
+            .map(|(key, value)| {
+                crate::source::ParsedVariable::simple(
+                    *key,
+                    *value,
+                    crate::source::VariableSource::Memory,
+                )
+            })
+            .collect::<Vec<_>>()
+            .into(),
+            timestamp: Instant::now(),
+            version: None,
+        }];
+        let context = super::super::workspace::WorkspaceContext {
+            workspace_root: std::path::PathBuf::from("/repo"),
+            package_root: std::path::PathBuf::from("/repo"),
+            package_name: None,
+            env_files: Vec::new(),
+        };
+        let resolve = |key: &str| {
+            engine
+                .resolve_inner(key, &context, &snapshots)
+                .unwrap()
+                .unwrap()
+        };
+
+        let literal = resolve("CHAIN_HOST");
+        assert_eq!(literal.interpolation_depth, 0);
+        assert!(!literal.is_interpolated());
+
+        let nested = resolve("CHAIN_URL");
+        assert_eq!(nested.resolved_value.as_str(), "http://localhost/api");
+        assert_eq!(nested.interpolation_depth, 2);
+        assert!(nested.is_interpolated());
+
+        // Same answer when the referenced value comes from the shared pass.
+        let resolved = engine
+            .all_variables_inner(&context, &snapshots, &snapshots.iter().collect::<Vec<_>>())
+            .unwrap();
+        let depths: Vec<_> = resolved
+            .iter()
+            .map(|v| (v.key.as_str(), v.interpolation_depth))
+            .collect();
+        assert_eq!(
+            depths,
+            vec![("CHAIN_HOST", 0), ("CHAIN_ORIGIN", 1), ("CHAIN_URL", 2)]
+        );
+    }
+
+    #[test]
+    fn test_package_scope_leaves_root_references_unresolved() {
+        let engine = ResolutionEngine::new(
+            &super::super::config::ResolutionConfig {
+                interpolation_scope: super::super::config::InterpolationScope::Package,
+                ..Default::default()
+            },
+            &super::super::config::InterpolationConfig::default(),
+            &super::super::config::CacheConfig::default(),
+        );
+
+        let snapshot = |path: &str, vars: &[(&str, &str)]| crate::source::SourceSnapshot {
+            source_id: crate::source::SourceId::new(format!("file:{}", path)),
+            source_type: crate::source::SourceType::File,
+            variables: vars
+                .iter()
+                .map(|(key, value)| crate::source::ParsedVariable {
+                    key: CompactString::new(key),
+                    raw_value: CompactString::new(value),
+                    source: crate::source::VariableSource::File {
+                        path: std::path::PathBuf::from(path),
+                        offset: 0,
+                    },
+                    description: None,
+                    is_commented: false,
+                    is_literal: false,
+                })
+                .collect::<Vec<_>>()
+                .into(),
+            timestamp: Instant::now(),
+            version: None,
+        };
+        let snapshots = vec![
+            snapshot("/repo/.env", &[("SCOPE_ROOT_HOST", "root.example.com")]),
+            snapshot(
+                "/repo/apps/web/.env",
+                &[
+                    ("SCOPE_PORT", "3000"),
+                    ("SCOPE_LOCAL_URL", "localhost:${SCOPE_PORT}"),
+                    ("SCOPE_ROOT_URL", "${SCOPE_ROOT_HOST}/api"),
+                ],
+            ),
+        ];
+        let context = super::super::workspace::WorkspaceContext {
+            workspace_root: std::path::PathBuf::from("/repo"),
+            package_root: std::path::PathBuf::from("/repo/apps/web"),
+            package_name: None,
+            env_files: Vec::new(),
+        };
+
+        let local = engine
+            .resolve_inner("SCOPE_LOCAL_URL", &context, &snapshots)
+            .unwrap()
+            .unwrap();
+        assert_eq!(local.resolved_value.as_str(), "localhost:3000");
+
+        let root = engine
+            .resolve_inner("SCOPE_ROOT_URL", &context, &snapshots)
+            .unwrap()
+            .unwrap();
+        assert_eq!(root.resolved_value.as_str(), "${SCOPE_ROOT_HOST}/api");
+    }
+
+    #[test]
+    fn test_on_undefined_modes() {
+        use super::super::config::OnUndefined;
+
+        let snapshots = vec![crate::source::SourceSnapshot {
+            source_id: crate::source::SourceId::new("memory:undefined"),
+            source_type: crate::source::SourceType::Memory,
+            variables: [
+                ("UNDEF_PLAIN", "${UNDEF_MISSING}/x"),
+                ("UNDEF_BARE", "$UNDEF_MISSING/x"),
+                ("UNDEF_FALLBACK", "${UNDEF_MISSING:-fallback}"),
+            ]
+            .iter()
+            .map(|(key, value)| {
+                crate::source::ParsedVariable::simple(
+                    *key,
+                    *value,
+                    crate::source::VariableSource::Memory,
+                )
+            })
+            .collect::<Vec<_>>()
+            .into(),
+            timestamp: Instant::now(),
+            version: None,
+        }];
+        let context = super::super::workspace::WorkspaceContext {
+            workspace_root: std::path::PathBuf::from("/repo"),
+            package_root: std::path::PathBuf::from("/repo"),
+            package_name: None,
+            env_files: Vec::new(),
+        };
+        let engine = |on_undefined| {
+            ResolutionEngine::new(
+                &super::super::config::ResolutionConfig {
+                    on_undefined,
+                    ..Default::default()
+                },
+                &super::super::config::InterpolationConfig::default(),
+                &super::super::config::CacheConfig::default(),
+            )
+        };
+        let resolve = |engine: &ResolutionEngine, key: &str| {
+            engine
+                .resolve_inner(key, &context, &snapshots)
+                .map(|v| v.unwrap().resolved_value.to_string())
+        };
+
+        let keep = engine(OnUndefined::Keep);
+        assert_eq!(resolve(&keep, "UNDEF_PLAIN").unwrap(), "${UNDEF_MISSING}/x");
+        assert_eq!(resolve(&keep, "UNDEF_BARE").unwrap(), "$UNDEF_MISSING/x");
+
+        let empty = engine(OnUndefined::Empty);
+        assert_eq!(resolve(&empty, "UNDEF_PLAIN").unwrap(), "/x");
+        assert_eq!(resolve(&empty, "UNDEF_BARE").unwrap(), "/x");
+        assert_eq!(resolve(&empty, "UNDEF_FALLBACK").unwrap(), "fallback");
+
+        let error = engine(OnUndefined::Error);
+        assert!(matches!(
+            resolve(&error, "UNDEF_PLAIN"),
+            Err(AbundantisError::UndefinedVariable { key }) if key == "UNDEF_MISSING"
+        ));
+        assert!(resolve(&error, "UNDEF_BARE").is_err());
+        assert_eq!(resolve(&error, "UNDEF_FALLBACK").unwrap(), "fallback");
+    }
+
+    #[test]
+    fn test_all_variables_picks_the_winning_definitions() {
+        let snapshot =
+            |id: &str, source_type, variables: &[(&str, &str)]| crate::source::SourceSnapshot {
+                source_id: crate::source::SourceId::new(id),
+                source_type,
+                variables: variables
+                    .iter()
+                    .map(|(key, value)| {
+                        crate::source::ParsedVariable::simple(
+                            *key,
+                            *value,
+                            crate::source::VariableSource::Memory,
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+                timestamp: Instant::now(),
+                version: None,
+            };
+        let snapshots = vec![
+            snapshot(
+                "file:/repo/.env",
+                crate::source::SourceType::File,
+                &[
+                    ("SHARED", "file"),
+                    ("REPEATED", "first"),
+                    ("REPEATED", "last"),
+                ],
+            ),
+            snapshot(
+                "shell:env",
+                crate::source::SourceType::Shell,
+                &[("SHARED", "shell")],
+            ),
+        ];
+        let context = super::super::workspace::WorkspaceContext {
+            workspace_root: std::path::PathBuf::from("/repo"),
+            package_root: std::path::PathBuf::from("/repo"),
+            package_name: None,
+            env_files: Vec::new(),
+        };
+        let engine = ResolutionEngine::new(
+            &super::super::config::ResolutionConfig::default(),
+            &super::super::config::InterpolationConfig::default(),
+            &super::super::config::CacheConfig::default(),
+        );
+
+        let all = engine
+            .all_variables_inner(&context, &snapshots, &snapshots.iter().collect::<Vec<_>>())
+            .unwrap();
+        let values: HashMap<_, _> = all
+            .iter()
+            .map(|v| (v.key.as_str(), v.resolved_value.as_str()))
+            .collect();
+        assert_eq!(all.len(), 2);
+        assert_eq!(values["SHARED"], "shell");
+        assert_eq!(values["REPEATED"], "last");
+
+        for variable in &all {
+            let resolved = engine
+                .resolve_inner(&variable.key, &context, &snapshots)
+                .unwrap()
+                .unwrap();
+            assert_eq!(resolved.resolved_value, variable.resolved_value);
+        }
+    }
+
+    #[test]
+    fn test_append_list_merge_joins_definitions() {
+        use super::super::config::MergeStrategy;
+
+        let snapshot = |id: &str, source_type, path: &str| crate::source::SourceSnapshot {
+            source_id: crate::source::SourceId::new(id),
+            source_type,
+            variables: vec![crate::source::ParsedVariable::simple(
+                "PATH",
+                path,
+                crate::source::VariableSource::Memory,
+            )]
+            .into(),
+            timestamp: Instant::now(),
+            version: None,
+        };
+        let snapshots = vec![
+            snapshot(
+                "file:/repo/.env",
+                crate::source::SourceType::File,
+                "/repo/bin",
+            ),
+            snapshot("shell:env", crate::source::SourceType::Shell, "/usr/bin"),
+        ];
+        let context = super::super::workspace::WorkspaceContext {
+            workspace_root: std::path::PathBuf::from("/repo"),
+            package_root: std::path::PathBuf::from("/repo"),
+            package_name: None,
+            env_files: Vec::new(),
+        };
+        let engine = |merge| {
+            ResolutionEngine::new(
+                &super::super::config::ResolutionConfig {
+                    merge,
+                    ..Default::default()
+                },
+                &super::super::config::InterpolationConfig::default(),
+                &super::super::config::CacheConfig::default(),
+            )
+        };
+
+        let overriding = engine(HashMap::new());
+        let resolved = overriding.resolve_inner("PATH", &context, &snapshots);
+        assert_eq!(resolved.unwrap().unwrap().resolved_value, "/usr/bin");
+
+        let appending = engine(HashMap::from([(
+            CompactString::new("PATH"),
+            MergeStrategy::AppendList {
+                separator: ":".to_string(),
+            },
+        )]));
+        let resolved = appending
+            .resolve_inner("PATH", &context, &snapshots)
+            .unwrap()
+            .unwrap();
+        assert_eq!(resolved.resolved_value, "/usr/bin:/repo/bin");
+        assert_eq!(resolved.raw_value, "/usr/bin:/repo/bin");
+
+        let all = appending
+            .all_variables_inner(&context, &snapshots, &snapshots.iter().collect::<Vec<_>>())
+            .unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].resolved_value, "/usr/bin:/repo/bin");
+    }
+
+    #[test]
+    fn test_append_list_merge_orders_parts_by_rank() {
+        use super::super::config::MergeStrategy;
+
+        let snapshot = |id: &str, source_type, path: &str| crate::source::SourceSnapshot {
+            source_id: crate::source::SourceId::new(id),
+            source_type,
+            variables: vec![crate::source::ParsedVariable::simple(
+                "PATH",
+                path,
+                crate::source::VariableSource::Memory,
+            )]
+            .into(),
+            timestamp: Instant::now(),
+            version: None,
+        };
+        let schema = snapshot(
+            "memory:schema",
+            crate::source::SourceType::Memory,
+            "/default",
+        );
+        let snapshots = vec![
+            snapshot(
+                "file:/repo/a/.env.paths",
+                crate::source::SourceType::File,
+                "/a",
+            ),
+            snapshot(
+                "file:/repo/b/.env.paths",
+                crate::source::SourceType::File,
+                "/b",
+            ),
+            snapshot("shell:env", crate::source::SourceType::Shell, "/usr/bin"),
+            schema.clone(),
+        ];
+        let context = super::super::workspace::WorkspaceContext {
+            workspace_root: std::path::PathBuf::from("/repo"),
+            package_root: std::path::PathBuf::from("/repo"),
+            package_name: None,
+            env_files: Vec::new(),
+        };
+        let engine = ResolutionEngine::new(
+            &super::super::config::ResolutionConfig {
+                merge: HashMap::from([(
+                    CompactString::new("PATH"),
+                    MergeStrategy::AppendList {
+                        separator: ":".to_string(),
+                    },
+                )]),
+                ..Default::default()
+            },
+            &super::super::config::InterpolationConfig::default(),
+            &super::super::config::CacheConfig::default(),
+        );
+        *engine.schema_defaults.write() = Some(schema);
+
+        let resolved = engine
+            .resolve_inner("PATH", &context, &snapshots)
+            .unwrap()
+            .unwrap();
+        assert_eq!(resolved.resolved_value, "/usr/bin:/b:/a");
+
+        // Picking the lower of the tied files doesn't move it to the front.
+        engine.set_conflict_resolver(Some(Arc::new(|_, _| 0)));
+        let resolved = engine
+            .resolve_inner("PATH", &context, &snapshots)
+            .unwrap()
+            .unwrap();
+        assert_eq!(resolved.resolved_value, "/usr/bin:/b:/a");
+    }
+
+    /// Wraps a `MemorySource`, counting how often the registry loads it.
+    struct CountingSource {
+        inner: crate::source::MemorySource,
+        loads: std::sync::atomic::AtomicUsize,
+    }
+
+    impl crate::source::EnvSource for CountingSource {
+        fn id(&self) -> &crate::source::SourceId {
+            self.inner.id()
+        }
+
+        fn source_type(&self) -> crate::source::SourceType {
+            self.inner.source_type()
+        }
+
+        fn priority(&self) -> crate::source::Priority {
+            self.inner.priority()
+        }
+
+        fn capabilities(&self) -> crate::source::SourceCapabilities {
+            self.inner.capabilities()
+        }
+
+        fn load(
+            &self,
+        ) -> std::result::Result<crate::source::SourceSnapshot, crate::error::SourceError> {
+            self.loads.fetch_add(1, Ordering::SeqCst);
+            self.inner.load()
+        }
+
+        fn has_changed(&self) -> bool {
+            self.inner.has_changed()
+        }
+
+        fn invalidate(&self) {
+            self.inner.invalidate()
+        }
+
+        fn fingerprint(&self) -> Option<u64> {
+            self.inner.fingerprint()
+        }
+    }
+
+    #[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+    #[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+    async fn test_resolve_batch_loads_sources_once() {
+        let source = Arc::new(CountingSource {
+            inner: crate::source::MemorySource::new(),
+            loads: std::sync::atomic::AtomicUsize::new(0),
+        });
+        source.inner.set("BATCH_HOST", "localhost");
+        source.inner.set("BATCH_PORT", "5432");
+        source.inner.set("BATCH_USER", "app");
+        source
+            .inner
+            .set("BATCH_URL", "${BATCH_USER}@${BATCH_HOST}:${BATCH_PORT}");
+
+        let registry = crate::source::SourceRegistry::new();
+        registry.register_sync(source.clone());
+        let context = super::super::workspace::WorkspaceContext {
+            workspace_root: std::path::PathBuf::from("/repo"),
+            package_root: std::path::PathBuf::from("/repo"),
+            package_name: None,
+            env_files: Vec::new(),
+        };
+        let new_engine = || {
+            ResolutionEngine::new(
+                &super::super::config::ResolutionConfig::default(),
+                &super::super::config::InterpolationConfig::default(),
+                &super::super::config::CacheConfig::default(),
+            )
+        };
+        let keys = [
+            "BATCH_URL",
+            "BATCH_HOST",
+            "BATCH_MISSING",
+            "BATCH_PORT",
+            "BATCH_USER",
+        ];
+
+        let engine = new_engine();
+        let batch = engine
+            .resolve_batch(&keys, &context, &registry, None)
+            .await
+            .unwrap();
+        assert_eq!(source.loads.load(Ordering::SeqCst), 1);
+
+        let engine = new_engine();
+        let mut individual = Vec::new();
+        for key in keys {
+            individual.push(engine.resolve(key, &context, &registry).await.unwrap());
+        }
+
+        let values = |results: &[Option<Arc<ResolvedVariable>>]| -> Vec<Option<String>> {
+            results
+                .iter()
+                .map(|r| r.as_ref().map(|v| v.resolved_value.to_string()))
+                .collect()
+        };
+        assert_eq!(values(&batch), values(&individual));
+        assert_eq!(values(&batch)[0].as_deref(), Some("app@localhost:5432"));
+    }
+
+    #[cfg(feature = "file")]
+    #[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+    #[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+    async fn test_cache_is_scoped_to_file_filter() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let registry = crate::source::SourceRegistry::new();
+        let mut ids = Vec::new();
+        for (name, value) in [
+            (".env.staging", "staging"),
+            (".env.production", "production"),
+        ] {
+            let path = dir.path().join(name);
+            std::fs::write(&path, format!("FILTER_STAGE={}\n", value)).unwrap();
+            ids.push(
+                registry.register_sync(Arc::new(crate::source::FileSource::new(&path).unwrap())),
+            );
+        }
+        let context = super::super::workspace::WorkspaceContext {
+            workspace_root: dir.path().to_path_buf(),
+            package_root: dir.path().to_path_buf(),
+            package_name: None,
+            env_files: Vec::new(),
+        };
+        let engine = ResolutionEngine::new(
+            &super::super::config::ResolutionConfig::default(),
+            &super::super::config::InterpolationConfig::default(),
+            &super::super::config::CacheConfig::default(),
+        );
+
+        let filters: Vec<HashSet<_>> = ids.iter().map(|id| [id.clone()].into()).collect();
+        let resolve =
+            |filter| engine.resolve_with_filter("FILTER_STAGE", &context, &registry, Some(filter));
+        let staging = resolve(&filters[0]).await.unwrap().unwrap();
+        let production = resolve(&filters[1]).await.unwrap().unwrap();
+
+        assert_eq!(staging.resolved_value.as_str(), "staging");
+        assert_eq!(production.resolved_value.as_str(), "production");
+        let cached = resolve(&filters[0]).await.unwrap().unwrap();
+        assert!(Arc::ptr_eq(&cached, &staging));
+    }
+
+    #[test]
+    fn test_to_dot_highlights_cycles() {
+        let mut graph = DependencyGraph::new();
+        graph.add_edge("URL".into(), "HOST".into(), None);
+        graph.add_edge("URL".into(), "HOST".into(), None);
+        graph.add_edge("A".into(), "B".into(), None);
+        graph.add_edge("B".into(), "A".into(), None);
+
+        assert_eq!(
+            graph.to_dot(),
+            r#"digraph env {
+    "URL" -> "HOST";
+    "A" -> "B" [color=red];
+    "B" -> "A" [color=red];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_graph_rebuild_only_touches_changed_sources() {
+        let engine = ResolutionEngine::new(
+            &super::super::config::ResolutionConfig::default(),
+            &super::super::config::InterpolationConfig::default(),
+            &super::super::config::CacheConfig::default(),
+        );
+        let snapshot =
+            |id: &str, version: u64, vars: &[(&str, &str)]| crate::source::SourceSnapshot {
+                source_id: crate::source::SourceId::new(id),
+                source_type: crate::source::SourceType::Memory,
+                variables: vars
+                    .iter()
+                    .map(|(key, value)| crate::source::ParsedVariable {
+                        key: CompactString::new(key),
+                        raw_value: CompactString::new(value),
+                        source: crate::source::VariableSource::Memory,
+                        description: None,
+                        is_commented: false,
+                        is_literal: false,
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+                timestamp: Instant::now(),
+                version: Some(version),
+            };
+        let edge_builds = || engine.edge_builds.load(Ordering::Relaxed);
+
+        let first = snapshot("memory:a", 1, &[("A", "${B}"), ("A2", "${B}")]);
+        let second = snapshot("memory:b", 1, &[("B", "plain"), ("C", "${D}")]);
+        engine
+            .maybe_rebuild_graph(&[first.clone(), second.clone()])
+            .unwrap();
+        assert_eq!(edge_builds(), 3);
+
+        // Unchanged versions build nothing.
+        engine
+            .maybe_rebuild_graph(&[first.clone(), second.clone()])
+            .unwrap();
+        assert_eq!(edge_builds(), 3);
+
+        // Only the changed source's single edge is rebuilt.
+        let second = snapshot("memory:b", 2, &[("B", "plain"), ("C", "${A}")]);
+        engine
+            .maybe_rebuild_graph(&[first.clone(), second])
+            .unwrap();
+        assert_eq!(edge_builds(), 4);
+        assert_eq!(engine.graph().read().get_dependencies("C"), vec!["A"]);
+        assert_eq!(engine.graph().read().get_dependencies("A"), vec!["B"]);
+
+        // A cycle spanning both sources is still caught, and reported
+        // again while nothing changes.
+        let second = snapshot("memory:b", 3, &[("B", "${A}")]);
+        let snapshots = [first.clone(), second];
+        assert!(matches!(
+            engine.maybe_rebuild_graph(&snapshots),
+            Err(AbundantisError::CircularDependency { .. })
+        ));
+        assert!(engine.maybe_rebuild_graph(&snapshots).is_err());
+        assert_eq!(edge_builds(), 5);
+
+        let second = snapshot("memory:b", 4, &[("B", "plain")]);
+        engine
+            .maybe_rebuild_graph(&[first.clone(), second.clone()])
+            .unwrap();
+        assert_eq!(edge_builds(), 5);
+
+        // Unversioned snapshots are rebuilt once their variables change,
+        // not merely because they carry no version.
+        let unversioned = |vars| crate::source::SourceSnapshot {
+            version: None,
+            ..snapshot("shell:env", 0, vars)
+        };
+        let shell = unversioned(&[("S", "${B}")]);
+        let snapshots = [first.clone(), second.clone(), shell.clone()];
+        engine.maybe_rebuild_graph(&snapshots).unwrap();
+        engine.maybe_rebuild_graph(&snapshots).unwrap();
+        assert_eq!(edge_builds(), 6);
+
+        let shell = unversioned(&[("S", "${A}")]);
+        engine.maybe_rebuild_graph(&[first, second, shell]).unwrap();
+        assert_eq!(edge_builds(), 7);
+        assert_eq!(engine.graph().read().get_dependencies("S"), vec!["A"]);
     }
 
     #[test]
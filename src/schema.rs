@@ -0,0 +1,116 @@
+//! Declarations files such as `.env.example` or `.env.schema`.
+//!
+//! A declarations file lists the variables a project expects, in the usual
+//! `KEY=value` syntax. A non-empty value is the key's default, used when no
+//! source defines it; the comment lines directly above a declaration become
+//! its description.
+
+use crate::error::{AbundantisError, Result};
+use crate::source::parse::{parse_env, strip_bom};
+use crate::source::{ParsedVariable, SourceId, SourceSnapshot, SourceType};
+use compact_str::CompactString;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Modification time and length of a file, `None` if it couldn't be read.
+type FileFingerprint = Option<(Option<std::time::SystemTime>, u64)>;
+
+#[derive(Debug, Clone)]
+pub struct EnvSchema {
+    path: PathBuf,
+    defaults: Arc<[ParsedVariable]>,
+    descriptions: HashMap<CompactString, CompactString>,
+    /// The file as it was when loaded; `None` when parsed from a string.
+    loaded_from: Option<FileFingerprint>,
+}
+
+impl EnvSchema {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| AbundantisError::Config {
+            message: format!("Failed to read schema file: {}", e),
+            path: Some(path.to_path_buf()),
+        })?;
+        Ok(Self {
+            loaded_from: Some(fingerprint(path)),
+            ..Self::parse(&content, path)
+        })
+    }
+
+    /// Whether the file this schema was loaded from changed since.
+    pub fn has_changed(&self) -> bool {
+        self.loaded_from
+            .is_some_and(|loaded| loaded != fingerprint(&self.path))
+    }
+
+    /// Parses declarations from `content`; `path` is recorded as the source
+    /// of every default.
+    pub fn parse(content: &str, path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let content = strip_bom(content);
+        let parsed = parse_env(content, path);
+
+        let mut defaults = Vec::new();
+        let mut descriptions = HashMap::new();
+        for variable in parsed.variables {
+            if variable.is_commented {
+                continue;
+            }
+
+            if let Some(description) = &variable.description {
+                descriptions.insert(variable.key.clone(), description.clone());
+            }
+
+            if !variable.raw_value.is_empty() {
+                defaults.push(variable);
+            }
+        }
+
+        Self {
+            path: path.to_path_buf(),
+            defaults: defaults.into(),
+            descriptions,
+            loaded_from: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn default_value(&self, key: &str) -> Option<&str> {
+        self.defaults
+            .iter()
+            .find(|v| v.key == key)
+            .map(|v| v.raw_value.as_str())
+    }
+
+    pub fn description(&self, key: &str) -> Option<&str> {
+        self.descriptions.get(key).map(|d| d.as_str())
+    }
+
+    pub(crate) fn descriptions(&self) -> &HashMap<CompactString, CompactString> {
+        &self.descriptions
+    }
+
+    /// The declared defaults as a snapshot the resolution engine ranks below
+    /// every real source. Its id, `memory:schema:<path>`, is reserved.
+    pub(crate) fn defaults_snapshot(&self) -> SourceSnapshot {
+        SourceSnapshot {
+            source_id: SourceId::canonical(
+                SourceType::Memory,
+                format_args!("schema:{}", self.path.display()),
+            ),
+            source_type: SourceType::Memory,
+            variables: Arc::clone(&self.defaults),
+            timestamp: std::time::Instant::now(),
+            version: None,
+        }
+    }
+}
+
+fn fingerprint(path: &Path) -> FileFingerprint {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok(), metadata.len()))
+}
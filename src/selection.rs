@@ -1,37 +1,41 @@
+use crate::config::SelectionConfig;
 use crate::path_cache::PathCache;
 use crate::workspace::{PackageInfo, WorkspaceManager};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-const AUTO_DISCOVERY_PRIORITY: &[&str] = &[
-    ".env.local",
-    ".env.development",
-    ".env.dev",
-    ".env",
-    ".env.test",
-    ".env.staging",
-    ".env.production",
-    ".env.prod",
-];
+/// Files layered on top of each other when no environment is configured,
+/// lowest precedence first.
+const AUTO_DISCOVERY_LAYERS: &[&str] = &[".env", ".env.local"];
 
 pub struct ActiveFileSelector {
     workspace_root: PathBuf,
     path_cache: Arc<PathCache>,
+    config: SelectionConfig,
 }
 
 impl ActiveFileSelector {
     pub fn new(workspace_root: &Path, path_cache: Arc<PathCache>) -> Self {
+        Self::with_config(workspace_root, path_cache, SelectionConfig::default())
+    }
+
+    pub fn with_config(
+        workspace_root: &Path,
+        path_cache: Arc<PathCache>,
+        config: SelectionConfig,
+    ) -> Self {
         Self {
             workspace_root: workspace_root.to_path_buf(),
             path_cache,
+            config,
         }
     }
 
     pub fn resolve_patterns(&self, base_dir: &Path, patterns: &[String]) -> Vec<PathBuf> {
         let mut result = Vec::new();
 
-        for pattern in patterns {
+        for pattern in patterns.iter().flat_map(|pattern| expand_braces(pattern)) {
             let full_pattern = if pattern.starts_with('/') || pattern.starts_with("./") {
                 pattern.clone()
             } else {
@@ -59,6 +63,10 @@ impl ActiveFileSelector {
                     let mut matches: Vec<PathBuf> = entries
                         .filter_map(|entry| entry.ok())
                         .filter(|path| path.is_file())
+                        .filter(|path| {
+                            self.config.match_dotfiles
+                                || !wildcard_matched_dotfile(&pattern_str, path)
+                        })
                         .collect();
 
                     if matches.is_empty() {
@@ -96,26 +104,51 @@ impl ActiveFileSelector {
         let is_monorepo = packages.len() > 1 || package_root != self.workspace_root;
 
         if is_monorepo {
-            for env_file_name in AUTO_DISCOVERY_PRIORITY {
-                let root_env_path = self.workspace_root.join(env_file_name);
-                if root_env_path.exists() {
-                    result.push(root_env_path);
-                    break;
-                }
-            }
+            result.extend(self.discover_in_directory(&self.workspace_root));
         }
 
-        for env_file_name in AUTO_DISCOVERY_PRIORITY {
-            let package_env_path = package_root.join(env_file_name);
-            if package_env_path.exists() {
-                result.push(package_env_path);
-                break;
-            }
-        }
+        result.extend(self.discover_in_directory(package_root));
 
         result
     }
 
+    /// Every existing file of the conventional layering is active, lowest
+    /// precedence first: `.env` and `.env.local`, followed by the configured
+    /// environment's own files. Without an environment and with neither base
+    /// file present, the first existing file in
+    /// [`SelectionConfig::auto_priority`] is used.
+    fn discover_in_directory(&self, dir: &Path) -> Vec<PathBuf> {
+        let environment_files = self.config.environment_files();
+        let layers: Vec<&str> = if environment_files.is_empty() {
+            AUTO_DISCOVERY_LAYERS.to_vec()
+        } else {
+            environment_files.iter().map(|name| name.as_str()).collect()
+        };
+
+        let layered: Vec<PathBuf> = layers
+            .iter()
+            .map(|name| dir.join(name))
+            .filter(|path| path.exists())
+            .collect();
+        if !layered.is_empty() || !environment_files.is_empty() {
+            return layered;
+        }
+
+        self.config
+            .auto_priority
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.exists())
+            .into_iter()
+            .collect()
+    }
+
+    /// Env files active for `file_path`.
+    ///
+    /// The closest directory-scoped override containing `file_path` wins
+    /// outright; otherwise the global patterns apply, and with neither (or an
+    /// empty pattern list) files are auto-discovered from the package root.
+    /// See [`merge_active_files`] for how the two selections combine.
     pub fn compute_active_files(
         &self,
         file_path: &Path,
@@ -125,23 +158,23 @@ impl ActiveFileSelector {
     ) -> Vec<PathBuf> {
         let canonical_file = self.path_cache.canonicalize(file_path);
 
-        let mut result = Vec::new();
+        let mut global = Vec::new();
 
         if let Some(patterns) = global_patterns {
             if patterns.is_empty() {
                 let context = workspace.context_for_file(file_path);
                 if let Some(ctx) = context {
-                    result.extend(
+                    global.extend(
                         self.auto_discover_files(&ctx.package_root, workspace.packages().to_vec()),
                     );
                 }
             } else {
-                result.extend(self.resolve_patterns(&self.workspace_root, patterns));
+                global.extend(self.resolve_patterns(&self.workspace_root, patterns));
             }
         } else {
             let context = workspace.context_for_file(file_path);
             if let Some(ctx) = context {
-                result.extend(self.auto_discover_files(&ctx.package_root, workspace.packages()));
+                global.extend(self.auto_discover_files(&ctx.package_root, workspace.packages()));
             }
         }
 
@@ -160,21 +193,116 @@ impl ActiveFileSelector {
             }
         }
 
-        if let Some((scope_dir, patterns)) = best_match {
+        let scoped = best_match.map(|(scope_dir, patterns)| {
             if patterns.is_empty() {
-                let context = workspace.context_for_file(scope_dir);
-                if let Some(ctx) = context {
-                    result.extend(
-                        self.auto_discover_files(&ctx.package_root, workspace.packages().to_vec()),
-                    );
-                }
+                workspace
+                    .context_for_file(scope_dir)
+                    .map(|ctx| {
+                        self.auto_discover_files(&ctx.package_root, workspace.packages().to_vec())
+                    })
+                    .unwrap_or_default()
             } else {
-                result.extend(self.resolve_patterns(scope_dir, &patterns));
+                self.resolve_patterns(scope_dir, &patterns)
             }
-        }
+        });
+
+        // Different spellings of one file (`apps/../.env`, symlinked
+        // directories) would otherwise register and load it twice.
+        let mut seen = std::collections::HashSet::new();
+        merge_active_files(global, scoped)
+            .into_iter()
+            .filter(|path| seen.insert(self.path_cache.canonicalize(path)))
+            .collect()
+    }
+}
 
-        result
+/// Combines the globally selected files with those of a directory-scoped
+/// override. The override, when present, replaces the global selection
+/// rather than adding to it. Duplicates are dropped, keeping the first
+/// occurrence so precedence order is preserved.
+pub fn merge_active_files(global: Vec<PathBuf>, scoped: Option<Vec<PathBuf>>) -> Vec<PathBuf> {
+    let mut seen = std::collections::HashSet::new();
+    scoped
+        .unwrap_or(global)
+        .into_iter()
+        .filter(|path| seen.insert(path.clone()))
+        .collect()
+}
+
+/// Expands shell-style brace alternatives, so `.env.{local,development}`
+/// becomes `.env.local` and `.env.development`. Groups may nest; `\{`, `\}`
+/// and `\,` are literal, and a group without a comma is left as-is.
+fn expand_braces(pattern: &str) -> Vec<String> {
+    let Some((open, separators)) = find_brace_group(pattern) else {
+        return vec![pattern
+            .replace("\\{", "{")
+            .replace("\\}", "}")
+            .replace("\\,", ",")];
+    };
+
+    let close = separators[separators.len() - 1];
+    let prefix = &pattern[..open];
+    let suffix = &pattern[close + 1..];
+    let mut start = open + 1;
+    let mut expanded = Vec::new();
+    for &end in &separators {
+        let alternative = format!("{}{}{}", prefix, &pattern[start..end], suffix);
+        expanded.extend(expand_braces(&alternative));
+        start = end + 1;
+    }
+    expanded
+}
+
+/// Finds the first brace group with at least one top-level comma. Returns the
+/// position of its `{` and of every top-level `,` followed by the closing `}`.
+fn find_brace_group(pattern: &str) -> Option<(usize, Vec<usize>)> {
+    let bytes = pattern.as_bytes();
+    let mut open = 0;
+    while open < bytes.len() {
+        match bytes[open] {
+            b'\\' => open += 1,
+            b'{' => {
+                let mut depth = 0;
+                let mut separators = Vec::new();
+                let mut i = open;
+                while i < bytes.len() {
+                    match bytes[i] {
+                        b'\\' => i += 1,
+                        b'{' => depth += 1,
+                        b',' if depth == 1 => separators.push(i),
+                        b'}' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                if !separators.is_empty() {
+                                    separators.push(i);
+                                    return Some((open, separators));
+                                }
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    i += 1;
+                }
+            }
+            _ => {}
+        }
+        open += 1;
     }
+    None
+}
+
+/// Whether `path` is a dotfile that `pattern` only matched through a
+/// wildcard. Checked after globbing because `glob`'s own
+/// `require_literal_leading_dot` also drops dotfiles named literally, such as
+/// `.env*`.
+fn wildcard_matched_dotfile(pattern: &str, path: &Path) -> bool {
+    let is_dotfile = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with('.'));
+    let file_pattern = pattern.rsplit('/').next().unwrap_or(pattern);
+    is_dotfile && !file_pattern.starts_with('.')
 }
 
 #[cfg(test)]
@@ -238,6 +366,77 @@ mod tests {
         assert_eq!(result[0], env_local);
     }
 
+    #[test]
+    fn test_auto_discovery_custom_priority() {
+        let temp_dir = setup_test_workspace();
+        let workspace_root = temp_dir.path();
+
+        fs::write(workspace_root.join(".env.development"), "TEST=dev").unwrap();
+        let env_ci = workspace_root.join(".env.ci");
+        fs::write(&env_ci, "TEST=ci").unwrap();
+
+        let selector = ActiveFileSelector::with_config(
+            workspace_root,
+            Arc::new(PathCache::new()),
+            SelectionConfig {
+                auto_priority: vec![".env.ci".into(), ".env.development".into()],
+                ..Default::default()
+            },
+        );
+        let packages = vec![PackageInfo {
+            name: Some(CompactString::new("root")),
+            root: workspace_root.to_path_buf(),
+            relative_path: CompactString::new("."),
+        }];
+
+        let result = selector.auto_discover_files(workspace_root, packages);
+        assert_eq!(result, vec![env_ci]);
+    }
+
+    #[test]
+    fn test_auto_discovery_layers_local_over_base() {
+        let temp_dir = setup_test_workspace();
+        let workspace_root = temp_dir.path();
+
+        let env = workspace_root.join(".env");
+        fs::write(&env, "TEST=base").unwrap();
+        let env_local = workspace_root.join(".env.local");
+        fs::write(&env_local, "TEST=local").unwrap();
+        fs::write(workspace_root.join(".env.development"), "TEST=dev").unwrap();
+
+        let path_cache = Arc::new(PathCache::new());
+        let selector = ActiveFileSelector::new(workspace_root, path_cache);
+        let packages = vec![PackageInfo {
+            name: Some(CompactString::new("root")),
+            root: workspace_root.to_path_buf(),
+            relative_path: CompactString::new("."),
+        }];
+
+        let result = selector.auto_discover_files(workspace_root, packages.clone());
+        assert_eq!(result, vec![env.clone(), env_local.clone()]);
+
+        let env_development_local = workspace_root.join(".env.development.local");
+        fs::write(&env_development_local, "TEST=dev-local").unwrap();
+        let selector = ActiveFileSelector::with_config(
+            workspace_root,
+            Arc::new(PathCache::new()),
+            SelectionConfig {
+                environment: Some(CompactString::new("development")),
+                ..Default::default()
+            },
+        );
+        let result = selector.auto_discover_files(workspace_root, packages);
+        assert_eq!(
+            result,
+            vec![
+                env,
+                env_local,
+                workspace_root.join(".env.development"),
+                env_development_local,
+            ]
+        );
+    }
+
     #[test]
     fn test_auto_discovery_monorepo() {
         let temp_dir = setup_test_workspace();
@@ -310,4 +509,141 @@ mod tests {
         assert_eq!(result[0], env_a);
         assert_eq!(result[1], env_b);
     }
+
+    #[test]
+    fn test_wildcard_matches_dotfiles_only_when_enabled() {
+        let temp_dir = setup_test_workspace();
+        let workspace_root = temp_dir.path();
+
+        let env = workspace_root.join(".env");
+        fs::write(&env, "TEST=1").unwrap();
+        let visible = workspace_root.join("app.env");
+        fs::write(&visible, "TEST=2").unwrap();
+
+        let path_cache = Arc::new(PathCache::new());
+        let selector = ActiveFileSelector::new(workspace_root, Arc::clone(&path_cache));
+        let result = selector.resolve_patterns(workspace_root, &["*".to_string()]);
+        assert_eq!(result, vec![visible.clone()]);
+
+        let selector = ActiveFileSelector::with_config(
+            workspace_root,
+            path_cache,
+            SelectionConfig {
+                match_dotfiles: true,
+                ..Default::default()
+            },
+        );
+        let result = selector.resolve_patterns(workspace_root, &["*".to_string()]);
+        assert_eq!(result, vec![env, visible]);
+    }
+
+    #[test]
+    fn test_expand_braces() {
+        assert_eq!(
+            expand_braces(".env.{local,development}"),
+            vec![".env.local", ".env.development"]
+        );
+        assert_eq!(
+            expand_braces(".env{,.{dev,prod}{,.local}}"),
+            vec![
+                ".env",
+                ".env.dev",
+                ".env.dev.local",
+                ".env.prod",
+                ".env.prod.local"
+            ]
+        );
+        assert_eq!(expand_braces(".env.{local}"), vec![".env.{local}"]);
+        assert_eq!(expand_braces(".env.\\{a,b\\}"), vec![".env.{a,b}"]);
+        assert_eq!(expand_braces(".env.{a\\,b,c}"), vec![".env.a,b", ".env.c"]);
+        assert_eq!(expand_braces(".env.{a,b"), vec![".env.{a,b"]);
+    }
+
+    #[test]
+    fn test_resolve_patterns_brace_expansion() {
+        let temp_dir = setup_test_workspace();
+        let workspace_root = temp_dir.path();
+
+        let env_local = workspace_root.join(".env.local");
+        fs::write(&env_local, "TEST=local").unwrap();
+        let env_dev = workspace_root.join(".env.development");
+        fs::write(&env_dev, "TEST=dev").unwrap();
+        fs::write(workspace_root.join(".env.production"), "TEST=prod").unwrap();
+
+        let path_cache = Arc::new(PathCache::new());
+        let selector = ActiveFileSelector::new(workspace_root, path_cache);
+        let result =
+            selector.resolve_patterns(workspace_root, &[".env.{local,development}".to_string()]);
+
+        assert_eq!(result, vec![env_local, env_dev]);
+    }
+
+    #[test]
+    fn test_merge_active_files() {
+        let env = PathBuf::from("/repo/.env");
+        let local = PathBuf::from("/repo/.env.local");
+        let scoped = PathBuf::from("/repo/apps/web/.env");
+
+        // Global only: kept in order, duplicates dropped.
+        assert_eq!(
+            merge_active_files(vec![env.clone(), local.clone(), env.clone()], None),
+            vec![env.clone(), local.clone()]
+        );
+
+        // Directory override only.
+        assert_eq!(
+            merge_active_files(Vec::new(), Some(vec![scoped.clone()])),
+            vec![scoped.clone()]
+        );
+
+        // Overlap: the override replaces the global selection.
+        assert_eq!(
+            merge_active_files(
+                vec![env.clone(), local.clone()],
+                Some(vec![scoped.clone(), env.clone(), scoped.clone()])
+            ),
+            vec![scoped, env]
+        );
+    }
+
+    #[test]
+    fn test_compute_active_files_directory_override_replaces_global() {
+        let temp_dir = setup_test_workspace();
+        let workspace_root = temp_dir.path();
+
+        let root_env = workspace_root.join(".env");
+        fs::write(&root_env, "TEST=root").unwrap();
+        let app_dir = workspace_root.join("packages/app1");
+        let app_env = app_dir.join(".env.app");
+        fs::write(&app_env, "TEST=app").unwrap();
+
+        let workspace = WorkspaceManager::with_root(
+            workspace_root.to_path_buf(),
+            &crate::config::WorkspaceConfig {
+                provider: Some(crate::config::MonorepoProviderType::Custom),
+                roots: vec![".".into()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let selector = ActiveFileSelector::new(workspace_root, Arc::new(PathCache::new()));
+        let global = vec![".env".to_string(), ".env".to_string()];
+        let scoped: HashMap<_, _> = [(app_dir.clone(), vec![".env.app".to_string()])].into();
+
+        let outside = selector.compute_active_files(
+            &workspace_root.join("index.js"),
+            Some(&global),
+            &scoped,
+            &workspace,
+        );
+        assert_eq!(outside, vec![root_env]);
+
+        let inside = selector.compute_active_files(
+            &app_dir.join("index.js"),
+            Some(&global),
+            &scoped,
+            &workspace,
+        );
+        assert_eq!(inside, vec![app_env]);
+    }
 }
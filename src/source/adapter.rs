@@ -0,0 +1,91 @@
+use super::traits::*;
+use crate::error::SourceError;
+use std::sync::Arc;
+
+/// Exposes a synchronous [`EnvSource`] through the [`AsyncEnvSource`] interface.
+///
+/// `load` runs on tokio's blocking pool so that file or process reads never stall
+/// the async executor.
+pub struct AsyncAdapter<S: EnvSource + 'static> {
+    inner: Arc<S>,
+}
+
+impl<S: EnvSource + 'static> AsyncAdapter<S> {
+    pub fn new(source: S) -> Self {
+        Self {
+            inner: Arc::new(source),
+        }
+    }
+
+    pub fn from_arc(source: Arc<S>) -> Self {
+        Self { inner: source }
+    }
+
+    pub fn inner(&self) -> &Arc<S> {
+        &self.inner
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: EnvSource + 'static> AsyncEnvSource for AsyncAdapter<S> {
+    fn id(&self) -> &SourceId {
+        self.inner.id()
+    }
+
+    fn source_type(&self) -> SourceType {
+        self.inner.source_type()
+    }
+
+    fn priority(&self) -> Priority {
+        self.inner.priority()
+    }
+
+    fn capabilities(&self) -> SourceCapabilities {
+        self.inner.capabilities()
+    }
+
+    async fn load(&self) -> Result<SourceSnapshot, SourceError> {
+        let inner = Arc::clone(&self.inner);
+        tokio::task::spawn_blocking(move || inner.load())
+            .await
+            .map_err(|e| SourceError::SourceRead {
+                source_name: self.inner.id().to_string(),
+                reason: format!("Blocking load task failed: {}", e),
+            })?
+    }
+
+    async fn refresh(&self) -> Result<bool, SourceError> {
+        let changed = self.inner.has_changed();
+        if changed {
+            self.inner.invalidate();
+        }
+        Ok(changed)
+    }
+
+    fn metadata(&self) -> SourceMetadata {
+        self.inner.metadata()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::source::{MemorySource, SourceRegistry};
+
+    #[tokio::test]
+    async fn test_memory_source_through_async_registry() {
+        let source = MemorySource::new();
+        source.set("ADAPTED_KEY", "adapted_value");
+
+        let registry = SourceRegistry::new();
+        let id = registry.register_sync_as_async(source);
+
+        assert_eq!(id.as_str(), "memory:default");
+        assert!(registry.has_async_sources());
+        assert!(!registry.is_registered(&id));
+
+        let snapshots = registry.load_all().await.unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].variables[0].key.as_str(), "ADAPTED_KEY");
+        assert_eq!(snapshots[0].variables[0].raw_value.as_str(), "adapted_value");
+    }
+}
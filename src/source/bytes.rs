@@ -0,0 +1,115 @@
+use super::parse::parse_env;
+use super::traits::*;
+use super::variable::{ParsedVariable, VariableSource};
+use crate::error::{Diagnostic, SourceError};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Env content supplied directly rather than read from disk, e.g. piped in on
+/// stdin. Parsed once with the same rules as env files; the content never
+/// changes, so the source can't be watched.
+#[cfg(feature = "file")]
+pub struct BytesSource {
+    id: SourceId,
+    variables: Arc<[ParsedVariable]>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+#[cfg(feature = "file")]
+impl BytesSource {
+    /// Parses `content` as dotenv data. An `id` without a type prefix is
+    /// namespaced as `memory:<id>`. Invalid UTF-8 is replaced rather than
+    /// rejected; diagnostics refer to the content by `id` as given.
+    pub fn new(id: impl Into<SourceId>, content: &[u8]) -> Self {
+        let name = id.into();
+        let id = match name.source_type() {
+            Some(_) => name.clone(),
+            None => SourceId::canonical(SourceType::Memory, &name),
+        };
+        let content = String::from_utf8_lossy(content);
+        let parsed = parse_env(&content, Path::new(name.as_str()));
+
+        let variables: Vec<ParsedVariable> = parsed
+            .variables
+            .into_iter()
+            .map(|variable| ParsedVariable {
+                source: VariableSource::Memory,
+                ..variable
+            })
+            .collect();
+
+        Self {
+            id,
+            variables: variables.into(),
+            diagnostics: parsed.diagnostics,
+        }
+    }
+}
+
+#[cfg(feature = "file")]
+impl EnvSource for BytesSource {
+    fn id(&self) -> &SourceId {
+        &self.id
+    }
+
+    fn source_type(&self) -> SourceType {
+        SourceType::Memory
+    }
+
+    fn priority(&self) -> Priority {
+        Priority::MEMORY
+    }
+
+    fn capabilities(&self) -> SourceCapabilities {
+        SourceCapabilities::READ | SourceCapabilities::CACHEABLE
+    }
+
+    fn load(&self) -> Result<SourceSnapshot, SourceError> {
+        Ok(SourceSnapshot {
+            source_id: self.id.clone(),
+            source_type: SourceType::Memory,
+            variables: Arc::clone(&self.variables),
+            timestamp: std::time::Instant::now(),
+            version: Some(0),
+        })
+    }
+
+    fn has_changed(&self) -> bool {
+        false
+    }
+
+    fn invalidate(&self) {}
+
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.clone()
+    }
+}
+
+#[cfg(feature = "file")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_bytes_without_filesystem() {
+        let source = BytesSource::new("stdin", b"# generated\nKEY=value\nQUOTED=\"a b\"\n");
+
+        assert_eq!(source.id().as_str(), "memory:stdin");
+        let prefixed = BytesSource::new(SourceId::new("memory:piped"), b"KEY=value\n");
+        assert_eq!(prefixed.id().as_str(), "memory:piped");
+        assert!(!source.capabilities().contains(SourceCapabilities::WATCH));
+
+        let snapshot = source.load().unwrap();
+        let vars: Vec<_> = snapshot
+            .variables
+            .iter()
+            .map(|v| (v.key.as_str(), v.raw_value.as_str()))
+            .collect();
+        assert_eq!(vars, vec![("KEY", "value"), ("QUOTED", "a b")]);
+        assert!(snapshot
+            .variables
+            .iter()
+            .all(|v| v.source == VariableSource::Memory));
+        assert!(source.diagnostics().is_empty());
+    }
+}
@@ -1,11 +1,65 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, Default)]
+/// Default for [`FileSourceConfig::max_file_bytes`]: 10 MiB.
+pub const DEFAULT_MAX_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+#[derive(Debug, Clone)]
 pub struct FileSourceConfig {
     pub active_files: Option<Vec<String>>,
 
     pub directory_overrides: HashMap<PathBuf, Vec<String>>,
+
+    /// Fail the load on malformed lines instead of skipping them and
+    /// reporting an `EDF001` diagnostic.
+    pub strict_parse: bool,
+
+    /// Treat `[section]` lines as INI-style headers that prefix the keys
+    /// below them, so `host` under `[db]` is read as `DB_HOST`.
+    pub sections: bool,
+
+    /// Refuse to read files larger than this many bytes, so a stray binary
+    /// matched by a glob can't exhaust memory.
+    pub max_file_bytes: u64,
+
+    /// Whitespace to strip from unquoted values.
+    pub trim_values: TrimPolicy,
+}
+
+impl Default for FileSourceConfig {
+    fn default() -> Self {
+        Self {
+            active_files: None,
+            directory_overrides: HashMap::new(),
+            strict_parse: false,
+            sections: false,
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+            trim_values: TrimPolicy::None,
+        }
+    }
+}
+
+/// Which whitespace around an unquoted value is insignificant. Quoted values
+/// are always kept verbatim.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrimPolicy {
+    /// Keep the value exactly as written.
+    #[default]
+    None,
+    /// Strip whitespace after the value, so `KEY=value   ` is `value`.
+    Trailing,
+    /// Strip whitespace on both sides of the value.
+    Both,
+}
+
+impl TrimPolicy {
+    pub(crate) fn apply(self, value: &str) -> &str {
+        match self {
+            TrimPolicy::None => value,
+            TrimPolicy::Trailing => value.trim_end(),
+            TrimPolicy::Both => value.trim(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
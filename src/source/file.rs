@@ -1,24 +1,40 @@
+use super::config::FileSourceConfig;
+use super::parse::{continues, parse_env_file, starts_assignment, strip_bom, take_directives};
 use super::traits::*;
-use super::variable::{ParsedVariable, VariableSource};
-use crate::error::SourceError;
+use super::variable::ParsedVariable;
+use crate::error::{Diagnostic, DiagnosticSeverity, SourceError};
 use compact_str::CompactString;
 use parking_lot::{Mutex, RwLock};
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Instant, SystemTime};
 
 #[cfg(feature = "file")]
 pub struct FileSource {
     path: PathBuf,
     id: SourceId,
+    config: FileSourceConfig,
     last_modified: Mutex<Option<SystemTime>>,
     cached_vars: RwLock<Option<Vec<ParsedVariable>>>,
+    diagnostics: RwLock<Vec<Diagnostic>>,
     version: RwLock<Option<u64>>,
     next_version: Mutex<u64>,
+    /// Serializes reparsing so one change yields exactly one version bump.
+    reload_lock: Mutex<()>,
+    last_refreshed: RwLock<Option<Instant>>,
+    error_count: AtomicU32,
 }
 
 #[cfg(feature = "file")]
 impl FileSource {
     pub fn new(path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        Self::with_config(path, FileSourceConfig::default())
+    }
+
+    pub fn with_config(
+        path: impl AsRef<Path>,
+        config: FileSourceConfig,
+    ) -> Result<Self, std::io::Error> {
         let path = path.as_ref().to_path_buf();
 
         if !path.exists() {
@@ -28,15 +44,20 @@ impl FileSource {
             ));
         }
 
-        let id = SourceId::new(format!("file:{}", path.display()));
+        let id = SourceId::canonical(SourceType::File, path.display());
 
         Ok(Self {
             path,
             id,
+            config,
             last_modified: Mutex::new(None),
             cached_vars: RwLock::new(None),
+            diagnostics: RwLock::new(Vec::new()),
             version: RwLock::new(None),
             next_version: Mutex::new(1),
+            reload_lock: Mutex::new(()),
+            last_refreshed: RwLock::new(None),
+            error_count: AtomicU32::new(0),
         })
     }
 
@@ -45,18 +66,79 @@ impl FileSource {
     }
 
     pub fn reload(&self) -> Result<(), std::io::Error> {
-        *self.cached_vars.write() = None;
-        self.load().map_err(|e| match e {
+        let _guard = self.reload_lock.lock();
+        self.parse_and_store().map_err(|e| match e {
             SourceError::SourceRead { reason, .. } => std::io::Error::other(reason),
             _ => std::io::Error::other(e.to_string()),
         })?;
         Ok(())
     }
 
+    fn cached_snapshot(&self) -> Option<SourceSnapshot> {
+        let cache = self.cached_vars.read();
+        let vars = cache.as_ref()?;
+        if self.check_modified() {
+            return None;
+        }
+
+        Some(SourceSnapshot {
+            source_id: self.id.clone(),
+            source_type: SourceType::File,
+            variables: vars.clone().into(),
+            timestamp: std::time::Instant::now(),
+            version: *self.version.read(),
+        })
+    }
+
+    /// Reparses the file and publishes the result under a new version.
+    /// Callers must hold `reload_lock`.
+    fn parse_and_store(&self) -> Result<SourceSnapshot, SourceError> {
+        let vars = self.parse_file()?;
+        let version = {
+            let mut next = self.next_version.lock();
+            let v = *next;
+            *next += 1;
+            v
+        };
+
+        // Swap variables and version together so readers never pair one
+        // parse's variables with another parse's version.
+        let mut cache = self.cached_vars.write();
+        *cache = Some(vars.clone());
+        *self.version.write() = Some(version);
+        drop(cache);
+        *self.last_refreshed.write() = Some(Instant::now());
+
+        Ok(SourceSnapshot {
+            source_id: self.id.clone(),
+            source_type: SourceType::File,
+            variables: vars.into(),
+            timestamp: std::time::Instant::now(),
+            version: Some(version),
+        })
+    }
+
     fn parse_file(&self) -> Result<Vec<ParsedVariable>, SourceError> {
-        let content = std::fs::read_to_string(&self.path).map_err(|e| SourceError::SourceRead {
-            source_name: self.path.display().to_string(),
-            reason: e.to_string(),
+        if let Ok(metadata) = self.path.metadata() {
+            if metadata.len() > self.config.max_file_bytes {
+                self.error_count.fetch_add(1, Ordering::Relaxed);
+                return Err(SourceError::SourceRead {
+                    source_name: self.path.display().to_string(),
+                    reason: format!(
+                        "file too large ({} bytes, limit {})",
+                        metadata.len(),
+                        self.config.max_file_bytes
+                    ),
+                });
+            }
+        }
+
+        let bytes = std::fs::read(&self.path).map_err(|e| {
+            self.error_count.fetch_add(1, Ordering::Relaxed);
+            SourceError::SourceRead {
+                source_name: self.path.display().to_string(),
+                reason: e.to_string(),
+            }
         })?;
 
         if let Ok(metadata) = self.path.metadata() {
@@ -65,34 +147,23 @@ impl FileSource {
             }
         }
 
-        let parsed = korni::parse_with_options(
-            &content,
-            korni::ParseOptions {
-                track_positions: true,
-                include_comments: false,
-            },
-        );
-        let mut variables = Vec::with_capacity(parsed.len());
-
-        for entry in parsed {
-            if let korni::Entry::Pair(kv) = entry {
-                let description = None;
-                let offset = kv.key_span.map(|s| s.start.offset).unwrap_or(0);
-
-                variables.push(ParsedVariable {
-                    key: CompactString::new(&kv.key),
-                    raw_value: CompactString::new(&kv.value),
-                    source: VariableSource::File {
-                        path: self.path.clone(),
-                        offset,
-                    },
-                    description,
-                    is_commented: kv.is_comment,
-                });
-            }
+        let parsed = parse_env_file(&bytes, &self.path, &self.config);
+        let first_error = parsed
+            .diagnostics
+            .iter()
+            .find(|d| d.severity == DiagnosticSeverity::Error)
+            .map(|d| (d.line, d.message.clone()));
+        *self.diagnostics.write() = parsed.diagnostics;
+
+        if let (true, Some((line, message))) = (self.config.strict_parse, first_error) {
+            return Err(SourceError::ParseError {
+                path: self.path.clone(),
+                line,
+                message,
+            });
         }
 
-        Ok(variables)
+        Ok(parsed.variables)
     }
 
     fn check_modified(&self) -> bool {
@@ -123,35 +194,75 @@ impl FileSource {
             reason: e.to_string(),
         })?;
 
-        let mut lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
-        let mut key_found = false;
-        let key_str = key.as_str();
-
-        for (idx, line) in lines.iter_mut().enumerate() {
-            if let Some(equal_pos) = line.find('=') {
-                let line_key = &line[..equal_pos].trim();
-                if *line_key == key_str {
-                    let prefix = &line[..=equal_pos];
-                    let mut new_line = String::with_capacity(prefix.len() + value.len());
-                    new_line.push_str(prefix);
-                    new_line.push_str(value.as_str());
-                    lines[idx] = new_line;
-                    key_found = true;
-                    break;
-                }
-            }
-        }
-
-        if !key_found {
+        let written = self.written_key(&content, &key, "set_variable")?;
+        let mut content = EditableContent::new(&content);
+        if !content.assign(written, value.as_str()) {
             return Err(SourceError::UnsupportedOperation {
                 operation: "set_variable".into(),
                 source_type: "FileSource".into(),
-                reason: format!("Key '{}' not found in file", key_str),
+                reason: format!("Key '{}' not found in file", key),
             });
         }
 
-        let new_content = lines.join("\n");
-        std::fs::write(&self.path, new_content).map_err(|e| SourceError::SourceRead {
+        self.write_content(&content)
+    }
+
+    /// Sets every `(key, value)` pair with a single write. Unlike
+    /// [`set_variable`](Self::set_variable), keys the file doesn't define yet
+    /// are appended rather than rejected. Nothing is written if any key lacks
+    /// the file's `prefix=` directive.
+    pub fn set_variables(
+        &self,
+        variables: &[(impl AsRef<str>, impl AsRef<str>)],
+    ) -> Result<(), SourceError> {
+        let content = std::fs::read_to_string(&self.path).map_err(|e| SourceError::SourceRead {
+            source_name: self.path.display().to_string(),
+            reason: e.to_string(),
+        })?;
+
+        let written: Vec<_> = variables
+            .iter()
+            .map(|(key, _)| self.written_key(&content, key.as_ref(), "set_variables"))
+            .collect::<Result<_, _>>()?;
+        let mut content = EditableContent::new(&content);
+        for (key, (_, value)) in written.into_iter().zip(variables) {
+            let value = value.as_ref();
+            if !content.assign(key, value) {
+                content.lines.push(format!("{}={}", key, value));
+                content.trailing_newline = true;
+            }
+        }
+
+        self.write_content(&content)
+    }
+
+    /// `key` as spelled in the file, without the prefix a `prefix=` directive
+    /// adds to every key it defines. Keys lacking that prefix can't be
+    /// written to the file and are rejected.
+    fn written_key<'a>(
+        &self,
+        content: &str,
+        key: &'a str,
+        operation: &str,
+    ) -> Result<&'a str, SourceError> {
+        let mut header = content.lines().next().unwrap_or_default().to_string();
+        let Some(prefix) = take_directives(&mut header, &self.path).0.prefix else {
+            return Ok(key);
+        };
+
+        key.strip_prefix(prefix.as_str())
+            .ok_or_else(|| SourceError::UnsupportedOperation {
+                operation: operation.into(),
+                source_type: "FileSource".into(),
+                reason: format!(
+                    "Key '{}' lacks the prefix '{}' the file's directive adds",
+                    key, prefix
+                ),
+            })
+    }
+
+    fn write_content(&self, content: &EditableContent) -> Result<(), SourceError> {
+        std::fs::write(&self.path, content.render()).map_err(|e| SourceError::SourceRead {
             source_name: self.path.display().to_string(),
             reason: format!("Failed to write file: {}", e),
         })?;
@@ -191,31 +302,20 @@ impl FileSource {
             }
         };
 
-        let lines: Vec<String> = content
-            .lines()
-            .filter(|line| {
-                if let Some(equal_pos) = line.find('=') {
-                    let line_key = &line[..equal_pos].trim();
-                    *line_key != key_str
-                } else {
-                    true
-                }
-            })
-            .map(|s| s.to_string())
-            .collect();
-
-        let new_content = lines.join("\n");
-        std::fs::write(&self.path, new_content).map_err(|e| SourceError::SourceRead {
-            source_name: self.path.display().to_string(),
-            reason: format!("Failed to write file: {}", e),
-        })?;
-
-        *self.cached_vars.write() = None;
-        {
-            let mut next = self.next_version.lock();
-            *next += 1;
+        let written = self.written_key(&content, key_str, "remove_variable")?;
+        let mut content = EditableContent::new(&content);
+        let mut idx = 0;
+        while idx < content.lines.len() {
+            let entry_len = content.entry_len(idx);
+            if assignment_split(&content.lines[idx], written).is_some() {
+                content.lines.drain(idx..idx + entry_len);
+            } else {
+                idx += entry_len;
+            }
         }
 
+        self.write_content(&content)?;
+
         Ok(removed)
     }
 
@@ -250,41 +350,17 @@ impl EnvSource for FileSource {
     }
 
     fn load(&self) -> Result<SourceSnapshot, SourceError> {
-        // Fast path: read locks only
-        {
-            let cache = self.cached_vars.read();
-            if let Some(vars) = cache.as_ref() {
-                if !self.check_modified() {
-                    let v = *self.version.read();
-                    return Ok(SourceSnapshot {
-                        source_id: self.id.clone(),
-                        variables: vars.clone().into(),
-                        timestamp: std::time::Instant::now(),
-                        version: v,
-                    });
-                }
-            }
+        if let Some(snapshot) = self.cached_snapshot() {
+            return Ok(snapshot);
         }
-        // Read locks released here
 
-        // Slow path: write locks
-        let version = {
-            let mut next = self.next_version.lock();
-            let v = *next;
-            *next += 1;
-            v
-        };
-
-        let vars = self.parse_file()?;
-        *self.cached_vars.write() = Some(vars.clone());
-        *self.version.write() = Some(version);
+        let _guard = self.reload_lock.lock();
+        // Another thread may have reparsed the file while we waited.
+        if let Some(snapshot) = self.cached_snapshot() {
+            return Ok(snapshot);
+        }
 
-        Ok(SourceSnapshot {
-            source_id: self.id.clone(),
-            variables: vars.into(),
-            timestamp: std::time::Instant::now(),
-            version: Some(version),
-        })
+        self.parse_and_store()
     }
 
     fn has_changed(&self) -> bool {
@@ -292,15 +368,133 @@ impl EnvSource for FileSource {
     }
 
     fn invalidate(&self) {
+        let _guard = self.reload_lock.lock();
         *self.cached_vars.write() = None;
         *self.last_modified.lock() = None;
     }
+
+    /// Hashes the file's mtime and length without reading it, plus the
+    /// edit counter and the last seen mtime so our own writes and
+    /// [`invalidate`](EnvSource::invalidate) change it too.
+    fn fingerprint(&self) -> Option<u64> {
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = ahash::AHasher::default();
+        self.id.hash(&mut hasher);
+        if let Ok(metadata) = self.path.metadata() {
+            metadata.modified().ok().hash(&mut hasher);
+            metadata.len().hash(&mut hasher);
+        }
+        self.last_modified.lock().hash(&mut hasher);
+        self.next_version.lock().hash(&mut hasher);
+        Some(hasher.finish())
+    }
+
+    fn metadata(&self) -> SourceMetadata {
+        SourceMetadata {
+            display_name: Some(CompactString::new(self.path.display().to_string())),
+            description: None,
+            last_refreshed: *self.last_refreshed.read(),
+            error_count: self.error_count.load(Ordering::Relaxed),
+        }
+    }
+
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.read().clone()
+    }
+}
+
+/// Env file content split into lines for in-place edits. Remembers the BOM
+/// and trailing newline so writing it back doesn't reformat the file.
+#[cfg(feature = "file")]
+struct EditableContent {
+    bom: bool,
+    trailing_newline: bool,
+    lines: Vec<String>,
+}
+
+#[cfg(feature = "file")]
+impl EditableContent {
+    fn new(content: &str) -> Self {
+        let stripped = strip_bom(content);
+        Self {
+            bom: stripped.len() != content.len(),
+            trailing_newline: stripped.ends_with('\n'),
+            lines: stripped.lines().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    /// Number of lines the entry starting at `idx` spans, counting the lines
+    /// an unquoted value is continued onto with a trailing backslash.
+    fn entry_len(&self, idx: usize) -> usize {
+        let line = &self.lines[idx];
+        let quoted = line
+            .split_once('=')
+            .is_some_and(|(_, value)| value.trim_start().starts_with(['"', '\'']));
+        if quoted || line.trim_start().starts_with('#') {
+            return 1;
+        }
+
+        let mut len = 1;
+        while idx + len < self.lines.len()
+            && continues(&self.lines[idx + len - 1])
+            && !starts_assignment(&self.lines[idx + len])
+        {
+            len += 1;
+        }
+        len
+    }
+
+    /// Replaces the value of the first assignment to `key`, dropping any
+    /// lines it was continued onto. False when `key` isn't assigned.
+    fn assign(&mut self, key: &str, value: &str) -> bool {
+        let mut idx = 0;
+        while idx < self.lines.len() {
+            let entry_len = self.entry_len(idx);
+            let line = &mut self.lines[idx];
+            if let Some(equal_pos) = assignment_split(line, key) {
+                let prefix = &line[..=equal_pos];
+                let mut new_line = String::with_capacity(prefix.len() + value.len());
+                new_line.push_str(prefix);
+                new_line.push_str(value);
+                *line = new_line;
+                self.lines.drain(idx + 1..idx + entry_len);
+                return true;
+            }
+            idx += entry_len;
+        }
+        false
+    }
+
+    fn render(&self) -> String {
+        let mut rendered = String::new();
+        if self.bom {
+            rendered.push('\u{feff}');
+        }
+        rendered.push_str(&self.lines.join("\n"));
+        if self.trailing_newline && !self.lines.is_empty() {
+            rendered.push('\n');
+        }
+        rendered
+    }
+}
+
+/// Position of the `=` in `line` if it assigns `key`. Lines with NUL bytes
+/// never match, since the parser skips them too.
+#[cfg(feature = "file")]
+fn assignment_split(line: &str, key: &str) -> Option<usize> {
+    if line.contains('\0') {
+        return None;
+    }
+    let equal_pos = line.find('=')?;
+    (line[..equal_pos].trim() == key).then_some(equal_pos)
 }
 
 #[cfg(feature = "file")]
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::source::TrimPolicy;
     use std::io::Write;
     use tempfile::NamedTempFile;
 
@@ -329,6 +523,22 @@ mod tests {
         assert!(!source.has_changed());
     }
 
+    #[test]
+    fn test_fingerprint_tracks_file_changes() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "KEY=value").unwrap();
+
+        let source = FileSource::new(file.path()).unwrap();
+        source.load().unwrap();
+        let fingerprint = source.fingerprint();
+        assert_eq!(source.fingerprint(), fingerprint);
+        source.load().unwrap();
+        assert_eq!(source.fingerprint(), fingerprint);
+
+        writeln!(file, "OTHER=123").unwrap();
+        assert_ne!(source.fingerprint(), fingerprint);
+    }
+
     #[test]
     fn test_version_tracking() {
         let mut file = NamedTempFile::new().unwrap();
@@ -348,6 +558,146 @@ mod tests {
         assert!(v2.unwrap() > v1.unwrap());
     }
 
+    #[test]
+    fn test_concurrent_reload_and_load() {
+        use std::sync::Arc;
+
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "KEY=value").unwrap();
+
+        let source = Arc::new(FileSource::new(file.path()).unwrap());
+        let initial = source.load().unwrap().version.unwrap();
+
+        const THREADS: u64 = 4;
+        const RELOADS: u64 = 25;
+
+        let handles: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let source = Arc::clone(&source);
+                std::thread::spawn(move || {
+                    let mut last_seen = 0;
+                    for _ in 0..RELOADS {
+                        source.reload().unwrap();
+                        for _ in 0..4 {
+                            let snapshot = source.load().unwrap();
+                            let version = snapshot.version.unwrap();
+                            assert!(version >= last_seen, "version went backwards");
+                            assert_eq!(snapshot.variables.len(), 1);
+                            last_seen = version;
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(
+            source.load().unwrap().version,
+            Some(initial + THREADS * RELOADS)
+        );
+    }
+
+    #[test]
+    fn test_trim_values_policy() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "KEY=value   ").unwrap();
+        writeln!(file, "QUOTED=\"padded   \"").unwrap();
+
+        let load = |trim_values| {
+            let source = FileSource::with_config(
+                file.path(),
+                FileSourceConfig {
+                    trim_values,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+            source
+                .load()
+                .unwrap()
+                .variables
+                .iter()
+                .map(|v| (v.key.to_string(), v.raw_value.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            load(TrimPolicy::None),
+            vec![
+                ("KEY".to_string(), "value   ".to_string()),
+                ("QUOTED".to_string(), "padded   ".to_string()),
+            ]
+        );
+        assert_eq!(
+            load(TrimPolicy::Trailing),
+            vec![
+                ("KEY".to_string(), "value".to_string()),
+                ("QUOTED".to_string(), "padded   ".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_leading_directive_configures_file() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            "#!abundantis interpolation=off, prefix=APP_, colour=red"
+        )
+        .unwrap();
+        writeln!(file, "URL=${{HOST}}/api").unwrap();
+
+        let source = FileSource::new(file.path()).unwrap();
+        let snapshot = source.load().unwrap();
+        assert_eq!(snapshot.variables.len(), 1);
+        let variable = &snapshot.variables[0];
+        assert_eq!(variable.key.as_str(), "APP_URL");
+        assert_eq!(variable.raw_value.as_str(), "${HOST}/api");
+        assert!(variable.is_literal);
+        assert_eq!(variable.description, None);
+
+        let diagnostics = source.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, crate::error::DiagnosticCode::EDF004);
+        assert!(diagnostics[0].message.contains("colour=red"));
+    }
+
+    #[test]
+    fn test_edits_strip_the_directive_prefix() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "# abundantis: prefix=APP_").unwrap();
+        writeln!(file, "PORT=80").unwrap();
+        writeln!(file, "HOST=localhost").unwrap();
+
+        let source = FileSource::new(file.path()).unwrap();
+        source.set_variable("APP_PORT", "8080").unwrap();
+        source
+            .set_variables(&[("APP_HOST", "example.com"), ("APP_DEBUG", "1")])
+            .unwrap();
+        let removed = source.remove_variable("APP_PORT").unwrap();
+        assert_eq!(removed.raw_value.as_str(), "8080");
+
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(
+            content,
+            "# abundantis: prefix=APP_\nHOST=example.com\nDEBUG=1\n"
+        );
+
+        assert!(matches!(
+            source.set_variable("PORT", "1"),
+            Err(SourceError::UnsupportedOperation { .. })
+        ));
+        assert!(matches!(
+            source.set_variables(&[("APP_HOST", "a"), ("OTHER", "b")]),
+            Err(SourceError::UnsupportedOperation { .. })
+        ));
+        let unchanged = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(unchanged, content);
+    }
+
     #[test]
     fn test_set_variable() {
         let mut file = NamedTempFile::new().unwrap();
@@ -363,6 +713,20 @@ mod tests {
         assert!(!content.contains("KEY=value1"));
     }
 
+    #[test]
+    fn test_set_variables_appends_missing_keys() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "KEY=value1\nOTHER=123").unwrap();
+
+        let source = FileSource::new(file.path()).unwrap();
+        source
+            .set_variables(&[("KEY", "value2"), ("NEW", "added")])
+            .unwrap();
+
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(content, "KEY=value2\nOTHER=123\nNEW=added\n");
+    }
+
     #[test]
     fn test_remove_variable() {
         let mut file = NamedTempFile::new().unwrap();
@@ -378,4 +742,321 @@ mod tests {
         assert!(!content.contains("KEY=value1"));
         assert!(content.contains("OTHER=123"));
     }
+
+    #[test]
+    fn test_leading_comments_become_descriptions() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "# Generated file").unwrap();
+        writeln!(file).unwrap();
+        writeln!(file, "# The database URL").unwrap();
+        writeln!(file, "#   (read-write)").unwrap();
+        writeln!(file, "DATABASE_URL=postgres://localhost").unwrap();
+        writeln!(file, "PLAIN=1").unwrap();
+        writeln!(file).unwrap();
+        writeln!(file, "# Cache size").unwrap();
+        writeln!(file, "# CACHE_SIZE=64").unwrap();
+        writeln!(file, "CACHE_SIZE=128").unwrap();
+        writeln!(file).unwrap();
+        writeln!(file, "# OLD_KEY=value").unwrap();
+        writeln!(file, "NEW_KEY=value").unwrap();
+
+        let source = FileSource::new(file.path()).unwrap();
+        let snapshot = source.load().unwrap();
+        let descriptions: Vec<_> = snapshot
+            .variables
+            .iter()
+            .map(|v| (v.key.as_str(), v.description.as_deref()))
+            .collect();
+        assert_eq!(
+            descriptions,
+            vec![
+                ("DATABASE_URL", Some("The database URL (read-write)")),
+                ("PLAIN", None),
+                ("CACHE_SIZE", Some("Cache size")),
+                ("NEW_KEY", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_backslash_continues_value() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "CERT=line1\\").unwrap();
+        writeln!(file, "  line2\\").unwrap();
+        writeln!(file, "line3").unwrap();
+        writeln!(file, "PATHLIKE=C:\\\\").unwrap();
+        writeln!(file, "OTHER=123").unwrap();
+
+        let source = FileSource::new(file.path()).unwrap();
+        let snapshot = source.load().unwrap();
+        let vars: Vec<_> = snapshot
+            .variables
+            .iter()
+            .map(|v| (v.key.as_str(), v.raw_value.as_str()))
+            .collect();
+        assert_eq!(
+            vars,
+            vec![
+                ("CERT", "line1line2line3"),
+                ("PATHLIKE", "C:\\\\"),
+                ("OTHER", "123")
+            ]
+        );
+        assert!(source.diagnostics().is_empty());
+
+        let other = snapshot
+            .variables
+            .iter()
+            .find(|v| v.key == "OTHER")
+            .unwrap();
+        let super::super::VariableSource::File { offset, .. } = other.source else {
+            panic!("expected a file source");
+        };
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert!(content[offset..].starts_with("OTHER"));
+
+        source.set_variable("CERT", "joined").unwrap();
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(content, "CERT=joined\nPATHLIKE=C:\\\\\nOTHER=123\n");
+
+        std::fs::write(file.path(), "CERT=line1\\\nline2\nOTHER=123\n").unwrap();
+        source.remove_variable("CERT").unwrap();
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(content, "OTHER=123\n");
+    }
+
+    #[test]
+    fn test_trailing_backslash_does_not_swallow_next_assignment() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "DIR=C:\\").unwrap();
+        writeln!(file, "NEXT=1").unwrap();
+
+        let source = FileSource::new(file.path()).unwrap();
+        let snapshot = source.load().unwrap();
+        let vars: Vec<_> = snapshot
+            .variables
+            .iter()
+            .map(|v| (v.key.as_str(), v.raw_value.as_str()))
+            .collect();
+        assert_eq!(vars, vec![("DIR", "C:\\"), ("NEXT", "1")]);
+
+        source.set_variable("DIR", "D:\\").unwrap();
+        let content = std::fs::read_to_string(file.path()).unwrap();
+        assert_eq!(content, "DIR=D:\\\nNEXT=1\n");
+
+        std::fs::write(file.path(), "NEXT=1\r\nDIR=C:\\\r\n").unwrap();
+        let source = FileSource::new(file.path()).unwrap();
+        let snapshot = source.load().unwrap();
+        let vars: Vec<_> = snapshot
+            .variables
+            .iter()
+            .map(|v| (v.key.as_str(), v.raw_value.as_str()))
+            .collect();
+        assert_eq!(vars, vec![("NEXT", "1"), ("DIR", "C:\\")]);
+    }
+
+    #[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+    #[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+    async fn test_duplicate_key_last_definition_wins() {
+        use crate::config::{CacheConfig, InterpolationConfig, ResolutionConfig};
+        use crate::resolution::ResolutionEngine;
+        use std::sync::Arc;
+
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "KEY=a").unwrap();
+        writeln!(file, "OTHER=123").unwrap();
+        writeln!(file, "KEY=b").unwrap();
+
+        let source = Arc::new(FileSource::new(file.path()).unwrap());
+        let snapshot = source.load().unwrap();
+
+        let keys: Vec<_> = snapshot.variables.iter().map(|v| v.key.as_str()).collect();
+        assert_eq!(keys, vec!["OTHER", "KEY"]);
+        assert_eq!(snapshot.variables[1].raw_value.as_str(), "b");
+
+        let diagnostics = source.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, crate::error::DiagnosticCode::EDF002);
+        assert_eq!(diagnostics[0].line, 3);
+        assert!(diagnostics[0].message.contains("line 1"));
+
+        let registry = crate::source::SourceRegistry::new();
+        registry.register_sync(source);
+        let engine = ResolutionEngine::new(
+            &ResolutionConfig::default(),
+            &InterpolationConfig::default(),
+            &CacheConfig::default(),
+        );
+        let root = file.path().parent().unwrap().to_path_buf();
+        let context = crate::workspace::WorkspaceContext {
+            workspace_root: root.clone(),
+            package_root: root,
+            package_name: None,
+            env_files: vec![file.path().to_path_buf()],
+        };
+        let resolved = engine
+            .resolve("KEY", &context, &registry)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(resolved.resolved_value.as_str(), "b");
+    }
+
+    #[test]
+    fn test_malformed_line() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(file, "# comment").unwrap();
+        writeln!(file, "KEY=value").unwrap();
+        writeln!(file, "NOEQUALSIGN").unwrap();
+        writeln!(file, "OTHER=123").unwrap();
+
+        let lenient = FileSource::new(file.path()).unwrap();
+        let snapshot = lenient.load().unwrap();
+        assert_eq!(snapshot.variables.len(), 2);
+        let diagnostics = lenient.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, crate::error::DiagnosticCode::EDF001);
+        assert_eq!(diagnostics[0].line, 3);
+
+        let strict = FileSource::with_config(
+            file.path(),
+            FileSourceConfig {
+                strict_parse: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        match strict.load() {
+            Err(SourceError::ParseError {
+                path,
+                line,
+                message,
+            }) => {
+                assert_eq!(path, file.path());
+                assert_eq!(line, 3);
+                assert!(message.contains("NOEQUALSIGN"));
+            }
+            other => panic!("expected ParseError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_bom_prefixed_file() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"\xef\xbb\xbfKEY=value\nOTHER=1").unwrap();
+
+        let source = FileSource::new(file.path()).unwrap();
+        let snapshot = source.load().unwrap();
+        let vars: Vec<_> = snapshot
+            .variables
+            .iter()
+            .map(|v| (v.key.as_str(), v.raw_value.as_str()))
+            .collect();
+        assert_eq!(vars, vec![("KEY", "value"), ("OTHER", "1")]);
+        assert!(source.diagnostics().is_empty());
+
+        source.set_variable("KEY", "updated").unwrap();
+        let written = std::fs::read(file.path()).unwrap();
+        assert_eq!(written, b"\xef\xbb\xbfKEY=updated\nOTHER=1");
+    }
+
+    #[test]
+    fn test_invalid_utf8_line_is_skipped() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"BEFORE=1\nBROKEN=caf\xe9\nAFTER=2\n")
+            .unwrap();
+
+        let source = FileSource::new(file.path()).unwrap();
+        let snapshot = source.load().unwrap();
+        let vars: Vec<_> = snapshot
+            .variables
+            .iter()
+            .map(|v| (v.key.as_str(), v.raw_value.as_str()))
+            .collect();
+        assert_eq!(vars, vec![("BEFORE", "1"), ("AFTER", "2")]);
+
+        let diagnostics = source.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, crate::error::DiagnosticCode::EDF001);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (2, 11));
+    }
+
+    #[test]
+    fn test_nul_line_is_skipped() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"BEFORE=1\nBROKEN=a\0b\nAFTER=2\n").unwrap();
+
+        let source = FileSource::new(file.path()).unwrap();
+        let snapshot = source.load().unwrap();
+        let vars: Vec<_> = snapshot
+            .variables
+            .iter()
+            .map(|v| (v.key.as_str(), v.raw_value.as_str()))
+            .collect();
+        assert_eq!(vars, vec![("BEFORE", "1"), ("AFTER", "2")]);
+
+        let diagnostics = source.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, crate::error::DiagnosticCode::EDF003);
+        assert_eq!(diagnostics[0].line, 2);
+
+        assert!(source.set_variable("BROKEN", "fixed").is_err());
+        source.remove_variable("AFTER").unwrap();
+        let written = std::fs::read(file.path()).unwrap();
+        assert_eq!(written, b"BEFORE=1\nBROKEN=a\0b\n");
+    }
+
+    #[test]
+    fn test_section_headers_prefix_keys() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"APP_NAME=demo\n[db]\nhost=localhost\nport=5432\n\n[cache]\nhost=redis\n")
+            .unwrap();
+
+        let source = FileSource::with_config(
+            file.path(),
+            FileSourceConfig {
+                sections: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let snapshot = source.load().unwrap();
+        let vars: Vec<_> = snapshot
+            .variables
+            .iter()
+            .map(|v| (v.key.as_str(), v.raw_value.as_str()))
+            .collect();
+        assert_eq!(
+            vars,
+            vec![
+                ("APP_NAME", "demo"),
+                ("DB_HOST", "localhost"),
+                ("DB_PORT", "5432"),
+                ("CACHE_HOST", "redis"),
+            ]
+        );
+        assert!(source.diagnostics().is_empty());
+
+        let plain = FileSource::new(file.path()).unwrap();
+        let snapshot = plain.load().unwrap();
+        assert!(snapshot.variables.iter().any(|v| v.key == "host"));
+    }
+
+    #[test]
+    fn test_oversized_file_is_not_read() {
+        let file = NamedTempFile::new().unwrap();
+        // Sparse, so the test doesn't write the bytes out.
+        file.as_file()
+            .set_len(super::super::config::DEFAULT_MAX_FILE_BYTES + 1)
+            .unwrap();
+
+        let source = FileSource::new(file.path()).unwrap();
+        match source.load() {
+            Err(SourceError::SourceRead { reason, .. }) => {
+                assert!(reason.contains("file too large"), "{}", reason)
+            }
+            other => panic!("expected SourceRead, got {:?}", other.map(|s| s.variables)),
+        }
+        assert_eq!(source.metadata().error_count, 1);
+    }
 }
@@ -0,0 +1,223 @@
+use super::config::FileSourceConfig;
+use super::parse::parse_env_file;
+use super::traits::*;
+use super::variable::{ParsedVariable, VariableSource};
+use crate::error::{Diagnostic, DiagnosticSeverity, SourceError};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// An env file read from a git repository at a given ref (`HEAD~1`, a tag, a
+/// commit id), without checking it out. Works on bare repositories too.
+///
+/// The blob is read once and parsed like an env file on disk, directives
+/// and trim policy included. It isn't part of the working tree, so like
+/// other content held in memory it registers as `memory:<repo>/<file>@<ref>`
+/// and is never dropped by the active-file filter.
+#[cfg(feature = "git")]
+pub struct GitSource {
+    id: SourceId,
+    reference: String,
+    variables: Arc<[ParsedVariable]>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+#[cfg(feature = "git")]
+impl GitSource {
+    /// Reads `file` (relative to the repository root) as it was at
+    /// `reference` in the repository at `repo`.
+    pub fn new(
+        repo: impl AsRef<Path>,
+        reference: &str,
+        file: impl AsRef<Path>,
+    ) -> Result<Self, SourceError> {
+        Self::with_config(repo, reference, file, FileSourceConfig::default())
+    }
+
+    /// [`new`](Self::new), parsing under `config` the way a
+    /// [`FileSource`](super::FileSource) with that config would.
+    pub fn with_config(
+        repo: impl AsRef<Path>,
+        reference: &str,
+        file: impl AsRef<Path>,
+        config: FileSourceConfig,
+    ) -> Result<Self, SourceError> {
+        let repo = repo.as_ref();
+        let file = file.as_ref();
+        let location = PathBuf::from(format!("{}@{}", repo.join(file).display(), reference));
+        let read_error = |reason: String| SourceError::SourceRead {
+            source_name: location.display().to_string(),
+            reason,
+        };
+
+        let repository =
+            git2::Repository::open(repo).map_err(|e| read_error(e.message().into()))?;
+        let spec = format!(
+            "{}:{}",
+            reference,
+            file.to_string_lossy().replace('\\', "/")
+        );
+        let blob = repository
+            .revparse_single(&spec)
+            .and_then(|object| object.peel_to_blob())
+            .map_err(|e| read_error(e.message().into()))?;
+        if blob.size() as u64 > config.max_file_bytes {
+            return Err(read_error(format!(
+                "file too large ({} bytes, limit {})",
+                blob.size(),
+                config.max_file_bytes
+            )));
+        }
+
+        let parsed = parse_env_file(blob.content(), &location, &config);
+        let first_error = parsed
+            .diagnostics
+            .iter()
+            .find(|d| d.severity == DiagnosticSeverity::Error);
+        if let (true, Some(error)) = (config.strict_parse, first_error) {
+            return Err(SourceError::ParseError {
+                path: location.clone(),
+                line: error.line,
+                message: error.message.clone(),
+            });
+        }
+
+        let variables: Vec<ParsedVariable> = parsed
+            .variables
+            .into_iter()
+            .map(|variable| ParsedVariable {
+                source: VariableSource::Memory,
+                ..variable
+            })
+            .collect();
+
+        Ok(Self {
+            id: SourceId::canonical(SourceType::Memory, location.display()),
+            reference: reference.to_string(),
+            variables: variables.into(),
+            diagnostics: parsed.diagnostics,
+        })
+    }
+
+    pub fn reference(&self) -> &str {
+        &self.reference
+    }
+}
+
+#[cfg(feature = "git")]
+impl EnvSource for GitSource {
+    fn id(&self) -> &SourceId {
+        &self.id
+    }
+
+    fn source_type(&self) -> SourceType {
+        SourceType::Memory
+    }
+
+    fn priority(&self) -> Priority {
+        Priority::MEMORY
+    }
+
+    fn capabilities(&self) -> SourceCapabilities {
+        SourceCapabilities::READ | SourceCapabilities::CACHEABLE
+    }
+
+    fn load(&self) -> Result<SourceSnapshot, SourceError> {
+        Ok(SourceSnapshot {
+            source_id: self.id.clone(),
+            source_type: SourceType::Memory,
+            variables: Arc::clone(&self.variables),
+            timestamp: std::time::Instant::now(),
+            version: Some(0),
+        })
+    }
+
+    fn has_changed(&self) -> bool {
+        false
+    }
+
+    fn invalidate(&self) {}
+
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.clone()
+    }
+}
+
+#[cfg(feature = "git")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commit(repo: &git2::Repository, content: &str, message: &str) {
+        let workdir = repo.workdir().unwrap();
+        std::fs::write(workdir.join(".env"), content).unwrap();
+
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(".env")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("test", "test@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_reads_env_file_at_ref() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        commit(&repo, "DATABASE_URL=postgres://old\n", "first");
+        commit(&repo, "DATABASE_URL=postgres://new\n", "second");
+
+        let source = GitSource::new(dir.path(), "HEAD~1", ".env").unwrap();
+        assert_eq!(source.reference(), "HEAD~1");
+
+        let snapshot = source.load().unwrap();
+        assert_eq!(snapshot.variables.len(), 1);
+        let variable = &snapshot.variables[0];
+        assert_eq!(variable.raw_value, "postgres://old");
+        assert_eq!(variable.source, VariableSource::Memory);
+        assert_eq!(
+            source.id().as_str(),
+            format!("memory:{}@HEAD~1", dir.path().join(".env").display())
+        );
+
+        let current = GitSource::new(dir.path(), "HEAD", ".env").unwrap();
+        assert_eq!(
+            current.load().unwrap().variables[0].raw_value,
+            "postgres://new"
+        );
+
+        assert!(matches!(
+            GitSource::new(dir.path(), "HEAD", ".env.missing"),
+            Err(SourceError::SourceRead { .. })
+        ));
+    }
+
+    #[test]
+    fn test_applies_directives_and_trim_policy() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        commit(&repo, "# abundantis: prefix=APP_\nPORT=8080   \n", "first");
+
+        let config = FileSourceConfig {
+            trim_values: super::super::config::TrimPolicy::Trailing,
+            ..Default::default()
+        };
+        let source = GitSource::with_config(dir.path(), "HEAD", ".env", config).unwrap();
+        let snapshot = source.load().unwrap();
+        let vars: Vec<_> = snapshot
+            .variables
+            .iter()
+            .map(|v| (v.key.as_str(), v.raw_value.as_str()))
+            .collect();
+        assert_eq!(vars, vec![("APP_PORT", "8080")]);
+    }
+}
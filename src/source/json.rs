@@ -0,0 +1,268 @@
+use super::traits::*;
+use super::variable::{ParsedVariable, VariableSource};
+use crate::error::{Diagnostic, DiagnosticCode, DiagnosticSeverity, SourceError};
+use compact_str::CompactString;
+use hashbrown::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A JSON config file consumed as env variables. Nested objects are flattened
+/// into `_`-joined upper-case keys (`{"db": {"url": "x"}}` becomes
+/// `DB_URL=x`) and arrays into indexed keys (`LIST_0`, `LIST_1`, ...).
+///
+/// The file is read once. Like [`BytesSource`](super::BytesSource) it ranks
+/// as a memory source, so it applies regardless of the active env files and
+/// below the shell and env files. Keys that flatten to the same name (`a-b`
+/// and `a_b` both become `A_B`) are reported as `EDF002` warnings; the one
+/// appearing last wins.
+#[cfg(feature = "file")]
+pub struct JsonSource {
+    id: SourceId,
+    path: PathBuf,
+    variables: Arc<[ParsedVariable]>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+#[cfg(feature = "file")]
+impl JsonSource {
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, SourceError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path).map_err(|e| SourceError::SourceRead {
+            source_name: path.display().to_string(),
+            reason: e.to_string(),
+        })?;
+        Self::parse(&content, path)
+    }
+
+    /// Flattens `content`; `path` names the source in its id and
+    /// diagnostics. The top-level value must be an object.
+    pub fn parse(content: &str, path: impl AsRef<Path>) -> Result<Self, SourceError> {
+        let path = path.as_ref();
+        let parse_error = |line: usize, message: String| SourceError::ParseError {
+            path: path.to_path_buf(),
+            line: line as u32,
+            message,
+        };
+
+        let value: serde_json::Value =
+            serde_json::from_str(content).map_err(|e| parse_error(e.line(), e.to_string()))?;
+        if !value.is_object() {
+            return Err(parse_error(1, "expected a JSON object".to_string()));
+        }
+
+        let mut flattened = Vec::new();
+        flatten(&value, "", "", &mut flattened);
+
+        let mut diagnostics = Vec::new();
+        let mut winners: HashMap<CompactString, &str> = HashMap::new();
+        for (pointer, variable) in &flattened {
+            let Some(previous) = winners.insert(variable.key.clone(), pointer) else {
+                continue;
+            };
+            diagnostics.push(Diagnostic {
+                severity: DiagnosticSeverity::Warning,
+                code: DiagnosticCode::EDF002,
+                message: format!(
+                    "`{}` and `{}` both flatten to `{}`; `{}` wins",
+                    previous, pointer, variable.key, pointer
+                ),
+                path: path.to_path_buf(),
+                line: 1,
+                column: 1,
+                snippet: None,
+                label: None,
+            });
+        }
+        let variables: Vec<ParsedVariable> = flattened
+            .iter()
+            .filter(|(pointer, variable)| winners[&variable.key] == pointer.as_str())
+            .map(|(_, variable)| variable.clone())
+            .collect();
+
+        Ok(Self {
+            id: SourceId::canonical(SourceType::Memory, path.display()),
+            path: path.to_path_buf(),
+            variables: variables.into(),
+            diagnostics,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Appends one variable per scalar in `value`, named by the path leading to
+/// it, alongside that path as written in the JSON (`db.url`, `list.0`).
+#[cfg(feature = "file")]
+fn flatten(
+    value: &serde_json::Value,
+    prefix: &str,
+    pointer: &str,
+    out: &mut Vec<(String, ParsedVariable)>,
+) {
+    use serde_json::Value;
+
+    let mut descend = |segment: &str, nested: &Value| {
+        let key = env_key_segment(segment);
+        let (prefix, pointer) = if prefix.is_empty() {
+            (key, segment.to_string())
+        } else {
+            (
+                format!("{}_{}", prefix, key),
+                format!("{}.{}", pointer, segment),
+            )
+        };
+        flatten(nested, &prefix, &pointer, out);
+    };
+
+    let raw_value: CompactString = match value {
+        Value::Object(map) => {
+            for (key, nested) in map {
+                descend(key, nested);
+            }
+            return;
+        }
+        Value::Array(items) => {
+            for (index, nested) in items.iter().enumerate() {
+                descend(&index.to_string(), nested);
+            }
+            return;
+        }
+        Value::Null => return,
+        Value::String(s) => s.into(),
+        Value::Bool(b) => b.to_string().into(),
+        Value::Number(n) => n.to_string().into(),
+    };
+
+    out.push((
+        pointer.to_string(),
+        ParsedVariable::simple(prefix, raw_value, VariableSource::Memory),
+    ));
+}
+
+/// Upper-cases `segment` and replaces anything that can't appear in an env
+/// key with `_`.
+#[cfg(feature = "file")]
+fn env_key_segment(segment: &str) -> String {
+    segment
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "file")]
+impl EnvSource for JsonSource {
+    fn id(&self) -> &SourceId {
+        &self.id
+    }
+
+    fn source_type(&self) -> SourceType {
+        SourceType::Memory
+    }
+
+    fn priority(&self) -> Priority {
+        Priority::MEMORY
+    }
+
+    fn capabilities(&self) -> SourceCapabilities {
+        SourceCapabilities::READ | SourceCapabilities::CACHEABLE
+    }
+
+    fn load(&self) -> Result<SourceSnapshot, SourceError> {
+        Ok(SourceSnapshot {
+            source_id: self.id.clone(),
+            source_type: SourceType::Memory,
+            variables: Arc::clone(&self.variables),
+            timestamp: std::time::Instant::now(),
+            version: Some(0),
+        })
+    }
+
+    fn has_changed(&self) -> bool {
+        false
+    }
+
+    fn invalidate(&self) {}
+
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.clone()
+    }
+}
+
+#[cfg(feature = "file")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flattens_nested_objects_and_arrays() {
+        let source = JsonSource::parse(
+            r#"{"db": {"url": "postgres://x", "pool": {"max": 10}}, "list": ["a", "b"], "debug": true, "unset": null, "api-key": "k"}"#,
+            "/repo/config.json",
+        )
+        .unwrap();
+        assert_eq!(source.id().as_str(), "memory:/repo/config.json");
+
+        let snapshot = source.load().unwrap();
+        let mut vars: Vec<_> = snapshot
+            .variables
+            .iter()
+            .map(|v| (v.key.as_str(), v.raw_value.as_str()))
+            .collect();
+        vars.sort();
+        assert_eq!(
+            vars,
+            vec![
+                ("API_KEY", "k"),
+                ("DB_POOL_MAX", "10"),
+                ("DB_URL", "postgres://x"),
+                ("DEBUG", "true"),
+                ("LIST_0", "a"),
+                ("LIST_1", "b"),
+            ]
+        );
+        assert!(snapshot
+            .variables
+            .iter()
+            .all(|v| v.source == VariableSource::Memory));
+        assert!(source.diagnostics().is_empty());
+    }
+
+    #[test]
+    fn test_reports_keys_that_flatten_alike() {
+        let source =
+            JsonSource::parse(r#"{"a-b": "dash", "a_b": "underscore"}"#, "c.json").unwrap();
+
+        let snapshot = source.load().unwrap();
+        let vars: Vec<_> = snapshot
+            .variables
+            .iter()
+            .map(|v| (v.key.as_str(), v.raw_value.as_str()))
+            .collect();
+        assert_eq!(vars, vec![("A_B", "underscore")]);
+
+        let diagnostics = source.diagnostics();
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].code, DiagnosticCode::EDF002);
+        assert!(diagnostics[0].message.contains("`a-b` and `a_b`"));
+    }
+
+    #[test]
+    fn test_rejects_non_object_json() {
+        assert!(matches!(
+            JsonSource::parse("[1, 2]", "list.json"),
+            Err(SourceError::ParseError { .. })
+        ));
+        assert!(matches!(
+            JsonSource::parse("{\"a\":", "broken.json"),
+            Err(SourceError::ParseError { .. })
+        ));
+    }
+}
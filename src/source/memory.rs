@@ -15,7 +15,7 @@ pub struct MemorySource {
 impl MemorySource {
     pub fn new() -> Self {
         Self {
-            id: SourceId::new("memory"),
+            id: SourceId::canonical(SourceType::Memory, "default"),
             variables: Mutex::new(IndexMap::new()),
             version: Mutex::new(0),
             last_loaded_version: Mutex::new(None),
@@ -34,6 +34,7 @@ impl MemorySource {
                 source: VariableSource::Memory,
                 description: None,
                 is_commented: false,
+                is_literal: false,
             },
         );
         *self.version.lock() += 1;
@@ -57,6 +58,7 @@ impl MemorySource {
                 source: VariableSource::Memory,
                 description: Some(description),
                 is_commented: false,
+                is_literal: false,
             },
         );
         *self.version.lock() += 1;
@@ -110,14 +112,18 @@ impl EnvSource for MemorySource {
     }
 
     fn load(&self) -> Result<SourceSnapshot, SourceError> {
-        let vars: Vec<ParsedVariable> = self.variables.lock().values().cloned().collect();
+        // Writers bump the version while holding the variables lock, so
+        // reading both under it pairs the variables with their version.
+        let variables = self.variables.lock();
+        let vars: Vec<ParsedVariable> = variables.values().cloned().collect();
         let current_version = *self.version.lock();
+        drop(variables);
 
-        // Update last loaded version
         *self.last_loaded_version.lock() = Some(current_version);
 
         Ok(SourceSnapshot {
             source_id: self.id.clone(),
+            source_type: SourceType::Memory,
             variables: vars.into(),
             timestamp: std::time::Instant::now(),
             version: Some(current_version),
@@ -129,7 +135,13 @@ impl EnvSource for MemorySource {
         *self.last_loaded_version.lock() != Some(current)
     }
 
-    fn invalidate(&self) {}
+    fn invalidate(&self) {
+        *self.last_loaded_version.lock() = None;
+    }
+
+    fn fingerprint(&self) -> Option<u64> {
+        Some(*self.version.lock())
+    }
 }
 
 #[cfg(test)]
@@ -173,4 +185,24 @@ mod tests {
 
         assert!(v2 > v1);
     }
+
+    #[test]
+    fn test_has_changed_tracks_loads() {
+        let source = MemorySource::new();
+        source.set("KEY1", "value1");
+        assert!(source.has_changed());
+
+        source.load().unwrap();
+        assert!(!source.has_changed());
+        source.load().unwrap();
+        assert!(!source.has_changed());
+
+        source.set("KEY1", "value2");
+        assert!(source.has_changed());
+        source.load().unwrap();
+        assert!(!source.has_changed());
+
+        source.invalidate();
+        assert!(source.has_changed());
+    }
 }
@@ -1,12 +1,23 @@
+#[cfg(feature = "async")]
+mod adapter;
 pub mod config;
 mod registry;
 mod traits;
 mod variable;
 
+#[cfg(feature = "file")]
+mod bytes;
 #[cfg(feature = "file")]
 mod file;
 #[cfg(feature = "file")]
 mod file_manager;
+#[cfg(feature = "file")]
+mod json;
+#[cfg(feature = "file")]
+pub(crate) mod parse;
+
+#[cfg(feature = "git")]
+mod git;
 
 #[cfg(feature = "shell")]
 mod shell;
@@ -16,18 +27,27 @@ mod memory;
 #[cfg(feature = "remote")]
 pub mod remote;
 
+#[cfg(feature = "async")]
+pub use adapter::AsyncAdapter;
 pub use config::{
     FileSourceConfig, MemorySourceConfig, RemoteSourceConfig, ShellSourceConfig,
-    SourceRefreshOptions,
+    SourceRefreshOptions, TrimPolicy, DEFAULT_MAX_FILE_BYTES,
 };
 pub use registry::*;
 pub use traits::*;
 pub use variable::*;
 
+#[cfg(feature = "file")]
+pub use bytes::BytesSource;
 #[cfg(feature = "file")]
 pub use file::FileSource;
 #[cfg(feature = "file")]
 pub use file_manager::FileSourceManager;
+#[cfg(feature = "file")]
+pub use json::JsonSource;
+
+#[cfg(feature = "git")]
+pub use git::GitSource;
 
 #[cfg(feature = "shell")]
 pub use shell::ShellSource;
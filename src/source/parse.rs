@@ -0,0 +1,579 @@
+use super::config::{FileSourceConfig, TrimPolicy};
+use super::variable::{ParsedVariable, VariableSource};
+use crate::error::{Diagnostic, DiagnosticCode, DiagnosticSeverity};
+use compact_str::CompactString;
+use hashbrown::HashMap;
+use std::path::Path;
+
+/// Result of parsing env-file content: the variables it defines plus any
+/// problems worth surfacing to the user.
+#[derive(Debug, Default)]
+pub(crate) struct ParsedContent {
+    pub variables: Vec<ParsedVariable>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// Parses dotenv content into variables.
+///
+/// When a key is defined more than once, the last definition wins (matching
+/// dotenv convention): earlier definitions are dropped and an `EDF002`
+/// warning is reported at the overriding line. Lines that are neither blank,
+/// comments nor part of an entry are reported as `EDF001` errors.
+///
+/// A leading UTF-8 BOM is ignored, and lines containing NUL bytes are blanked
+/// out and reported as `EDF003` warnings so they can't corrupt neighbouring
+/// entries.
+///
+/// The run of `#` comment lines directly above an entry becomes its
+/// description.
+///
+/// An unquoted value ending in a single `\` continues on the next line: the
+/// backslash and line break are dropped along with the next line's leading
+/// whitespace, so `A=one\` followed by `  two` defines `A` as `onetwo`. A
+/// next line that is itself a `KEY=` assignment is never swallowed, so
+/// `DIR=C:\` keeps its backslash.
+pub(crate) fn parse_env(content: &str, path: &Path) -> ParsedContent {
+    parse_env_trimmed(content, path, TrimPolicy::None)
+}
+
+/// [`parse_env`], stripping whitespace from unquoted values per `trim`.
+pub(crate) fn parse_env_trimmed(content: &str, path: &Path, trim: TrimPolicy) -> ParsedContent {
+    let content = strip_bom(content);
+    let mut diagnostics = Vec::new();
+    let sanitized;
+    let content = if content.contains('\0') {
+        sanitized = blank_nul_lines(content, path, &mut diagnostics);
+        sanitized.as_str()
+    } else {
+        content
+    };
+    let joined;
+    let mut protected = None;
+    let content = if content.contains("\\\n") || content.contains("\\\r\n") {
+        let kept;
+        (joined, kept) = join_continuations(content);
+        if !kept.is_empty() {
+            protected = Some(protect_backslashes(&joined, &kept));
+        }
+        joined.as_str()
+    } else {
+        content
+    };
+    // What korni sees: `content` with the backslashes it would otherwise
+    // take as line continuations swapped out. Offsets are the same in both.
+    let input = protected.as_deref().unwrap_or(content);
+
+    let parsed = korni::parse_with_options(
+        input,
+        korni::ParseOptions {
+            track_positions: true,
+            include_comments: false,
+        },
+    );
+
+    let mut variables: Vec<Option<ParsedVariable>> = Vec::with_capacity(parsed.len());
+    let mut seen: HashMap<CompactString, (usize, usize)> = HashMap::new();
+    let lines: Vec<&str> = content.lines().collect();
+    let mut covered_lines = vec![false; lines.len()];
+
+    for entry in parsed {
+        if let korni::Entry::Pair(kv) = entry {
+            let offset = kv.key_span.map(|s| s.start.offset).unwrap_or(0);
+            let key = CompactString::new(&kv.key);
+
+            let (first_line, _) = line_col(content, offset);
+            let first = first_line as usize - 1;
+            let last = first + kv.value.matches('\n').count();
+            for covered in covered_lines.iter_mut().take(last + 1).skip(first) {
+                *covered = true;
+            }
+
+            let description = if kv.is_comment {
+                None
+            } else {
+                description_above(&lines, first)
+            };
+
+            if !kv.is_comment {
+                if let Some((previous_idx, previous_offset)) =
+                    seen.insert(key.clone(), (variables.len(), offset))
+                {
+                    variables[previous_idx] = None;
+
+                    let (previous_line, _) = line_col(content, previous_offset);
+                    let (line, column) = line_col(content, offset);
+                    diagnostics.push(Diagnostic {
+                        severity: DiagnosticSeverity::Warning,
+                        code: DiagnosticCode::EDF002,
+                        message: format!(
+                            "Duplicate key `{}` overrides the definition on line {}",
+                            key, previous_line
+                        ),
+                        path: path.to_path_buf(),
+                        line,
+                        column,
+                        snippet: lines.get(line as usize - 1).map(|l| l.to_string()),
+                        label: Some(format!("overrides line {}", previous_line)),
+                    });
+                }
+            }
+
+            let raw_value = if is_quoted(content, offset) {
+                kv.value.as_ref()
+            } else {
+                trim.apply(unquoted_value(input, content, &kv))
+            };
+            variables.push(Some(ParsedVariable {
+                key,
+                raw_value: CompactString::new(raw_value),
+                source: VariableSource::File {
+                    path: path.to_path_buf(),
+                    offset,
+                },
+                description,
+                is_commented: kv.is_comment,
+                is_literal: false,
+            }));
+        }
+    }
+
+    for (idx, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        if covered_lines[idx] || trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        diagnostics.push(Diagnostic {
+            severity: DiagnosticSeverity::Error,
+            code: DiagnosticCode::EDF001,
+            message: format!("Expected `KEY=VALUE`, found `{}`", trimmed),
+            path: path.to_path_buf(),
+            line: idx as u32 + 1,
+            column: (line.len() - line.trim_start().len()) as u32 + 1,
+            snippet: Some(line.to_string()),
+            label: Some("expected `KEY=VALUE`".to_string()),
+        });
+    }
+    diagnostics.sort_by_key(|d| d.line);
+
+    ParsedContent {
+        variables: variables.into_iter().flatten().collect(),
+        diagnostics,
+    }
+}
+
+/// Parses INI-style content where `[section]` headers prefix the keys that
+/// follow them: `host` under `[db]` becomes `DB_HOST`. Keys before the first
+/// header are kept as written. Each section is parsed on its own, so the
+/// same key under two headers is not a duplicate.
+pub(crate) fn parse_env_sections(content: &str, path: &Path, trim: TrimPolicy) -> ParsedContent {
+    let content = strip_bom(content);
+
+    // Byte ranges of each section's body; header lines belong to none.
+    let mut sections: Vec<(usize, usize, Option<String>)> = vec![(0, 0, None)];
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim();
+        let header = trimmed
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+            .map(str::trim)
+            .filter(|name| !name.is_empty());
+        if let Some(name) = header {
+            if let Some(last) = sections.last_mut() {
+                last.1 = offset;
+            }
+            sections.push((offset + line.len(), 0, Some(name.to_uppercase())));
+        }
+        offset += line.len();
+    }
+    if let Some(last) = sections.last_mut() {
+        last.1 = content.len();
+    }
+
+    let mut result = ParsedContent::default();
+    for (start, end, section) in sections {
+        // Blank everything outside the section so offsets and line numbers
+        // still refer to the whole file.
+        let blank = |text: &str| -> String {
+            text.bytes()
+                .map(|b| if b == b'\n' { '\n' } else { ' ' })
+                .collect()
+        };
+        let mut body = blank(&content[..start]);
+        body.push_str(&content[start..end]);
+        body.push_str(&blank(&content[end..]));
+
+        let parsed = parse_env_trimmed(&body, path, trim);
+        result.diagnostics.extend(parsed.diagnostics);
+        for mut variable in parsed.variables {
+            if let Some(section) = &section {
+                variable.key =
+                    compact_str::format_compact!("{}_{}", section, variable.key.to_uppercase());
+            }
+            result.variables.retain(|v| v.key != variable.key);
+            result.variables.push(variable);
+        }
+    }
+    result.diagnostics.sort_by_key(|d| d.line);
+    result
+}
+
+/// An unquoted value as written in `content`, given the `input` korni
+/// parsed it from. korni stops the value at the first blank, so whitespace
+/// running from there to the end of the line is taken back from the source
+/// text for [`TrimPolicy`] to decide on.
+fn unquoted_value<'a>(input: &str, content: &'a str, kv: &'a korni::KeyValuePair<'_>) -> &'a str {
+    let Some(span) = kv.value_span else {
+        return kv.value.as_ref();
+    };
+    let (start, end) = (span.start.offset, span.end.offset);
+    if input.get(start..end) != Some(kv.value.as_ref()) {
+        return kv.value.as_ref();
+    }
+
+    let rest = &content[end..];
+    let line_rest =
+        rest[..memchr::memchr(b'\n', rest.as_bytes()).unwrap_or(rest.len())].trim_end_matches('\r');
+    if line_rest.trim_start_matches([' ', '\t']).is_empty() {
+        &content[start..end + line_rest.len()]
+    } else {
+        &content[start..end]
+    }
+}
+
+/// Whether the value of the entry whose key starts at `key_offset` opens
+/// with a quote.
+fn is_quoted(content: &str, key_offset: usize) -> bool {
+    content
+        .get(key_offset..)
+        .and_then(|entry| entry.split_once('='))
+        .map(|(_, value)| {
+            value
+                .trim_start_matches([' ', '\t'])
+                .starts_with(['"', '\'', '`'])
+        })
+        .unwrap_or(false)
+}
+
+/// Parses the raw bytes of an env file the way [`FileSource`] does: decoded
+/// line by line, with `# abundantis:` directives applied and `config`'s
+/// section and trim settings honoured. Diagnostics come back in line order.
+///
+/// [`FileSource`]: super::FileSource
+pub(crate) fn parse_env_file(
+    bytes: &[u8],
+    path: &Path,
+    config: &FileSourceConfig,
+) -> ParsedContent {
+    let (mut content, mut decode_diagnostics) = decode_lines(bytes, path);
+    let (directives, directive_diagnostics) = take_directives(&mut content, path);
+    decode_diagnostics.extend(directive_diagnostics);
+    let mut parsed = if config.sections {
+        parse_env_sections(&content, path, config.trim_values)
+    } else {
+        parse_env_trimmed(&content, path, config.trim_values)
+    };
+    directives.apply(&mut parsed.variables);
+    if !decode_diagnostics.is_empty() {
+        parsed.diagnostics.extend(decode_diagnostics);
+        parsed.diagnostics.sort_by_key(|d| d.line);
+    }
+    parsed
+}
+
+/// Decodes file bytes as UTF-8, replacing every line that isn't valid UTF-8
+/// with spaces of the same length and reporting it as an `EDF001` error, so
+/// one bad byte doesn't cost the rest of the file and offsets stay intact.
+pub(crate) fn decode_lines(bytes: &[u8], path: &Path) -> (String, Vec<Diagnostic>) {
+    let mut diagnostics = Vec::new();
+    let mut decoded = String::with_capacity(bytes.len());
+    for (idx, line) in bytes.split_inclusive(|b| *b == b'\n').enumerate() {
+        match std::str::from_utf8(line) {
+            Ok(line) => decoded.push_str(line),
+            Err(e) => {
+                let body_len = line.len()
+                    - line
+                        .iter()
+                        .rev()
+                        .take_while(|b| **b == b'\n' || **b == b'\r')
+                        .count();
+                let body = &line[..body_len];
+                diagnostics.push(Diagnostic {
+                    severity: DiagnosticSeverity::Error,
+                    code: DiagnosticCode::EDF001,
+                    message: "Line is not valid UTF-8 and was skipped".to_string(),
+                    path: path.to_path_buf(),
+                    line: idx as u32 + 1,
+                    column: e.valid_up_to() as u32 + 1,
+                    snippet: Some(String::from_utf8_lossy(body).into_owned()),
+                    label: Some("invalid UTF-8".to_string()),
+                });
+                decoded.extend(std::iter::repeat(' ').take(body_len));
+                decoded.push_str(&String::from_utf8_lossy(&line[body_len..]));
+            }
+        }
+    }
+    (decoded, diagnostics)
+}
+
+/// Joins the run of `#` comment lines directly above line `index`, leaving
+/// out commented-out assignments such as `# OLD_KEY=value`.
+pub(crate) fn description_above(lines: &[&str], index: usize) -> Option<CompactString> {
+    let comments: Vec<&str> = lines[..index.min(lines.len())]
+        .iter()
+        .rev()
+        .map(|line| line.trim())
+        .take_while(|line| line.starts_with('#'))
+        .map(|line| line.trim_start_matches('#').trim())
+        .filter(|line| !starts_assignment(line))
+        .collect();
+    if comments.is_empty() {
+        return None;
+    }
+
+    let description = comments.into_iter().rev().collect::<Vec<_>>().join(" ");
+    Some(CompactString::new(description.trim()))
+}
+
+/// Settings an env file declares for itself on its first line, as
+/// `#!abundantis interpolation=off, prefix=APP_` or
+/// `# abundantis: interpolation=off, prefix=APP_`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FileDirectives {
+    /// Interpolate `${...}` references in the file's values.
+    pub interpolation: bool,
+    /// Prepended to every key the file defines.
+    pub prefix: Option<CompactString>,
+}
+
+impl Default for FileDirectives {
+    fn default() -> Self {
+        Self {
+            interpolation: true,
+            prefix: None,
+        }
+    }
+}
+
+impl FileDirectives {
+    pub(crate) fn apply(&self, variables: &mut [ParsedVariable]) {
+        for variable in variables {
+            if let Some(prefix) = &self.prefix {
+                variable.key = compact_str::format_compact!("{}{}", prefix, variable.key);
+            }
+            variable.is_literal = !self.interpolation;
+        }
+    }
+}
+
+const DIRECTIVE_MARKERS: &[&str] = &["#!abundantis", "# abundantis:"];
+
+/// Reads the directive line at the top of `content`, if there is one, and
+/// blanks it so it isn't taken as the first entry's description. Unknown or
+/// malformed settings are skipped and reported as `EDF004` warnings.
+pub(crate) fn take_directives(
+    content: &mut String,
+    path: &Path,
+) -> (FileDirectives, Vec<Diagnostic>) {
+    let mut directives = FileDirectives::default();
+    let mut diagnostics = Vec::new();
+
+    let start = content.len() - strip_bom(content).len();
+    let line = content[start..].split('\n').next().unwrap_or_default();
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let Some(settings) = DIRECTIVE_MARKERS
+        .iter()
+        .find_map(|marker| line.strip_prefix(marker))
+    else {
+        return (directives, diagnostics);
+    };
+
+    for setting in settings.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let applied = match setting.split_once('=').map(|(n, v)| (n.trim(), v.trim())) {
+            Some(("interpolation", "on" | "true")) => {
+                directives.interpolation = true;
+                true
+            }
+            Some(("interpolation", "off" | "false")) => {
+                directives.interpolation = false;
+                true
+            }
+            Some(("prefix", prefix)) => {
+                directives.prefix = Some(CompactString::new(prefix));
+                true
+            }
+            _ => false,
+        };
+        if !applied {
+            diagnostics.push(Diagnostic {
+                severity: DiagnosticSeverity::Warning,
+                code: DiagnosticCode::EDF004,
+                message: format!("Unknown file directive `{}`", setting),
+                path: path.to_path_buf(),
+                line: 1,
+                column: (line.find(setting).unwrap_or(0) + 1) as u32,
+                snippet: Some(line.to_string()),
+                label: Some("expected `interpolation=on|off` or `prefix=...`".to_string()),
+            });
+        }
+    }
+
+    let len = line.len();
+    content.replace_range(start..start + len, &" ".repeat(len));
+    (directives, diagnostics)
+}
+
+/// Drops a leading UTF-8 byte order mark, which editors on Windows like to add.
+pub(crate) fn strip_bom(content: &str) -> &str {
+    content.strip_prefix('\u{feff}').unwrap_or(content)
+}
+
+/// Replaces every line containing a NUL byte with spaces of the same length,
+/// keeping the offsets of the remaining entries intact.
+fn blank_nul_lines(content: &str, path: &Path, diagnostics: &mut Vec<Diagnostic>) -> String {
+    let mut sanitized = String::with_capacity(content.len());
+    for (idx, line) in content.split_inclusive('\n').enumerate() {
+        let body = line.trim_end_matches(['\r', '\n']);
+        if !body.contains('\0') {
+            sanitized.push_str(line);
+            continue;
+        }
+
+        diagnostics.push(Diagnostic {
+            severity: DiagnosticSeverity::Warning,
+            code: DiagnosticCode::EDF003,
+            message: "Line contains a NUL byte and was skipped".to_string(),
+            path: path.to_path_buf(),
+            line: idx as u32 + 1,
+            column: body.find('\0').map(|c| c as u32 + 1).unwrap_or(1),
+            snippet: Some(body.replace('\0', "\\0")),
+            label: Some("NUL byte".to_string()),
+        });
+        sanitized.extend(std::iter::repeat(' ').take(body.len()));
+        sanitized.push_str(&line[body.len()..]);
+    }
+    sanitized
+}
+
+/// Whether `line` ends in a backslash that continues its value on the next
+/// line. A doubled backslash is an escaped one and doesn't continue.
+pub(crate) fn continues(line: &str) -> bool {
+    let body = line.trim_end_matches(['\r', '\n']);
+    let trailing = body.len() - body.trim_end_matches('\\').len();
+    trailing % 2 == 1
+}
+
+/// Whether `line` assigns a key, as `KEY=...` or `export KEY=...`.
+pub(crate) fn starts_assignment(line: &str) -> bool {
+    let line = line.trim_start();
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    let Some((key, _)) = line.split_once('=') else {
+        return false;
+    };
+    let key = key.trim_end();
+    key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-')
+}
+
+/// Whether a trailing backslash may pull in the upcoming line: there is one,
+/// and it doesn't start an assignment of its own.
+fn joins_next<'a>(lines: &mut std::iter::Peekable<impl Iterator<Item = &'a str>>) -> bool {
+    lines.peek().is_some_and(|next| !starts_assignment(next))
+}
+
+/// Joins backslash-continued lines of unquoted values into one line. The
+/// lines the value was continued onto are left blank, padded with spaces so
+/// that byte offsets and line numbers of every entry stay the same.
+///
+/// Also returns the offsets of the trailing backslashes of unquoted values
+/// that were not joined, such as `DIR=C:\` above another assignment, which
+/// korni would otherwise continue onto the next line.
+fn join_continuations(content: &str) -> (String, Vec<usize>) {
+    let mut kept = Vec::new();
+    let mut joined = String::with_capacity(content.len());
+    let mut lines = content.split_inclusive('\n').peekable();
+    let mut open_quote: Option<char> = None;
+
+    while let Some(line) = lines.next() {
+        let body = line.trim_end_matches(['\r', '\n']);
+        if let Some(quote) = open_quote {
+            if body.contains(quote) {
+                open_quote = None;
+            }
+            joined.push_str(line);
+            continue;
+        }
+
+        let value = body.split_once('=').map(|(_, value)| value.trim_start());
+        let quote = value
+            .and_then(|value| value.chars().next())
+            .filter(|c| *c == '"' || *c == '\'');
+        if let (Some(quote), Some(value)) = (quote, value) {
+            if !value[1..].contains(quote) {
+                open_quote = Some(quote);
+            }
+        }
+        let unquoted = value.is_some() && quote.is_none() && !body.trim_start().starts_with('#');
+        if !unquoted || !continues(body) || !joins_next(&mut lines) {
+            if unquoted && body.ends_with('\\') {
+                kept.push(joined.len() + body.len() - 1);
+            }
+            joined.push_str(line);
+            continue;
+        }
+
+        let mut logical = body[..body.len() - 1].to_string();
+        let mut consumed = vec![line];
+        while let Some(next) = lines
+            .next_if(|next| continues(consumed[consumed.len() - 1]) && !starts_assignment(next))
+        {
+            let next_body = next.trim_end_matches(['\r', '\n']).trim_start();
+            logical.push_str(
+                next_body
+                    .strip_suffix('\\')
+                    .filter(|_| continues(next) && joins_next(&mut lines))
+                    .unwrap_or(next_body),
+            );
+            consumed.push(next);
+        }
+
+        if logical.ends_with('\\') {
+            kept.push(joined.len() + logical.len() - 1);
+        }
+        let original: usize = consumed.iter().map(|line| line.len()).sum();
+        let endings: usize = consumed.iter().map(|line| line_ending(line).len()).sum();
+        joined.push_str(&logical);
+        joined.push_str(line_ending(consumed[0]));
+        let padding = original - endings - logical.len();
+        joined.extend(std::iter::repeat(' ').take(padding));
+        for line in &consumed[1..] {
+            joined.push_str(line_ending(line));
+        }
+    }
+    (joined, kept)
+}
+
+/// `content` with the backslash at each of `offsets` replaced by a `/`.
+fn protect_backslashes(content: &str, offsets: &[usize]) -> String {
+    let mut protected = content.to_string();
+    for &offset in offsets {
+        protected.replace_range(offset..offset + 1, "/");
+    }
+    protected
+}
+
+fn line_ending(line: &str) -> &str {
+    &line[line.trim_end_matches(['\r', '\n']).len()..]
+}
+
+/// Converts a byte offset into a 1-based `(line, column)` pair.
+pub(crate) fn line_col(content: &str, offset: usize) -> (u32, u32) {
+    let offset = offset.min(content.len());
+    let before = &content.as_bytes()[..offset];
+    let line = memchr::memchr_iter(b'\n', before).count() + 1;
+    let line_start = memchr::memrchr(b'\n', before).map(|i| i + 1).unwrap_or(0);
+    (line as u32, (offset - line_start) as u32 + 1)
+}
@@ -1,4 +1,5 @@
 use crate::error::SourceError;
+use crate::events::{AbundantisEvent, EventBus};
 use crate::source::traits::*;
 use compact_str::CompactString;
 use hashbrown::HashMap;
@@ -17,6 +18,10 @@ pub struct SourceRegistry {
     /// External provider adapters (out-of-process providers).
     #[cfg(feature = "remote")]
     external_providers: RwLock<HashMap<String, Arc<ExternalProviderAdapter>>>,
+    event_bus: RwLock<Option<Arc<EventBus>>>,
+    /// Last snapshot of each cacheable sync source, keyed by the source's
+    /// fingerprint at the time it was loaded.
+    snapshots: RwLock<HashMap<SourceId, (u64, SourceSnapshot)>>,
 }
 
 impl SourceRegistry {
@@ -46,6 +51,8 @@ impl SourceRegistry {
             factories: RwLock::new(factories),
             #[cfg(feature = "remote")]
             external_providers: RwLock::new(HashMap::new()),
+            event_bus: RwLock::new(None),
+            snapshots: RwLock::new(HashMap::new()),
         }
     }
 
@@ -55,17 +62,43 @@ impl SourceRegistry {
             .insert(CompactString::new(source_type), Arc::new(factory));
     }
 
+    /// Publishes `SourceAdded`/`SourceRemoved` on `event_bus` from now on
+    /// whenever a sync source is registered or unregistered.
+    pub fn set_event_bus(&self, event_bus: Arc<EventBus>) {
+        *self.event_bus.write() = Some(event_bus);
+    }
+
+    #[cfg(feature = "watch")]
+    pub(crate) fn publishes_events(&self) -> bool {
+        self.event_bus.read().is_some()
+    }
+
+    fn publish(&self, event: AbundantisEvent) {
+        let event_bus = self.event_bus.read().clone();
+        if let Some(event_bus) = event_bus {
+            event_bus.publish(event);
+        }
+    }
+
     pub fn register_sync(&self, source: Arc<dyn EnvSource>) -> SourceId {
         let id = source.id().clone();
-        self.sync_sources.write().insert(id.clone(), source.clone());
+        let replaced = self
+            .sync_sources
+            .write()
+            .insert(id.clone(), source.clone())
+            .is_some();
+        self.snapshots.write().remove(&id);
 
         if source.source_type() == SourceType::File {
-            if let Some(path) = id.as_str().strip_prefix("file:") {
+            if let Some(path) = id.as_str().strip_prefix(SourceType::File.id_prefix()) {
                 let path_buf = std::path::PathBuf::from(path);
                 self.path_index.write().insert(path_buf, id.clone());
             }
         }
 
+        if !replaced {
+            self.publish(AbundantisEvent::SourceAdded { source_id: id.clone() });
+        }
         id
     }
 
@@ -76,6 +109,12 @@ impl SourceRegistry {
         id
     }
 
+    /// Registers a sync source on the async path, wrapping it in an [`AsyncAdapter`].
+    #[cfg(feature = "async")]
+    pub fn register_sync_as_async<S: EnvSource + 'static>(&self, source: S) -> SourceId {
+        self.register_async(Arc::new(crate::source::AsyncAdapter::new(source)))
+    }
+
     pub fn sync_sources_by_priority(&self) -> Vec<Arc<dyn EnvSource>> {
         let sources = self.sync_sources.read();
         let mut sorted: Vec<_> = sources.values().cloned().collect();
@@ -99,7 +138,7 @@ impl SourceRegistry {
             let mut snapshots = Vec::new();
             let sources_guard = self.sync_sources.read();
             for (_id, source) in sources_guard.iter() {
-                let snapshot = source.load()?;
+                let snapshot = self.load_sync(source)?;
                 snapshots.push(snapshot);
             }
             snapshots
@@ -171,17 +210,93 @@ impl SourceRegistry {
         }
     }
 
+    /// Metadata for the sync or async source registered under `id`.
+    pub fn metadata(&self, id: &SourceId) -> Option<SourceMetadata> {
+        if let Some(source) = self.sync_sources.read().get(id) {
+            return Some(source.metadata());
+        }
+        #[cfg(feature = "async")]
+        if let Some(source) = self.async_sources.read().get(id) {
+            return Some(source.metadata());
+        }
+        None
+    }
+
+    pub fn get_sync(&self, id: &SourceId) -> Option<Arc<dyn EnvSource>> {
+        self.sync_sources.read().get(id).cloned()
+    }
+
+    /// Swaps the source registered under `id` for `source` under a single
+    /// write lock, so concurrent readers see either the old or the new source
+    /// and never a gap. Returns the replaced source, if there was one.
+    pub fn replace_sync(
+        &self,
+        id: &SourceId,
+        source: Arc<dyn EnvSource>,
+    ) -> Option<Arc<dyn EnvSource>> {
+        let new_id = source.id().clone();
+        let mut sources = self.sync_sources.write();
+        let previous = sources.remove(id);
+        sources.insert(new_id.clone(), source.clone());
+        {
+            let mut snapshots = self.snapshots.write();
+            snapshots.remove(id);
+            snapshots.remove(&new_id);
+        }
+
+        let mut path_index = self.path_index.write();
+        if let Some(path) = id.as_str().strip_prefix(SourceType::File.id_prefix()) {
+            path_index.remove(std::path::Path::new(path));
+        }
+        if source.source_type() == SourceType::File {
+            if let Some(path) = new_id.as_str().strip_prefix(SourceType::File.id_prefix()) {
+                path_index.insert(std::path::PathBuf::from(path), new_id.clone());
+            }
+        }
+
+        previous
+    }
+
     pub fn is_registered(&self, id: &SourceId) -> bool {
         self.sync_sources.read().contains_key(id)
     }
 
     pub fn unregister_sync(&self, id: &SourceId) {
-        self.sync_sources.write().remove(id);
+        let removed = self.sync_sources.write().remove(id).is_some();
+        self.snapshots.write().remove(id);
 
-        if let Some(path) = id.as_str().strip_prefix("file:") {
+        if let Some(path) = id.as_str().strip_prefix(SourceType::File.id_prefix()) {
             let path_buf = std::path::PathBuf::from(path);
             self.path_index.write().remove(&path_buf);
         }
+
+        if removed {
+            self.publish(AbundantisEvent::SourceRemoved { source_id: id.clone() });
+        }
+    }
+
+    /// Loads `source`, or returns its last snapshot if the source is
+    /// cacheable and its fingerprint hasn't changed since.
+    fn load_sync(&self, source: &Arc<dyn EnvSource>) -> Result<SourceSnapshot, SourceError> {
+        let fingerprint = match source.fingerprint() {
+            Some(fingerprint)
+                if source.capabilities().contains(SourceCapabilities::CACHEABLE) =>
+            {
+                fingerprint
+            }
+            _ => return source.load(),
+        };
+        if let Some((cached, snapshot)) = self.snapshots.read().get(source.id()) {
+            if *cached == fingerprint {
+                return Ok(snapshot.clone());
+            }
+        }
+
+        let snapshot = source.load()?;
+        self.snapshots
+            .write()
+            .insert(source.id().clone(), (fingerprint, snapshot.clone()));
+        Ok(snapshot)
     }
 
     pub fn registered_file_paths(&self) -> Vec<std::path::PathBuf> {
@@ -194,6 +309,22 @@ impl SourceRegistry {
         let count = count + self.async_sources.read().len();
         count
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.source_count() == 0
+    }
+
+    /// Unregisters every source, including external providers. Registered
+    /// factories are kept.
+    pub fn clear(&self) {
+        self.sync_sources.write().clear();
+        #[cfg(feature = "async")]
+        self.async_sources.write().clear();
+        self.path_index.write().clear();
+        self.snapshots.write().clear();
+        #[cfg(feature = "remote")]
+        self.external_providers.write().clear();
+    }
 }
 
 // External provider methods (out-of-process providers)
@@ -254,7 +385,7 @@ impl SourceRegistry {
     pub fn load_all(&self) -> Result<Vec<SourceSnapshot>, SourceError> {
         let mut snapshots = Vec::new();
         for source in self.sync_sources.read().values() {
-            snapshots.push(source.load()?);
+            snapshots.push(self.load_sync(source)?);
         }
         Ok(snapshots)
     }
@@ -326,4 +457,120 @@ mod tests {
         let registry = SourceRegistry::new();
         assert_eq!(registry.source_count(), 0);
     }
+
+    #[cfg(feature = "file")]
+    #[test]
+    fn test_clear_removes_all_sources() {
+        use crate::source::memory::MemorySource;
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(".env");
+        std::fs::write(&path, "KEY=value\n").unwrap();
+
+        let registry = SourceRegistry::new();
+        registry.register_sync(Arc::new(crate::source::FileSource::new(&path).unwrap()));
+        registry.register_sync(Arc::new(MemorySource::new()));
+        assert_eq!(registry.source_count(), 2);
+        assert_eq!(registry.registered_file_paths().len(), 1);
+
+        registry.clear();
+        assert_eq!(registry.source_count(), 0);
+        assert!(registry.is_empty());
+        assert!(registry.registered_file_paths().is_empty());
+        assert!(registry.sources_for_paths(&[path]).is_empty());
+    }
+
+    #[test]
+    fn test_registration_publishes_source_events() {
+        use crate::events::EventSubscriber;
+        use crate::source::memory::MemorySource;
+
+        struct Recorder(parking_lot::Mutex<Vec<AbundantisEvent>>);
+        impl EventSubscriber for Recorder {
+            fn on_event(&self, event: &AbundantisEvent) {
+                self.0.lock().push(event.clone());
+            }
+        }
+
+        let recorder = Arc::new(Recorder(parking_lot::Mutex::new(Vec::new())));
+        let event_bus = Arc::new(EventBus::new(16));
+        event_bus.subscribe(Arc::clone(&recorder) as Arc<dyn EventSubscriber>);
+
+        let registry = SourceRegistry::new();
+        registry.set_event_bus(event_bus);
+        let id = registry.register_sync(Arc::new(MemorySource::new()));
+        assert_eq!(registry.register_sync(Arc::new(MemorySource::new())), id);
+        registry.unregister_sync(&id);
+        registry.unregister_sync(&id);
+
+        assert_eq!(
+            *recorder.0.lock(),
+            vec![
+                AbundantisEvent::SourceAdded { source_id: id.clone() },
+                AbundantisEvent::SourceRemoved { source_id: id },
+            ]
+        );
+    }
+
+    #[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+    #[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+    async fn test_replacing_a_source_drops_its_cached_snapshot() {
+        use crate::source::memory::MemorySource;
+
+        let value = |snapshots: Vec<SourceSnapshot>| snapshots[0].variables[0].raw_value.clone();
+        let source = |value: &str| {
+            let source = MemorySource::new();
+            source.set("KEY", value);
+            Arc::new(source)
+        };
+
+        let registry = SourceRegistry::new();
+        let id = registry.register_sync(source("first"));
+        let loaded = registry.load_all().await.unwrap();
+        assert_eq!(value(loaded), "first");
+
+        // Same id and version count, so the fingerprint is the same too.
+        registry.replace_sync(&id, source("second"));
+        let loaded = registry.load_all().await.unwrap();
+        assert_eq!(value(loaded), "second");
+
+        registry.clear();
+        registry.register_sync(source("third"));
+        let loaded = registry.load_all().await.unwrap();
+        assert_eq!(value(loaded), "third");
+    }
+
+    #[test]
+    fn test_replace_sync_has_no_gap() {
+        use crate::source::memory::MemorySource;
+        use std::sync::atomic::{AtomicBool, Ordering};
+
+        let registry = Arc::new(SourceRegistry::new());
+        let id = registry.register_sync(Arc::new(MemorySource::new()));
+        let done = Arc::new(AtomicBool::new(false));
+
+        let reader = {
+            let registry = Arc::clone(&registry);
+            let id = id.clone();
+            let done = Arc::clone(&done);
+            std::thread::spawn(move || {
+                while !done.load(Ordering::Relaxed) {
+                    assert!(registry.get_sync(&id).is_some(), "source missing mid-swap");
+                }
+            })
+        };
+
+        for i in 0..1000 {
+            let replacement = MemorySource::new();
+            replacement.set("GENERATION", i.to_string());
+            let previous = registry.replace_sync(&id, Arc::new(replacement));
+            assert!(previous.is_some());
+        }
+        done.store(true, Ordering::Relaxed);
+
+        reader.join().unwrap();
+        assert_eq!(registry.source_count(), 1);
+        let snapshot = registry.get_sync(&id).unwrap().load().unwrap();
+        assert_eq!(snapshot.variables[0].raw_value.as_str(), "999");
+    }
 }
@@ -123,7 +123,7 @@ impl ExternalProviderAdapter {
         config: ExternalProviderConfig,
     ) -> Self {
         let provider_id = provider_id.into();
-        let source_id = SourceId::new(format!("external:{}", provider_id));
+        let source_id = SourceId::canonical(SourceType::Remote, &provider_id);
 
         Self {
             provider_id,
@@ -712,11 +712,13 @@ impl AsyncEnvSource for ExternalProviderAdapter {
                     },
                     description: s.description.as_ref().map(|d| CompactString::from(d.as_str())),
                     is_commented: false,
+                    is_literal: false,
                 })
                 .collect();
 
             return Ok(SourceSnapshot {
                 source_id: self.source_id.clone(),
+                source_type: SourceType::Remote,
                 variables: Arc::from(variables),
                 timestamp: Instant::now(),
                 version: None,
@@ -740,11 +742,13 @@ impl AsyncEnvSource for ExternalProviderAdapter {
                 },
                 description: s.description.as_ref().map(|d| CompactString::from(d.as_str())),
                 is_commented: false,
+                is_literal: false,
             })
             .collect();
 
         Ok(SourceSnapshot {
             source_id: self.source_id.clone(),
+            source_type: SourceType::Remote,
             variables: Arc::from(variables),
             timestamp: Instant::now(),
             version: None,
@@ -801,6 +805,6 @@ mod tests {
         let config = ExternalProviderConfig::default();
         let adapter = ExternalProviderAdapter::new("doppler", "/path/to/provider", config);
 
-        assert_eq!(adapter.id().as_str(), "external:doppler");
+        assert_eq!(adapter.id().as_str(), "remote:doppler");
     }
 }
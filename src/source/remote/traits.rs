@@ -184,7 +184,7 @@ impl ScopeSelection {
 }
 
 /// Provider-specific configuration.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct ProviderConfig {
     /// Whether this provider is enabled.
     #[serde(default)]
@@ -223,7 +223,7 @@ impl ProviderConfig {
 }
 
 /// Configuration for all remote sources.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct RemoteSourcesConfig {
     /// Per-provider configurations.
     #[serde(flatten)]
@@ -1,3 +1,4 @@
+use super::config::ShellSourceConfig;
 use super::traits::*;
 use super::variable::{ParsedVariable, VariableSource};
 use crate::error::SourceError;
@@ -10,17 +11,50 @@ use std::hash::{Hash, Hasher};
 #[cfg(feature = "shell")]
 pub struct ShellSource {
     id: SourceId,
+    config: ShellSourceConfig,
+    includes: Option<globset::GlobSet>,
     cached: Mutex<Option<HashMap<String, String>>>,
     cached_hash: Mutex<Option<u64>>,
+    last_refreshed: Mutex<Option<std::time::Instant>>,
 }
 
 #[cfg(feature = "shell")]
 impl ShellSource {
     pub fn new() -> Self {
+        Self::with_config(ShellSourceConfig::default())
+    }
+
+    pub fn with_config(config: ShellSourceConfig) -> Self {
+        Self::with_id("process", config)
+    }
+
+    /// Creates a shell source with the id `shell:<name>`, so several sources
+    /// with different configs (e.g. the full process env and a curated
+    /// subset) can be registered side by side.
+    pub fn with_id(name: &str, config: ShellSourceConfig) -> Self {
+        let includes = config.include_patterns.as_ref().map(|patterns| {
+            let mut builder = globset::GlobSetBuilder::new();
+            for pattern in patterns {
+                match globset::Glob::new(pattern) {
+                    Ok(glob) => {
+                        builder.add(glob);
+                    }
+                    Err(e) => tracing::warn!("Invalid shell include pattern {}: {}", pattern, e),
+                }
+            }
+            builder.build().unwrap_or_else(|e| {
+                tracing::warn!("Failed to build shell include set: {}", e);
+                globset::GlobSet::empty()
+            })
+        });
+
         Self {
-            id: SourceId::new("shell:process"),
+            id: SourceId::canonical(SourceType::Shell, name),
+            config,
+            includes,
             cached: Mutex::new(None),
             cached_hash: Mutex::new(None),
+            last_refreshed: Mutex::new(None),
         }
     }
 
@@ -35,14 +69,24 @@ impl ShellSource {
             return env.clone();
         }
 
-        let env: HashMap<String, String> = std::env::vars().collect();
+        let env: HashMap<String, String> = self.vars().collect();
         *cache = Some(env.clone());
         env
     }
 
+    /// Process variables visible to this source under its config.
+    fn vars(&self) -> impl Iterator<Item = (String, String)> + '_ {
+        std::env::vars()
+            .filter(|_| self.config.enabled)
+            .filter(|(key, _)| match &self.includes {
+                Some(includes) => includes.is_match(key),
+                None => true,
+            })
+    }
+
     fn compute_env_hash(&self) -> u64 {
         let mut hasher = AHasher::default();
-        let mut vars: Vec<_> = std::env::vars().collect();
+        let mut vars: Vec<_> = self.vars().collect();
         // Sort for consistent hashing
         vars.sort_by(|a, b| a.0.cmp(&b.0));
         for (key, value) in vars {
@@ -88,14 +132,17 @@ impl EnvSource for ShellSource {
                 source: VariableSource::Shell,
                 description: None,
                 is_commented: false,
+                is_literal: false,
             })
             .collect();
 
         // Update cached hash after loading
         *self.cached_hash.lock() = Some(self.compute_env_hash());
+        *self.last_refreshed.lock() = Some(std::time::Instant::now());
 
         Ok(SourceSnapshot {
             source_id: self.id.clone(),
+            source_type: SourceType::Shell,
             variables: vars.into(),
             timestamp: std::time::Instant::now(),
             version: None,
@@ -110,6 +157,17 @@ impl EnvSource for ShellSource {
     fn invalidate(&self) {
         self.refresh();
     }
+
+    fn fingerprint(&self) -> Option<u64> {
+        Some(self.compute_env_hash())
+    }
+
+    fn metadata(&self) -> SourceMetadata {
+        SourceMetadata {
+            last_refreshed: *self.last_refreshed.lock(),
+            ..SourceMetadata::default()
+        }
+    }
 }
 
 #[cfg(feature = "shell")]
@@ -123,6 +181,7 @@ mod tests {
 
         let source = ShellSource::new();
         let snapshot = source.load().unwrap();
+        assert_eq!(snapshot.source_type, SourceType::Shell);
 
         let test_var = snapshot
             .variables
@@ -139,4 +198,47 @@ mod tests {
         let source = ShellSource::new();
         assert_eq!(source.priority(), Priority::SHELL);
     }
+
+    #[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+    #[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+    async fn test_shell_sources_with_distinct_ids_coexist() {
+        std::env::set_var("ABUNDANTIS_SCOPED_APP_PORT", "8080");
+        std::env::set_var("ABUNDANTIS_SCOPED_DB_URL", "postgres://localhost");
+
+        let scoped = |name: &str, pattern: &str| {
+            ShellSource::with_id(
+                name,
+                ShellSourceConfig {
+                    include_patterns: Some(vec![pattern.into()]),
+                    ..Default::default()
+                },
+            )
+        };
+        let registry = crate::source::SourceRegistry::new();
+        registry.register_sync(std::sync::Arc::new(scoped(
+            "app",
+            "ABUNDANTIS_SCOPED_APP_*",
+        )));
+        registry.register_sync(std::sync::Arc::new(scoped("db", "ABUNDANTIS_SCOPED_DB_*")));
+
+        let mut snapshots = registry.load_all().await.unwrap();
+        snapshots.sort_by(|a, b| a.source_id.as_str().cmp(b.source_id.as_str()));
+        let loaded: Vec<_> = snapshots
+            .iter()
+            .map(|snapshot| {
+                let keys: Vec<_> = snapshot.variables.iter().map(|v| v.key.as_str()).collect();
+                (snapshot.source_id.as_str(), keys)
+            })
+            .collect();
+        assert_eq!(
+            loaded,
+            vec![
+                ("shell:app", vec!["ABUNDANTIS_SCOPED_APP_PORT"]),
+                ("shell:db", vec!["ABUNDANTIS_SCOPED_DB_URL"]),
+            ]
+        );
+
+        std::env::remove_var("ABUNDANTIS_SCOPED_APP_PORT");
+        std::env::remove_var("ABUNDANTIS_SCOPED_DB_URL");
+    }
 }
@@ -1,5 +1,5 @@
 use super::config::SourceRefreshOptions;
-use crate::error::SourceError;
+use crate::error::{Diagnostic, SourceError};
 use crate::source::variable::ParsedVariable;
 use compact_str::CompactString;
 use std::sync::Arc;
@@ -7,25 +7,60 @@ use std::sync::Arc;
 #[derive(Debug, Clone, Hash, Eq, PartialEq)]
 pub struct SourceId(CompactString);
 
+/// Prefix external provider ids had before they became remote sources.
+const LEGACY_EXTERNAL_PREFIX: &str = "external:";
+
 impl SourceId {
     pub fn new(id: impl Into<CompactString>) -> Self {
-        Self(id.into())
+        Self::from_compact(id.into())
+    }
+
+    /// Reads a legacy `external:<provider>` id as `remote:<provider>`, so
+    /// lookups by the old id keep finding the provider.
+    fn from_compact(id: CompactString) -> Self {
+        match id.strip_prefix(LEGACY_EXTERNAL_PREFIX) {
+            Some(provider) => Self::canonical(SourceType::Remote, provider),
+            None => Self(id),
+        }
+    }
+
+    /// Builds the canonical id for a source of `source_type`, e.g.
+    /// `file:/repo/.env` or `memory:default`.
+    pub fn canonical(source_type: SourceType, name: impl std::fmt::Display) -> Self {
+        Self(compact_str::format_compact!(
+            "{}{}",
+            source_type.id_prefix(),
+            name
+        ))
     }
 
     pub fn as_str(&self) -> &str {
         &self.0
     }
+
+    /// The source type named by the id's prefix, or `None` for ids that don't
+    /// follow the canonical `<type>:<name>` form.
+    pub fn source_type(&self) -> Option<SourceType> {
+        [
+            SourceType::File,
+            SourceType::Shell,
+            SourceType::Memory,
+            SourceType::Remote,
+        ]
+        .into_iter()
+        .find(|source_type| self.0.starts_with(source_type.id_prefix()))
+    }
 }
 
 impl From<&str> for SourceId {
     fn from(s: &str) -> Self {
-        Self(CompactString::new(s))
+        Self::from_compact(CompactString::new(s))
     }
 }
 
 impl From<String> for SourceId {
     fn from(s: String) -> Self {
-        Self(CompactString::new(s))
+        Self::from_compact(CompactString::new(s))
     }
 }
 
@@ -43,6 +78,39 @@ impl Priority {
     pub const FILE: Priority = Priority(50);
     pub const MEMORY: Priority = Priority(30);
     pub const REMOTE: Priority = Priority(75);
+
+    /// A priority for a custom source. The built-in priorities leave room in
+    /// between: below 30 sorts under memory sources, 31-49 between memory
+    /// and files, 51-74 between files and remote sources, 76-99 between
+    /// remote sources and the shell, and above 100 over the shell.
+    ///
+    /// Priority only orders sources in
+    /// [`SourceRegistry::sync_sources_by_priority`](crate::source::SourceRegistry::sync_sources_by_priority),
+    /// e.g. when refreshing. It doesn't affect which value wins a key: that
+    /// is decided by source type through `resolution.precedence`.
+    pub const fn custom(value: u32) -> Priority {
+        Priority(value)
+    }
+
+    /// `self`, or the priority right above `other` if `self` doesn't
+    /// already outrank it.
+    pub const fn above(self, other: Priority) -> Priority {
+        if self.0 > other.0 {
+            self
+        } else {
+            Priority(other.0.saturating_add(1))
+        }
+    }
+
+    /// `self`, or the priority right below `other` if `self` doesn't
+    /// already rank under it.
+    pub const fn below(self, other: Priority) -> Priority {
+        if self.0 < other.0 {
+            self
+        } else {
+            Priority(other.0.saturating_sub(1))
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -53,6 +121,18 @@ pub enum SourceType {
     Remote,
 }
 
+impl SourceType {
+    /// Prefix shared by the ids of every source of this type.
+    pub const fn id_prefix(self) -> &'static str {
+        match self {
+            SourceType::File => "file:",
+            SourceType::Shell => "shell:",
+            SourceType::Memory => "memory:",
+            SourceType::Remote => "remote:",
+        }
+    }
+}
+
 bitflags::bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct SourceCapabilities: u32 {
@@ -75,6 +155,7 @@ impl Default for SourceCapabilities {
 #[derive(Debug, Clone)]
 pub struct SourceSnapshot {
     pub source_id: SourceId,
+    pub source_type: SourceType,
     pub variables: Arc<[ParsedVariable]>,
     pub timestamp: std::time::Instant,
     pub version: Option<u64>,
@@ -110,6 +191,21 @@ pub trait EnvSource: Send + Sync {
     fn refresh(&self, _options: &SourceRefreshOptions) {
         self.invalidate();
     }
+
+    /// Problems found while loading the source (e.g. duplicate keys).
+    fn diagnostics(&self) -> Vec<Diagnostic> {
+        Vec::new()
+    }
+
+    /// A value that changes whenever the source's data may have. The
+    /// registry reuses its last snapshot of a cacheable source while this
+    /// stays the same.
+    ///
+    /// `None`, the default, means the source can't tell cheaply, so it is
+    /// loaded every time.
+    fn fingerprint(&self) -> Option<u64> {
+        None
+    }
 }
 
 #[cfg(feature = "async")]
@@ -127,3 +223,23 @@ pub trait AsyncEnvSource: Send + Sync {
         SourceMetadata::default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_priority_above_and_below() {
+        assert!(Priority::FILE.above(Priority::FILE) > Priority::FILE);
+        assert!(Priority::FILE.below(Priority::FILE) < Priority::FILE);
+        assert_eq!(Priority::MEMORY.above(Priority::FILE), Priority(51));
+        assert_eq!(Priority::SHELL.above(Priority::FILE), Priority::SHELL);
+        assert_eq!(Priority::SHELL.below(Priority::REMOTE), Priority(74));
+
+        let custom = Priority::custom(60);
+        assert!(Priority::FILE < custom && custom < Priority::REMOTE);
+        assert!(Priority::MEMORY < Priority::FILE);
+        assert!(Priority::FILE < Priority::REMOTE);
+        assert!(Priority::REMOTE < Priority::SHELL);
+    }
+}
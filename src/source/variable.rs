@@ -1,13 +1,19 @@
 use compact_str::CompactString;
 use std::path::PathBuf;
 
+/// A variable as a source defines it. Build one with
+/// [`simple`](Self::simple) and the `with_*` methods; the struct is
+/// non-exhaustive so new fields don't break callers.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct ParsedVariable {
     pub key: CompactString,
     pub raw_value: CompactString,
     pub source: VariableSource,
     pub description: Option<CompactString>,
     pub is_commented: bool,
+    /// Taken verbatim: `${...}` in the value is never interpolated.
+    pub is_literal: bool,
 }
 
 impl ParsedVariable {
@@ -22,8 +28,27 @@ impl ParsedVariable {
             source,
             description: None,
             is_commented: false,
+            is_literal: false,
         }
     }
+
+    /// Attaches the comment documenting the variable.
+    pub fn with_description(mut self, description: impl Into<CompactString>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Marks the variable as defined on a commented-out line.
+    pub fn with_commented(mut self, is_commented: bool) -> Self {
+        self.is_commented = is_commented;
+        self
+    }
+
+    /// Marks the value as literal, never interpolated.
+    pub fn with_literal(mut self, is_literal: bool) -> Self {
+        self.is_literal = is_literal;
+        self
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
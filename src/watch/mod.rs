@@ -4,6 +4,9 @@ use std::path::{Path, PathBuf};
 #[cfg(all(feature = "watch", feature = "async"))]
 use compact_str::CompactString;
 
+#[cfg(all(feature = "watch", feature = "async"))]
+use notify::event::{ModifyKind, RenameMode};
+
 #[cfg(all(feature = "watch", feature = "async"))]
 use notify::{Event, EventKind, RecursiveMode, Watcher};
 
@@ -11,7 +14,10 @@ use notify::{Event, EventKind, RecursiveMode, Watcher};
 use parking_lot::Mutex;
 
 #[cfg(all(feature = "watch", feature = "async"))]
-use std::collections::HashMap;
+use crate::source::{SourceId, SourceType};
+
+#[cfg(all(feature = "watch", feature = "async"))]
+use std::collections::{HashMap, HashSet};
 
 #[cfg(all(feature = "watch", feature = "async"))]
 use std::sync::Arc;
@@ -23,22 +29,49 @@ pub struct FileChanged {
     pub kind: ChangeKind,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg(all(feature = "watch", feature = "async"))]
 pub enum ChangeKind {
     Created,
     Modified,
     Deleted,
+    /// A file was renamed from `from` to `to`, at least one of which is
+    /// watched. If `from` was watched, the watch follows the file to `to`
+    /// when both are in the same directory and is dropped otherwise.
+    Renamed { from: PathBuf, to: PathBuf },
+}
+
+/// Canonical form of a possibly missing file: the file itself if it exists,
+/// otherwise its canonical parent joined with the file name.
+#[cfg(all(feature = "watch", feature = "async"))]
+pub(crate) fn canonical_path(path: &Path) -> PathBuf {
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+    match (path.parent().and_then(|p| p.canonicalize().ok()), path.file_name()) {
+        (Some(parent), Some(name)) => parent.join(name),
+        _ => path.to_path_buf(),
+    }
 }
 
 #[cfg(all(feature = "watch", feature = "async"))]
 pub type WatchCallback = Arc<dyn Fn(FileChanged) + Send + Sync>;
 
+/// Watches env files for changes and reports them to registered callbacks.
+///
+/// The OS watcher lives as long as this value. Dropping it unregisters every
+/// callback and removes every watch before the watcher's thread shuts down.
 #[cfg(all(feature = "watch", feature = "async"))]
 pub struct FileWatcher {
-    #[allow(dead_code)]
     watcher: Arc<Mutex<notify::RecommendedWatcher>>,
     paths: Arc<Mutex<HashMap<PathBuf, CompactString>>>,
+    /// Watched parent directories and how many watched files each holds.
+    /// Directories rather than files are watched so atomic replacements
+    /// (write to a temp file, rename over the target) are seen.
+    dirs: Arc<Mutex<HashMap<PathBuf, usize>>>,
+    /// Directories watched with everything below them. Changes to any file
+    /// inside are reported, watched individually or not.
+    recursive_dirs: Arc<Mutex<HashSet<PathBuf>>>,
     callbacks: Arc<Mutex<Vec<WatchCallback>>>,
 }
 
@@ -47,25 +80,88 @@ impl FileWatcher {
     pub fn new() -> Result<Self, notify::Error> {
         let paths = Arc::new(Mutex::new(HashMap::new()));
         let callbacks = Arc::new(Mutex::new(Vec::<WatchCallback>::new()));
+        let dirs = Arc::new(Mutex::new(HashMap::<PathBuf, usize>::new()));
+        let recursive_dirs = Arc::new(Mutex::new(HashSet::<PathBuf>::new()));
         let paths_clone = Arc::clone(&paths);
+        let dirs_clone = Arc::clone(&dirs);
+        let recursive_clone = Arc::clone(&recursive_dirs);
         let callbacks_clone = Arc::clone(&callbacks);
 
         let watcher: notify::RecommendedWatcher = notify::recommended_watcher(move |res: Result<Event, _>| {
             if let Ok(event) = res {
+                if let EventKind::Modify(ModifyKind::Name(RenameMode::Both)) = event.kind {
+                    if let [from, to] = event.paths.as_slice() {
+                        let from = canonical_path(from);
+                        let to = canonical_path(to);
+                        let watched = {
+                            let mut paths = paths_clone.lock();
+                            match paths.remove(&from) {
+                                Some(_) if from.parent() == to.parent() => {
+                                    let source_id =
+                                        SourceId::canonical(SourceType::File, to.display());
+                                    let source_id = CompactString::new(source_id.as_str());
+                                    paths.insert(to.clone(), source_id);
+                                    true
+                                }
+                                Some(_) => {
+                                    // The OS watch can't be changed from the
+                                    // event thread; it lingers harmlessly until
+                                    // the directory is watched again.
+                                    if let Some(dir) = from.parent() {
+                                        let mut dirs = dirs_clone.lock();
+                                        if let Some(count) = dirs.get_mut(dir) {
+                                            *count -= 1;
+                                            if *count == 0 {
+                                                dirs.remove(dir);
+                                            }
+                                        }
+                                    }
+                                    true
+                                }
+                                None => {
+                                    paths.contains_key(&to)
+                                        || recursive_clone
+                                            .lock()
+                                            .iter()
+                                            .any(|dir| to.starts_with(dir))
+                                }
+                            }
+                        };
+
+                        if watched {
+                            let change = FileChanged {
+                                path: to.clone(),
+                                kind: ChangeKind::Renamed { from, to },
+                            };
+                            let callbacks = callbacks_clone.lock();
+                            for callback in callbacks.iter() {
+                                callback(change.clone());
+                            }
+                        }
+                    }
+                    return;
+                }
+
                 for path in event.paths {
-                    let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+                    let canonical = canonical_path(&path);
 
-                    let source_id = {
-                        let paths = paths_clone.lock();
-                        paths.get(&canonical).cloned()
-                    };
+                    let watched = paths_clone.lock().contains_key(&canonical)
+                        || recursive_clone
+                            .lock()
+                            .iter()
+                            .any(|dir| canonical.starts_with(dir));
 
-                    if source_id.is_none() {
+                    if !watched {
                         continue;
                     }
 
                     let kind = match event.kind {
                         EventKind::Create(_) => ChangeKind::Created,
+                        // The halves of a rename pair are reported together
+                        // as `RenameMode::Both` above.
+                        EventKind::Modify(ModifyKind::Name(
+                            RenameMode::From | RenameMode::To,
+                        )) => continue,
                         EventKind::Modify(_) => ChangeKind::Modified,
                         EventKind::Remove(_) => ChangeKind::Deleted,
                         _ => continue,
@@ -84,24 +180,93 @@ impl FileWatcher {
         Ok(Self {
             watcher: Arc::new(Mutex::new(watcher)),
             paths,
+            dirs,
+            recursive_dirs,
             callbacks,
         })
     }
 
+    /// Watches `dir` and everything below it, including directories created
+    /// later, reporting changes to any file inside.
+    pub fn watch_dir_recursive(&self, dir: impl AsRef<Path>) -> Result<(), notify::Error> {
+        let dir = canonical_path(dir.as_ref());
+        if !self.recursive_dirs.lock().insert(dir.clone()) {
+            return Ok(());
+        }
+
+        let result = self.watcher.lock().watch(&dir, RecursiveMode::Recursive);
+        if result.is_err() {
+            self.recursive_dirs.lock().remove(&dir);
+        }
+        result
+    }
+
+    pub fn unwatch_dir_recursive(&self, dir: impl AsRef<Path>) {
+        let dir = canonical_path(dir.as_ref());
+        if !self.recursive_dirs.lock().remove(&dir) {
+            return;
+        }
+
+        if let Err(e) = self.watcher.lock().unwatch(&dir) {
+            tracing::warn!(path = %dir.display(), error = %e, "Failed to unwatch directory");
+        }
+        // Re-establish the per-file watches the recursive one covered.
+        let dirs = self.dirs.lock();
+        for watched in dirs.keys().filter(|watched| watched.starts_with(&dir)) {
+            if let Err(e) = self.watcher.lock().watch(watched, RecursiveMode::NonRecursive) {
+                tracing::warn!(path = %watched.display(), error = %e, "Failed to watch path");
+            }
+        }
+    }
+
+    pub fn recursive_dirs(&self) -> Vec<PathBuf> {
+        self.recursive_dirs.lock().iter().cloned().collect()
+    }
+
+    fn covered_recursively(&self, dir: &Path) -> bool {
+        self.recursive_dirs
+            .lock()
+            .iter()
+            .any(|recursive| dir.starts_with(recursive))
+    }
+
     pub fn watch(&self, path: impl AsRef<Path>, source_id: impl Into<CompactString>) {
-        let path = path.as_ref().canonicalize().unwrap_or_else(|_| path.as_ref().to_path_buf());
-        if let Err(e) = self.watcher.lock().watch(&path, RecursiveMode::NonRecursive) {
-            tracing::warn!(path = %path.display(), error = %e, "Failed to watch path");
+        let path = canonical_path(path.as_ref());
+        if self.paths.lock().insert(path.clone(), source_id.into()).is_some() {
+            return;
+        }
+
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_else(|| path.clone());
+        let mut dirs = self.dirs.lock();
+        let count = dirs.entry(dir.clone()).or_insert(0);
+        *count += 1;
+        if *count == 1 && !self.covered_recursively(&dir) {
+            if let Err(e) = self.watcher.lock().watch(&dir, RecursiveMode::NonRecursive) {
+                tracing::warn!(path = %path.display(), error = %e, "Failed to watch path");
+            }
         }
-        self.paths.lock().insert(path, source_id.into());
     }
 
     pub fn unwatch(&self, path: impl AsRef<Path>) {
-        let path = path.as_ref().canonicalize().unwrap_or_else(|_| path.as_ref().to_path_buf());
-        if let Err(e) = self.watcher.lock().unwatch(&path) {
-            tracing::warn!(path = %path.display(), error = %e, "Failed to unwatch path");
+        let path = canonical_path(path.as_ref());
+        if self.paths.lock().remove(&path).is_none() {
+            return;
+        }
+
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_else(|| path.clone());
+        let mut dirs = self.dirs.lock();
+        if let Some(count) = dirs.get_mut(&dir) {
+            *count -= 1;
+            if *count == 0 {
+                dirs.remove(&dir);
+                if self.covered_recursively(&dir) {
+                    return;
+                }
+                if let Err(e) = self.watcher.lock().unwatch(&dir) {
+                    tracing::warn!(path = %path.display(), error = %e, "Failed to unwatch path");
+                }
+            }
         }
-        self.paths.lock().remove(&path);
     }
 
     pub fn register_callback(&self, callback: WatchCallback) {
@@ -113,8 +278,26 @@ impl FileWatcher {
     }
 
     pub fn is_watching(&self, path: impl AsRef<Path>) -> bool {
-        let path = path.as_ref().canonicalize().unwrap_or_else(|_| path.as_ref().to_path_buf());
-        self.paths.lock().contains_key(&path)
+        self.paths.lock().contains_key(&canonical_path(path.as_ref()))
+    }
+}
+
+#[cfg(all(feature = "watch", feature = "async"))]
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        // Events still queued on the watcher's thread must not reach
+        // callbacks (and whatever they hold) once the owner is gone.
+        self.callbacks.lock().clear();
+        self.paths.lock().clear();
+
+        let recursive = std::mem::take(&mut *self.recursive_dirs.lock());
+        let dirs: Vec<PathBuf> = self.dirs.lock().drain().map(|(dir, _)| dir).collect();
+        let mut watcher = self.watcher.lock();
+        for dir in dirs.into_iter().chain(recursive) {
+            if let Err(e) = watcher.unwatch(&dir) {
+                tracing::debug!(path = %dir.display(), error = %e, "Failed to unwatch path");
+            }
+        }
     }
 }
 
@@ -162,14 +345,61 @@ mod tests {
         }));
 
         std::fs::write(&test_file, "TEST=value").unwrap();
-        tokio::time::sleep(Duration::from_millis(100)).await;
 
-        tokio::time::timeout(Duration::from_millis(500), async {
+        tokio::time::timeout(Duration::from_secs(5), async {
             while !callback_called.load(std::sync::atomic::Ordering::SeqCst) {
                 tokio::time::sleep(Duration::from_millis(10)).await;
             }
         })
         .await
-        .ok();
+        .expect("callback did not fire for a watched file");
+    }
+
+    #[tokio::test]
+    async fn test_unwatched_file_stops_firing() {
+        let temp_dir = TempDir::new().unwrap();
+        let watcher = FileWatcher::new().unwrap();
+        let test_file = temp_dir.path().join("test.env");
+        std::fs::write(&test_file, "TEST=1").unwrap();
+        watcher.watch(&test_file, "test-source");
+
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let calls_clone = Arc::clone(&calls);
+        watcher.register_callback(Arc::new(move |_change| {
+            calls_clone.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }));
+
+        std::fs::write(&test_file, "TEST=2").unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while calls.load(std::sync::atomic::Ordering::SeqCst) == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("callback did not fire for a watched file");
+
+        watcher.unwatch(&test_file);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let before = calls.load(std::sync::atomic::Ordering::SeqCst);
+        std::fs::write(&test_file, "TEST=3").unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), before);
+    }
+
+    #[tokio::test]
+    async fn test_drop_releases_callbacks() {
+        let temp_dir = TempDir::new().unwrap();
+        let watcher = FileWatcher::new().unwrap();
+        watcher.watch(temp_dir.path().join("test.env"), "test-source");
+
+        let held = Arc::new(());
+        let held_clone = Arc::clone(&held);
+        watcher.register_callback(Arc::new(move |_change| {
+            let _ = &held_clone;
+        }));
+        assert_eq!(Arc::strong_count(&held), 2);
+
+        drop(watcher);
+        assert_eq!(Arc::strong_count(&held), 1);
     }
 }
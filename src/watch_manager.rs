@@ -2,7 +2,7 @@
 use crate::events::AbundantisEvent;
 
 #[cfg(all(feature = "watch", feature = "async"))]
-use crate::source::{EnvSource, FileSource};
+use crate::source::{EnvSource, FileSource, SourceRegistry};
 
 #[cfg(all(feature = "watch", feature = "async"))]
 use compact_str::CompactString;
@@ -17,13 +17,43 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 #[cfg(all(feature = "watch", feature = "async"))]
-use crate::watch::{ChangeKind, FileChanged, FileWatcher};
+use crate::watch::{canonical_path, ChangeKind, FileChanged, FileWatcher};
 
 #[cfg(all(feature = "watch", feature = "async"))]
 pub struct WatchManager {
     watcher: Arc<FileWatcher>,
     file_sources: Arc<Mutex<std::collections::HashMap<PathBuf, Arc<FileSource>>>>,
     event_bus: Arc<crate::events::EventBus>,
+    workspace: Arc<Mutex<Option<Arc<WorkspaceWatch>>>>,
+}
+
+/// A workspace watched recursively by [`WatchManager::watch_workspace`]: env
+/// files matching `patterns` that appear or disappear under `root` are added
+/// to or removed from `registry`.
+#[cfg(all(feature = "watch", feature = "async"))]
+struct WorkspaceWatch {
+    root: PathBuf,
+    patterns: globset::GlobSet,
+    registry: Arc<SourceRegistry>,
+}
+
+#[cfg(all(feature = "watch", feature = "async"))]
+impl WorkspaceWatch {
+    /// Whether `path` is an env file this workspace keeps registered.
+    fn matches(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        let excluded = relative.components().any(|component| {
+            crate::workspace::provider::STANDARD_EXCLUSIONS
+                .iter()
+                .any(|excluded| component.as_os_str() == *excluded)
+        });
+        !excluded
+            && path
+                .file_name()
+                .is_some_and(|name| self.patterns.is_match(name))
+    }
 }
 
 #[cfg(all(feature = "watch", feature = "async"))]
@@ -35,11 +65,51 @@ impl WatchManager {
             watcher,
             file_sources: Arc::new(Mutex::new(std::collections::HashMap::new())),
             event_bus,
+            workspace: Arc::new(Mutex::new(None)),
         })
     }
 
+    /// Watches `root` recursively and keeps `registry` in sync with the env
+    /// files under it: files whose name matches one of `patterns` (e.g. the
+    /// workspace's `env_files`) are registered as [`FileSource`]s when they
+    /// appear and unregistered when they are deleted. Files already present
+    /// are left to the caller. Replaces any previously watched workspace.
+    pub fn watch_workspace(
+        &self,
+        root: impl AsRef<Path>,
+        patterns: &[CompactString],
+        registry: Arc<SourceRegistry>,
+    ) -> Result<(), notify::Error> {
+        let root = canonical_path(root.as_ref());
+
+        let mut builder = globset::GlobSetBuilder::new();
+        for pattern in patterns {
+            match globset::Glob::new(pattern) {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(e) => tracing::warn!("Invalid env file pattern {}: {}", pattern, e),
+            }
+        }
+        let patterns = builder.build().unwrap_or_else(|e| {
+            tracing::warn!("Failed to build env file patterns: {}", e);
+            globset::GlobSet::empty()
+        });
+
+        self.watcher.watch_dir_recursive(&root)?;
+        let previous = self.workspace.lock().replace(Arc::new(WorkspaceWatch {
+            root: root.clone(),
+            patterns,
+            registry,
+        }));
+        if let Some(previous) = previous.filter(|previous| previous.root != root) {
+            self.watcher.unwatch_dir_recursive(&previous.root);
+        }
+        Ok(())
+    }
+
     pub fn watch_file(&self, source: Arc<FileSource>) {
-        let path = source.path().to_path_buf();
+        let path = canonical_path(source.path());
         let source_id = source.as_ref().id().as_str();
 
         self.watcher.watch(&path, source_id);
@@ -47,7 +117,7 @@ impl WatchManager {
     }
 
     pub fn unwatch_file(&self, path: impl AsRef<Path>) {
-        let path_buf = path.as_ref().to_path_buf();
+        let path_buf = canonical_path(path.as_ref());
         self.watcher.unwatch(&path_buf);
         self.file_sources.lock().remove(&path_buf);
     }
@@ -55,51 +125,194 @@ impl WatchManager {
     pub fn start(&self) {
         let sources = Arc::clone(&self.file_sources);
         let event_bus = Arc::clone(&self.event_bus);
+        let watcher = Arc::downgrade(&self.watcher);
+        let workspace = Arc::clone(&self.workspace);
+
+        // Callbacks run on the watcher's event thread, which can't add or
+        // remove watches without deadlocking, so changes are handled on a
+        // worker thread. It exits once the watcher drops the callback.
+        let (sender, changes) = std::sync::mpsc::channel::<FileChanged>();
+        let worker = std::thread::Builder::new()
+            .name("abundantis-watch".into())
+            .spawn(move || {
+                for change in changes {
+                    Self::handle_change(&workspace, &sources, &watcher, change, &event_bus);
+                }
+            });
+        if let Err(e) = worker {
+            tracing::error!("Failed to start file watch worker: {}", e);
+            return;
+        }
 
         self.watcher
             .register_callback(Arc::new(move |change: FileChanged| {
-                let path = &change.path;
-
-                let source_opt = {
-                    let sources = sources.lock();
-                    sources.get(path).cloned()
-                };
+                let _ = sender.send(change);
+            }));
+    }
 
-                if let Some(source) = source_opt {
-                    match change.kind {
-                        ChangeKind::Created => {
-                            tracing::debug!("File created: {:?}", path);
-                            if let Err(e) = Self::handle_file_create(&source, &event_bus) {
-                                tracing::error!(
-                                    "Failed to handle file create for {:?}: {}",
-                                    path,
-                                    e
-                                );
-                            }
-                        }
-                        ChangeKind::Modified => {
-                            tracing::debug!("File modified: {:?}", path);
-                            if let Err(e) = Self::handle_file_change(&source, &event_bus) {
-                                tracing::error!(
-                                    "Failed to handle file change for {:?}: {}",
-                                    path,
-                                    e
-                                );
-                            }
-                        }
-                        ChangeKind::Deleted => {
-                            tracing::debug!("File deleted: {:?}", path);
-                            if let Err(e) = Self::handle_file_delete(&source, &event_bus) {
-                                tracing::error!(
-                                    "Failed to handle file delete for {:?}: {}",
-                                    path,
-                                    e
-                                );
-                            }
-                        }
+    /// Applies one change reported by the watcher.
+    fn handle_change(
+        workspace: &Mutex<Option<Arc<WorkspaceWatch>>>,
+        sources: &Mutex<std::collections::HashMap<PathBuf, Arc<FileSource>>>,
+        watcher: &std::sync::Weak<FileWatcher>,
+        change: FileChanged,
+        event_bus: &Arc<crate::events::EventBus>,
+    ) {
+        let workspace = workspace.lock().clone();
+        if let Some(workspace) = workspace {
+            if Self::sync_workspace(&workspace, sources, watcher, &change, event_bus) {
+                return;
+            }
+        }
+
+        if let ChangeKind::Renamed { from, to } = &change.kind {
+            tracing::debug!("File renamed: {:?} -> {:?}", from, to);
+            if let Err(e) = Self::handle_file_rename(sources, watcher, from, to, event_bus) {
+                tracing::error!("Failed to handle file rename {:?} -> {:?}: {}", from, to, e);
+            }
+            return;
+        }
+
+        let path = canonical_path(&change.path);
+        let path = &path;
+
+        let source_opt = {
+            let sources = sources.lock();
+            sources.get(path).cloned()
+        };
+
+        if let Some(source) = source_opt {
+            match change.kind {
+                ChangeKind::Created => {
+                    tracing::debug!("File created: {:?}", path);
+                    if let Err(e) = Self::handle_file_create(&source, event_bus) {
+                        tracing::error!("Failed to handle file create for {:?}: {}", path, e);
                     }
                 }
-            }));
+                ChangeKind::Modified => {
+                    tracing::debug!("File modified: {:?}", path);
+                    if let Err(e) = Self::handle_file_change(&source, event_bus) {
+                        tracing::error!("Failed to handle file change for {:?}: {}", path, e);
+                    }
+                }
+                ChangeKind::Deleted => {
+                    tracing::debug!("File deleted: {:?}", path);
+                    if let Err(e) = Self::handle_file_delete(&source, event_bus) {
+                        tracing::error!("Failed to handle file delete for {:?}: {}", path, e);
+                    }
+                }
+                ChangeKind::Renamed { .. } => {}
+            }
+        }
+    }
+
+    /// Registers env files appearing under the watched workspace and
+    /// unregisters deleted ones. Returns true when `change` was fully handled.
+    fn sync_workspace(
+        workspace: &WorkspaceWatch,
+        sources: &Mutex<std::collections::HashMap<PathBuf, Arc<FileSource>>>,
+        watcher: &std::sync::Weak<FileWatcher>,
+        change: &FileChanged,
+        event_bus: &Arc<crate::events::EventBus>,
+    ) -> bool {
+        let path = canonical_path(&change.path);
+        match &change.kind {
+            ChangeKind::Created if path.is_dir() => {
+                // Files written before the new directory was watched produce
+                // no events of their own.
+                let files: Vec<PathBuf> = walkdir::WalkDir::new(&path)
+                    .into_iter()
+                    .flatten()
+                    .filter(|entry| entry.file_type().is_file())
+                    .map(|entry| canonical_path(entry.path()))
+                    .filter(|file| workspace.matches(file))
+                    .collect();
+                for file in files {
+                    Self::adopt_file(workspace, sources, watcher, &file, event_bus);
+                }
+                true
+            }
+            ChangeKind::Created | ChangeKind::Modified => {
+                let known = sources.lock().contains_key(&path);
+                if known || !workspace.matches(&path) {
+                    return false;
+                }
+                Self::adopt_file(workspace, sources, watcher, &path, event_bus);
+                true
+            }
+            ChangeKind::Deleted => {
+                if !workspace.matches(&path) {
+                    return false;
+                }
+                let Some(source) = sources.lock().remove(&path) else {
+                    return false;
+                };
+                if let Err(e) = Self::handle_file_delete(&source, event_bus) {
+                    tracing::error!("Failed to handle file delete for {:?}: {}", path, e);
+                }
+                if let Some(watcher) = watcher.upgrade() {
+                    watcher.unwatch(&path);
+                }
+                workspace.registry.unregister_sync(source.as_ref().id());
+                if !workspace.registry.publishes_events() {
+                    event_bus.publish(AbundantisEvent::SourceRemoved {
+                        source_id: source.as_ref().id().clone(),
+                    });
+                }
+                true
+            }
+            ChangeKind::Renamed { to, .. } => {
+                let known = sources.lock().contains_key(to);
+                if known || !workspace.matches(to) {
+                    return false;
+                }
+                Self::adopt_file(workspace, sources, watcher, to, event_bus);
+                true
+            }
+        }
+    }
+
+    /// Starts tracking an env file that appeared under the workspace.
+    fn adopt_file(
+        workspace: &WorkspaceWatch,
+        sources: &Mutex<std::collections::HashMap<PathBuf, Arc<FileSource>>>,
+        watcher: &std::sync::Weak<FileWatcher>,
+        path: &Path,
+        event_bus: &Arc<crate::events::EventBus>,
+    ) {
+        if sources.lock().contains_key(path) {
+            return;
+        }
+        let source = match FileSource::new(path) {
+            Ok(source) => Arc::new(source),
+            Err(e) => {
+                tracing::debug!("Skipping env file {:?}: {}", path, e);
+                return;
+            }
+        };
+        let source_id = source.as_ref().id().clone();
+        tracing::info!("Discovered new env file: {}", path.display());
+
+        if let Some(watcher) = watcher.upgrade() {
+            watcher.watch(path, source_id.as_str());
+        }
+        sources
+            .lock()
+            .insert(path.to_path_buf(), Arc::clone(&source));
+        let registered = workspace.registry.is_registered(&source_id);
+        if !registered {
+            workspace
+                .registry
+                .register_sync(Arc::clone(&source) as Arc<dyn EnvSource>);
+        }
+        // A registry wired to an event bus announces the source itself.
+        if registered || !workspace.registry.publishes_events() {
+            event_bus.publish(AbundantisEvent::SourceAdded { source_id });
+        }
+
+        if let Err(e) = Self::handle_file_create(&source, event_bus) {
+            tracing::error!("Failed to handle file create for {:?}: {}", path, e);
+        }
     }
 
     fn handle_file_change(
@@ -148,6 +361,47 @@ impl WatchManager {
         Ok(())
     }
 
+    /// Reloads the source at `to` if a file was renamed over it, and moves
+    /// the registration of a watched `from` along with the file when the
+    /// watcher still follows it.
+    fn handle_file_rename(
+        sources: &Mutex<std::collections::HashMap<PathBuf, Arc<FileSource>>>,
+        watcher: &std::sync::Weak<FileWatcher>,
+        from: &Path,
+        to: &Path,
+        event_bus: &Arc<crate::events::EventBus>,
+    ) -> Result<(), String> {
+        let previous = sources.lock().remove(from);
+        if let Some(previous) = &previous {
+            event_bus.publish(AbundantisEvent::SourceRemoved {
+                source_id: previous.as_ref().id().clone(),
+            });
+        }
+
+        let existing = sources.lock().get(to).cloned();
+        if let Some(source) = existing {
+            return Self::handle_file_change(&source, event_bus);
+        }
+
+        let followed = watcher
+            .upgrade()
+            .is_some_and(|watcher| watcher.is_watching(to));
+        if previous.is_none() || !followed {
+            event_bus.publish(AbundantisEvent::CacheInvalidated { scope: None });
+            return Ok(());
+        }
+
+        let source = Arc::new(
+            FileSource::new(to).map_err(|e| format!("Failed to load renamed file: {}", e))?,
+        );
+        sources.lock().insert(to.to_path_buf(), Arc::clone(&source));
+        event_bus.publish(AbundantisEvent::SourceAdded {
+            source_id: source.as_ref().id().clone(),
+        });
+
+        Self::handle_file_create(&source, event_bus)
+    }
+
     fn handle_file_create(
         source: &Arc<FileSource>,
         event_bus: &Arc<crate::events::EventBus>,
@@ -223,4 +477,104 @@ mod tests {
         manager.unwatch_file(file.path());
         assert!(!manager.is_watching(file.path()));
     }
+
+    #[tokio::test]
+    async fn test_watch_workspace_registers_new_env_files() {
+        use crate::source::{SourceId, SourceType};
+
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let registry = Arc::new(SourceRegistry::new());
+        let event_bus = Arc::new(crate::events::EventBus::new(100));
+        let manager = WatchManager::new(event_bus).unwrap();
+        manager
+            .watch_workspace(&root, &[".env".into()], Arc::clone(&registry))
+            .unwrap();
+        manager.start();
+
+        let env = root.join("apps/new/.env");
+        std::fs::create_dir_all(env.parent().unwrap()).unwrap();
+        std::fs::write(&env, "NEW_APP_KEY=hello\n").unwrap();
+        std::fs::write(root.join("apps/new/notes.txt"), "NOT_ENV=1\n").unwrap();
+
+        let id = SourceId::canonical(SourceType::File, env.display());
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while !registry.is_registered(&id) {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("new env file was not registered");
+        assert!(manager.is_watching(&env));
+        assert_eq!(registry.source_count(), 1);
+
+        let engine = crate::resolution::ResolutionEngine::new(
+            &crate::config::ResolutionConfig::default(),
+            &crate::config::InterpolationConfig::default(),
+            &crate::config::CacheConfig::default(),
+        );
+        let context = crate::workspace::WorkspaceContext {
+            workspace_root: root.clone(),
+            package_root: root.join("apps/new"),
+            package_name: None,
+            env_files: vec![env.clone()],
+        };
+        let resolved = engine
+            .resolve("NEW_APP_KEY", &context, &registry)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(resolved.resolved_value, "hello");
+
+        std::fs::remove_file(&env).unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while registry.is_registered(&id) {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("deleted env file was not unregistered");
+        assert!(!manager.is_watching(&env));
+    }
+
+    #[tokio::test]
+    async fn test_rename_onto_watched_file_reloads_source() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let target = dir.path().join(".env");
+        std::fs::write(&target, "KEY=old\n").unwrap();
+
+        let event_bus = Arc::new(crate::events::EventBus::new(100));
+        let mut events = event_bus.subscribe_channel();
+        let manager = WatchManager::new(event_bus.clone()).unwrap();
+
+        let source = Arc::new(FileSource::new(&target).unwrap());
+        source.load().unwrap();
+        manager.watch_file(source.clone());
+        manager.start();
+
+        let staging = dir.path().join(".env.tmp");
+        std::fs::write(&staging, "KEY=new\nADDED=1\n").unwrap();
+        std::fs::rename(&staging, &target).unwrap();
+
+        let (changed, removed) = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            loop {
+                if let Ok(AbundantisEvent::VariablesChanged {
+                    source_id, removed, ..
+                }) = events.recv().await
+                {
+                    return (source_id, removed);
+                }
+            }
+        })
+        .await
+        .expect("rename was not picked up");
+        // Reloaded in place rather than reported as a deleted file.
+        assert_eq!(&changed, source.as_ref().id());
+        assert!(removed.is_empty());
+
+        let snapshot = source.load().unwrap();
+        let key = snapshot.variables.iter().find(|v| v.key == "KEY").unwrap();
+        assert_eq!(key.raw_value, "new");
+        assert!(manager.is_watching(&target));
+    }
 }
@@ -5,6 +5,11 @@ use hashbrown::HashMap;
 use parking_lot::RwLock;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Modification time and length of a provider config file, or `None` when
+/// it doesn't exist.
+type ConfigFingerprint = Option<(Option<SystemTime>, u64)>;
 
 pub struct WorkspaceManager {
     root: PathBuf,
@@ -15,6 +20,9 @@ pub struct WorkspaceManager {
 
     context_cache: RwLock<HashMap<PathBuf, Arc<WorkspaceContext>>>,
 
+    /// Provider config files as they were when packages were last discovered.
+    config_fingerprints: RwLock<Vec<(PathBuf, ConfigFingerprint)>>,
+
     cascading: bool,
 }
 
@@ -30,6 +38,7 @@ impl WorkspaceManager {
             config: config.clone(),
             packages: RwLock::new(HashMap::new()),
             context_cache: RwLock::new(HashMap::new()),
+            config_fingerprints: RwLock::new(Vec::new()),
             cascading: config.cascading,
         };
 
@@ -46,6 +55,7 @@ impl WorkspaceManager {
             config: config.clone(),
             packages: RwLock::new(HashMap::new()),
             context_cache: RwLock::new(HashMap::new()),
+            config_fingerprints: RwLock::new(Vec::new()),
             cascading: config.cascading,
         };
 
@@ -58,7 +68,8 @@ impl WorkspaceManager {
         let provider = ProviderRegistry::create(&self.config).ok_or_else(|| {
             AbundantisError::MissingConfig {
                 field: "workspace.provider",
-                suggestion: "Set to one of: turbo, nx, lerna, pnpm, npm, cargo, custom".into(),
+                suggestion:
+                    "Set to one of: turbo, nx, lerna, pnpm, npm, cargo, custom, directories".into(),
             }
         })?;
 
@@ -69,6 +80,15 @@ impl WorkspaceManager {
             });
         }
 
+        let fingerprints = provider
+            .config_files()
+            .into_iter()
+            .map(|name| {
+                let path = self.root.join(name);
+                let fingerprint = config_fingerprint(&path);
+                (path, fingerprint)
+            })
+            .collect();
         let packages = provider.discover_packages(&self.root)?;
 
         tracing::info!(
@@ -93,10 +113,21 @@ impl WorkspaceManager {
         }
 
         self.context_cache.write().clear();
+        *self.config_fingerprints.write() = fingerprints;
 
         Ok(())
     }
 
+    /// Whether a provider config file changed, appeared or disappeared since
+    /// packages were last discovered, so [`refresh`](Self::refresh) is due.
+    /// Stats every config file.
+    pub fn needs_rediscovery(&self) -> bool {
+        self.config_fingerprints
+            .read()
+            .iter()
+            .any(|(path, fingerprint)| config_fingerprint(path) != *fingerprint)
+    }
+
     pub fn context_for_file(&self, file_path: &Path) -> Option<WorkspaceContext> {
         {
             let cache = self.context_cache.read();
@@ -105,6 +136,24 @@ impl WorkspaceManager {
             }
         }
 
+        let package = self.owning_package(file_path)?;
+        let context = self.build_context(&package);
+
+        {
+            let mut cache = self.context_cache.write();
+            cache.insert(file_path.to_path_buf(), Arc::new(context.clone()));
+        }
+
+        Some(context)
+    }
+
+    /// The innermost package containing `file_path`, or `None` when the file
+    /// is outside every package (or doesn't exist).
+    pub fn package_for_file(&self, file_path: &Path) -> Option<PackageInfo> {
+        self.owning_package(file_path)
+    }
+
+    fn owning_package(&self, file_path: &Path) -> Option<PackageInfo> {
         let canonical = file_path.canonicalize().ok()?;
         let packages = self.packages.read();
 
@@ -130,15 +179,7 @@ impl WorkspaceManager {
             }
         }
 
-        let package = best_match?;
-        let context = self.build_context(package);
-
-        {
-            let mut cache = self.context_cache.write();
-            cache.insert(file_path.to_path_buf(), Arc::new(context.clone()));
-        }
-
-        Some(context)
+        best_match.cloned()
     }
 
     fn build_context(&self, package: &PackageInfo) -> WorkspaceContext {
@@ -172,6 +213,44 @@ impl WorkspaceManager {
         self.discover_packages()
     }
 
+    /// A manager for the same root and config with packages discovered
+    /// afresh, leaving `self` untouched.
+    pub fn rediscovered(&self) -> Result<Self> {
+        Self::with_root(self.root.clone(), &self.config)
+    }
+
+    /// Every package paired with the env files that exist for it, cascaded
+    /// root files included, ordered by package root.
+    pub fn all_env_files(&self) -> Vec<(PackageInfo, Vec<PathBuf>)> {
+        let mut packages = self.packages();
+        packages.sort_by(|a, b| a.root.cmp(&b.root));
+        packages
+            .into_iter()
+            .map(|package| {
+                let env_files = self.build_context(&package).env_files;
+                (package, env_files)
+            })
+            .collect()
+    }
+
+    /// Package patterns from the provider's configuration (e.g. the
+    /// `packages:` list in `pnpm-workspace.yaml`).
+    pub fn workspace_globs(&self) -> Vec<compact_str::CompactString> {
+        ProviderRegistry::create(&self.config)
+            .map(|provider| provider.workspace_globs(&self.root))
+            .unwrap_or_default()
+    }
+
+    /// Variable names declared to the monorepo tool, so callers can flag
+    /// variables that are used but undeclared (which Turbo treats as cache misses).
+    pub fn declared_env_keys(&self) -> std::collections::HashSet<compact_str::CompactString> {
+        ProviderRegistry::create(&self.config)
+            .map(|provider| provider.declared_env_keys(&self.root))
+            .unwrap_or_default()
+            .into_iter()
+            .collect()
+    }
+
     pub fn packages(&self) -> Vec<PackageInfo> {
         self.packages.read().values().cloned().collect()
     }
@@ -179,6 +258,16 @@ impl WorkspaceManager {
     pub fn root(&self) -> &Path {
         &self.root
     }
+
+    /// Whether the workspace root's env files cascade into packages.
+    pub fn cascading(&self) -> bool {
+        self.cascading
+    }
+}
+
+fn config_fingerprint(path: &Path) -> ConfigFingerprint {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.modified().ok(), metadata.len()))
 }
 
 #[cfg(test)]
@@ -41,23 +41,7 @@ impl MonorepoProvider for CargoProvider {
     }
 
     fn discover_packages(&self, root: &Path) -> crate::Result<Vec<PackageInfo>> {
-        let cargo_path = root.join("Cargo.toml");
-        let content = std::fs::read_to_string(&cargo_path).unwrap_or_default();
-
-        let parsed: toml::Value =
-            toml::from_str(&content).unwrap_or(toml::Value::Table(Default::default()));
-
-        let members = parsed
-            .get("workspace")
-            .and_then(|w| w.get("members"))
-            .and_then(|m| m.as_array())
-            .map(|arr| {
-                arr.iter()
-                    .filter_map(|v| v.as_str())
-                    .map(String::from)
-                    .collect::<Vec<_>>()
-            })
-            .unwrap_or_default();
+        let members = read_workspace_members(root);
 
         let mut packages = Vec::new();
 
@@ -95,6 +79,33 @@ impl MonorepoProvider for CargoProvider {
 
         Ok(packages)
     }
+
+    fn workspace_globs(&self, root: &Path) -> Vec<CompactString> {
+        read_workspace_members(root)
+            .iter()
+            .map(CompactString::new)
+            .collect()
+    }
+}
+
+fn read_workspace_members(root: &Path) -> Vec<String> {
+    let cargo_path = root.join("Cargo.toml");
+    let content = std::fs::read_to_string(&cargo_path).unwrap_or_default();
+
+    let parsed: toml::Value =
+        toml::from_str(&content).unwrap_or(toml::Value::Table(Default::default()));
+
+    parsed
+        .get("workspace")
+        .and_then(|w| w.get("members"))
+        .and_then(|m| m.as_array())
+        .map(|arr| {
+            arr.iter()
+                .filter_map(|v| v.as_str())
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default()
 }
 
 fn extract_cargo_name(path: &Path) -> Option<CompactString> {
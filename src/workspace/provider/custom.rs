@@ -58,4 +58,8 @@ impl MonorepoProvider for CustomProvider {
 
         Ok(packages)
     }
+
+    fn workspace_globs(&self, _root: &Path) -> Vec<CompactString> {
+        self.patterns.clone()
+    }
 }
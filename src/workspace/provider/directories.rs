@@ -0,0 +1,70 @@
+use super::{MonorepoProvider, PackageInfo};
+use crate::config::MonorepoProviderType;
+use compact_str::CompactString;
+use std::path::Path;
+
+/// Treats each listed directory as a package, without globbing or walking
+/// the tree. Directories that don't exist are skipped.
+pub struct DirectoriesProvider {
+    dirs: Vec<CompactString>,
+}
+
+impl DirectoriesProvider {
+    pub fn new(dirs: Vec<CompactString>) -> Self {
+        Self { dirs }
+    }
+}
+
+impl MonorepoProvider for DirectoriesProvider {
+    fn provider_type(&self) -> MonorepoProviderType {
+        MonorepoProviderType::Directories
+    }
+
+    fn config_file(&self) -> &'static str {
+        ".ecolog.toml"
+    }
+
+    fn detect(&self, _root: &Path) -> bool {
+        true
+    }
+
+    fn config_files(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    fn discover_packages(&self, root: &Path) -> crate::Result<Vec<PackageInfo>> {
+        let mut packages = Vec::with_capacity(self.dirs.len());
+        for dir in &self.dirs {
+            let relative = dir.trim_start_matches("./").trim_end_matches('/');
+            let relative = if relative.is_empty() { "." } else { relative };
+            let package_root = if relative == "." {
+                root.to_path_buf()
+            } else {
+                root.join(relative)
+            };
+
+            if !package_root.is_dir() {
+                tracing::warn!("Package directory not found: {}", package_root.display());
+                continue;
+            }
+            if packages
+                .iter()
+                .any(|p: &PackageInfo| p.root == package_root)
+            {
+                continue;
+            }
+
+            packages.push(PackageInfo {
+                root: package_root,
+                name: None,
+                relative_path: CompactString::new(relative),
+            });
+        }
+
+        Ok(packages)
+    }
+
+    fn workspace_globs(&self, _root: &Path) -> Vec<CompactString> {
+        self.dirs.clone()
+    }
+}
@@ -1,5 +1,6 @@
 use super::{MonorepoProvider, PackageInfo};
 use crate::config::MonorepoProviderType;
+use compact_str::CompactString;
 use serde::Deserialize;
 use std::path::Path;
 
@@ -19,11 +20,7 @@ impl Default for LernaProvider {
 
 #[derive(Debug, Deserialize)]
 struct LernaJson {
-    #[serde(default = "default_packages")]
-    packages: Vec<String>,
-    #[serde(default)]
-    #[allow(dead_code)]
-    version: String,
+    packages: Option<Vec<String>>,
 }
 
 fn default_packages() -> Vec<String> {
@@ -40,13 +37,26 @@ impl MonorepoProvider for LernaProvider {
     }
 
     fn discover_packages(&self, root: &Path) -> crate::Result<Vec<PackageInfo>> {
-        let config_path = root.join("lerna.json");
-        let content = std::fs::read_to_string(&config_path).unwrap_or_default();
-        let config: LernaJson = serde_json::from_str(&content).unwrap_or(LernaJson {
-            packages: default_packages(),
-            version: String::new(),
-        });
-
-        super::pnpm::expand_package_patterns(root, &config.packages)
+        super::pnpm::expand_package_patterns(root, &read_package_patterns(root))
     }
+
+    fn workspace_globs(&self, root: &Path) -> Vec<CompactString> {
+        read_package_patterns(root)
+            .iter()
+            .map(CompactString::new)
+            .collect()
+    }
+}
+
+/// `packages` from `lerna.json`, or, since newer Lerna defers to the package
+/// manager, the `workspaces` of the root `package.json`.
+fn read_package_patterns(root: &Path) -> Vec<String> {
+    let config_path = root.join("lerna.json");
+    let content = std::fs::read_to_string(&config_path).unwrap_or_default();
+    let config: LernaJson = serde_json::from_str(&content).unwrap_or(LernaJson { packages: None });
+
+    config
+        .packages
+        .or_else(|| super::npm::read_workspace_patterns(root))
+        .unwrap_or_else(default_packages)
 }
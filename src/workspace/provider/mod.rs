@@ -1,5 +1,6 @@
 mod cargo;
 mod custom;
+mod directories;
 mod lerna;
 mod npm;
 mod nx;
@@ -23,6 +24,24 @@ pub trait MonorepoProvider: Send + Sync {
         root.join(self.config_file()).exists()
     }
     fn discover_packages(&self, root: &Path) -> crate::Result<Vec<PackageInfo>>;
+
+    /// Files, relative to the root, whose contents decide the package list.
+    /// A change to any of them means packages must be rediscovered.
+    fn config_files(&self) -> Vec<&'static str> {
+        vec![self.config_file()]
+    }
+
+    /// Package patterns as configured by the provider (e.g. pnpm's `packages:`),
+    /// before they are expanded into packages.
+    fn workspace_globs(&self, _root: &Path) -> Vec<CompactString> {
+        Vec::new()
+    }
+
+    /// Environment variable names the provider's task configuration declares
+    /// (e.g. Turbo's `env` and `passThroughEnv`). Entries may be wildcards.
+    fn declared_env_keys(&self, _root: &Path) -> Vec<CompactString> {
+        Vec::new()
+    }
 }
 
 /// Configuration for glob pattern traversal.
@@ -158,6 +177,7 @@ where
 
 pub use cargo::CargoProvider;
 pub use custom::CustomProvider;
+pub use directories::DirectoriesProvider;
 pub use lerna::LernaProvider;
 pub use npm::NpmProvider;
 pub use nx::NxProvider;
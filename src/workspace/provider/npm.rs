@@ -1,8 +1,12 @@
 use super::{MonorepoProvider, PackageInfo};
 use crate::config::MonorepoProviderType;
+use compact_str::CompactString;
 use serde::Deserialize;
 use std::path::Path;
 
+/// Lockfiles that mark a single-package npm or Yarn repository.
+const LOCKFILES: &[&str] = &["package-lock.json", "yarn.lock"];
+
 pub struct NpmProvider;
 
 impl NpmProvider {
@@ -17,11 +21,16 @@ impl Default for NpmProvider {
     }
 }
 
+/// `workspaces` is either a plain list of globs or Yarn's object form,
+/// `{ "packages": [...], "nohoist": [...] }`.
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
 enum Workspaces {
     Array(Vec<String>),
-    Object { packages: Vec<String> },
+    Object {
+        #[serde(default)]
+        packages: Vec<String>,
+    },
 }
 
 #[derive(Debug, Deserialize)]
@@ -40,29 +49,46 @@ impl MonorepoProvider for NpmProvider {
 
     fn detect(&self, root: &Path) -> bool {
         let pkg_path = root.join("package.json");
-        if !pkg_path.exists() {
-            return false;
-        }
-
-        std::fs::read_to_string(&pkg_path)
+        let has_workspaces = std::fs::read_to_string(&pkg_path)
             .ok()
             .and_then(|c| serde_json::from_str::<PackageJson>(&c).ok())
             .map(|p| p.workspaces.is_some())
-            .unwrap_or(false)
+            .unwrap_or(false);
+
+        has_workspaces || has_lockfile(root)
     }
 
     fn discover_packages(&self, root: &Path) -> crate::Result<Vec<PackageInfo>> {
-        let pkg_path = root.join("package.json");
-        let content = std::fs::read_to_string(&pkg_path).unwrap_or_default();
-        let pkg: PackageJson =
-            serde_json::from_str(&content).unwrap_or(PackageJson { workspaces: None });
+        match read_workspace_patterns(root) {
+            Some(patterns) => super::pnpm::expand_package_patterns(root, &patterns),
+            None if has_lockfile(root) => Ok(vec![super::pnpm::root_package(root)]),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn workspace_globs(&self, root: &Path) -> Vec<CompactString> {
+        read_workspace_patterns(root)
+            .unwrap_or_default()
+            .iter()
+            .map(CompactString::new)
+            .collect()
+    }
+}
+
+fn has_lockfile(root: &Path) -> bool {
+    LOCKFILES
+        .iter()
+        .any(|lockfile| root.join(lockfile).exists())
+}
 
-        let patterns = match pkg.workspaces {
-            Some(Workspaces::Array(arr)) => arr,
-            Some(Workspaces::Object { packages }) => packages,
-            None => return Ok(Vec::new()),
-        };
+pub(super) fn read_workspace_patterns(root: &Path) -> Option<Vec<String>> {
+    let pkg_path = root.join("package.json");
+    let content = std::fs::read_to_string(&pkg_path).unwrap_or_default();
+    let pkg: PackageJson =
+        serde_json::from_str(&content).unwrap_or(PackageJson { workspaces: None });
 
-        super::pnpm::expand_package_patterns(root, &patterns)
+    match pkg.workspaces? {
+        Workspaces::Array(arr) => Some(arr),
+        Workspaces::Object { packages } => Some(packages),
     }
 }
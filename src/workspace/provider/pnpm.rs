@@ -36,18 +36,36 @@ impl MonorepoProvider for PnpmProvider {
         "pnpm-workspace.yaml"
     }
 
-    fn discover_packages(&self, root: &Path) -> crate::Result<Vec<PackageInfo>> {
-        let config_path = root.join("pnpm-workspace.yaml");
-        let content = std::fs::read_to_string(&config_path).unwrap_or_default();
+    fn detect(&self, root: &Path) -> bool {
+        root.join(self.config_file()).exists() || root.join("pnpm-lock.yaml").exists()
+    }
 
-        let config: PnpmWorkspace = serde_yaml_ng::from_str(&content).unwrap_or(PnpmWorkspace {
-            packages: vec!["packages/*".to_string()],
-        });
+    fn discover_packages(&self, root: &Path) -> crate::Result<Vec<PackageInfo>> {
+        if !root.join(self.config_file()).exists() && root.join("pnpm-lock.yaml").exists() {
+            return Ok(vec![root_package(root)]);
+        }
+        expand_package_patterns(root, &read_package_patterns(root))
+    }
 
-        expand_package_patterns(root, &config.packages)
+    fn workspace_globs(&self, root: &Path) -> Vec<CompactString> {
+        read_package_patterns(root)
+            .iter()
+            .map(CompactString::new)
+            .collect()
     }
 }
 
+fn read_package_patterns(root: &Path) -> Vec<String> {
+    let config_path = root.join("pnpm-workspace.yaml");
+    let content = std::fs::read_to_string(&config_path).unwrap_or_default();
+
+    let config: PnpmWorkspace = serde_yaml_ng::from_str(&content).unwrap_or(PnpmWorkspace {
+        packages: vec!["packages/*".to_string()],
+    });
+
+    config.packages
+}
+
 pub(super) fn expand_package_patterns(
     root: &Path,
     patterns: &[String],
@@ -61,6 +79,15 @@ pub(super) fn expand_package_patterns(
     traverse_glob_patterns(root, patterns, &config, extract_package_name)
 }
 
+/// The root of a single-package repository, detected by its lockfile alone.
+pub(super) fn root_package(root: &Path) -> PackageInfo {
+    PackageInfo {
+        root: root.to_path_buf(),
+        name: extract_package_name(root),
+        relative_path: CompactString::new("."),
+    }
+}
+
 fn extract_package_name(path: &Path) -> Option<CompactString> {
     let pkg_json = path.join("package.json");
     std::fs::read_to_string(&pkg_json)
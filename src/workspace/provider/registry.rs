@@ -1,10 +1,18 @@
 use super::{
-    CargoProvider, CustomProvider, LernaProvider, MonorepoProvider, NpmProvider, NxProvider,
-    PnpmProvider, TurboProvider,
+    CargoProvider, CustomProvider, DirectoriesProvider, LernaProvider, MonorepoProvider,
+    NpmProvider, NxProvider, PnpmProvider, TurboProvider,
 };
 use crate::config::{MonorepoProviderType, WorkspaceConfig};
 use std::sync::Arc;
 
+/// Package manager lockfiles, checked in order once no workspace config is
+/// found. Such repositories are treated as a single package at the root.
+const LOCKFILES: &[(&str, MonorepoProviderType)] = &[
+    ("pnpm-lock.yaml", MonorepoProviderType::Pnpm),
+    ("yarn.lock", MonorepoProviderType::Yarn),
+    ("package-lock.json", MonorepoProviderType::Npm),
+];
+
 pub struct ProviderRegistry;
 
 impl ProviderRegistry {
@@ -19,6 +27,9 @@ impl ProviderRegistry {
             MonorepoProviderType::Npm | MonorepoProviderType::Yarn => Arc::new(NpmProvider::new()),
             MonorepoProviderType::Cargo => Arc::new(CargoProvider::new()),
             MonorepoProviderType::Custom => Arc::new(CustomProvider::new(config.roots.clone())),
+            MonorepoProviderType::Directories => {
+                Arc::new(DirectoriesProvider::new(config.roots.clone()))
+            }
         };
 
         Some(provider)
@@ -56,6 +67,9 @@ impl ProviderRegistry {
             }
         }
 
-        None
+        LOCKFILES
+            .iter()
+            .find(|(lockfile, _)| root.join(lockfile).exists())
+            .map(|(_, provider)| *provider)
     }
 }
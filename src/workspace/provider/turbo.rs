@@ -1,6 +1,8 @@
 use super::{MonorepoProvider, PackageInfo};
 use crate::config::MonorepoProviderType;
+use compact_str::CompactString;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::path::Path;
 
 pub struct TurboProvider;
@@ -17,11 +19,31 @@ impl Default for TurboProvider {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct TurboJson {
     #[serde(default)]
     #[allow(dead_code)]
     extends: Vec<String>,
+    #[serde(default)]
+    global_env: Vec<String>,
+    #[serde(default)]
+    global_pass_through_env: Vec<String>,
+    /// Turbo 1.x task definitions.
+    #[serde(default)]
+    pipeline: HashMap<String, TurboTask>,
+    /// Turbo 2.x task definitions.
+    #[serde(default)]
+    tasks: HashMap<String, TurboTask>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TurboTask {
+    #[serde(default)]
+    env: Vec<String>,
+    #[serde(default)]
+    pass_through_env: Vec<String>,
 }
 
 impl MonorepoProvider for TurboProvider {
@@ -33,6 +55,10 @@ impl MonorepoProvider for TurboProvider {
         "turbo.json"
     }
 
+    fn config_files(&self) -> Vec<&'static str> {
+        vec!["turbo.json", "pnpm-workspace.yaml", "package.json"]
+    }
+
     fn discover_packages(&self, root: &Path) -> crate::Result<Vec<PackageInfo>> {
         if root.join("pnpm-workspace.yaml").exists() {
             return super::PnpmProvider::new().discover_packages(root);
@@ -40,4 +66,33 @@ impl MonorepoProvider for TurboProvider {
 
         super::NpmProvider::new().discover_packages(root)
     }
+
+    fn workspace_globs(&self, root: &Path) -> Vec<CompactString> {
+        if root.join("pnpm-workspace.yaml").exists() {
+            return super::PnpmProvider::new().workspace_globs(root);
+        }
+
+        super::NpmProvider::new().workspace_globs(root)
+    }
+
+    fn declared_env_keys(&self, root: &Path) -> Vec<CompactString> {
+        let content = std::fs::read_to_string(root.join("turbo.json")).unwrap_or_default();
+        let turbo: TurboJson = serde_json::from_str(&content).unwrap_or_default();
+
+        let task_keys = turbo
+            .pipeline
+            .values()
+            .chain(turbo.tasks.values())
+            .flat_map(|task| task.env.iter().chain(&task.pass_through_env));
+
+        turbo
+            .global_env
+            .iter()
+            .chain(&turbo.global_pass_through_env)
+            .chain(task_keys)
+            // `!KEY` entries exclude keys from wildcard matches; they declare nothing.
+            .filter(|key| !key.starts_with('!'))
+            .map(CompactString::new)
+            .collect()
+    }
 }
@@ -0,0 +1,1944 @@
+use abundantis::{
+    config::{MonorepoProviderType, OnUndefined, SourceDefaults},
+    Abundantis, AbundantisError, DiagnosticCode,
+};
+use std::collections::HashMap;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn workspace_with(files: &[(&str, &str)]) -> TempDir {
+    let dir = TempDir::new().unwrap();
+    for (path, content) in files {
+        let full_path = dir.path().join(path);
+        if let Some(parent) = full_path.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        std::fs::write(full_path, content).unwrap();
+    }
+    dir
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync)]
+async fn build(root: &Path) -> Abundantis {
+    Abundantis::builder()
+        .root(root)
+        .provider(MonorepoProviderType::Custom)
+        .roots(vec!["."])
+        .build()
+        .await
+        .unwrap()
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync)]
+async fn build_unwatched(root: &Path) -> Abundantis {
+    Abundantis::builder()
+        .root(root)
+        .provider(MonorepoProviderType::Custom)
+        .roots(vec!["."])
+        .watch(false)
+        .build()
+        .await
+        .unwrap()
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_get_raw_skips_interpolation() {
+    let dir = workspace_with(&[
+        (
+            ".env",
+            "ABUNDANTIS_RAW_HOST=localhost\nABUNDANTIS_RAW_URL=${ABUNDANTIS_RAW_HOST}/api\n",
+        ),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+    let file = dir.path().join("main.rs");
+
+    let raw = abundantis
+        .get_raw("ABUNDANTIS_RAW_URL", &file)
+        .await
+        .unwrap();
+    assert_eq!(raw.as_deref(), Some("${ABUNDANTIS_RAW_HOST}/api"));
+
+    let resolved = abundantis
+        .get_for_file("ABUNDANTIS_RAW_URL", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(resolved.resolved_value.as_str(), "localhost/api");
+
+    let missing = abundantis
+        .get_raw("ABUNDANTIS_RAW_MISSING", &file)
+        .await
+        .unwrap();
+    assert!(missing.is_none());
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_warm_cache_makes_lookups_cache_hits() {
+    let dir = workspace_with(&[
+        (
+            ".env",
+            "ABUNDANTIS_WARM_HOST=localhost\nABUNDANTIS_WARM_URL=${ABUNDANTIS_WARM_HOST}/api\n",
+        ),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+    let file = dir.path().join("main.rs");
+
+    let cached = abundantis.warm_cache(&file).await.unwrap();
+    assert!(cached >= 2);
+
+    let before = abundantis.stats();
+    let url = abundantis
+        .get_for_file("ABUNDANTIS_WARM_URL", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(url.resolved_value.as_str(), "localhost/api");
+    abundantis
+        .get_for_file("ABUNDANTIS_WARM_HOST", &file)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let after = abundantis.stats();
+    assert_eq!(after.cache_hits, before.cache_hits + 2);
+    assert_eq!(after.cache_misses, before.cache_misses);
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_interpolation_off_directive_keeps_file_values_literal() {
+    let dir = workspace_with(&[
+        (
+            ".env",
+            "ABUNDANTIS_DIRECTIVE_HOST=localhost\nABUNDANTIS_DIRECTIVE_URL=${ABUNDANTIS_DIRECTIVE_HOST}/api\n",
+        ),
+        (
+            ".env.local",
+            "# abundantis: interpolation=off\nABUNDANTIS_DIRECTIVE_TEMPLATE=${ABUNDANTIS_DIRECTIVE_HOST}/api\n",
+        ),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+    let file = dir.path().join("main.rs");
+
+    let template = abundantis
+        .get_for_file("ABUNDANTIS_DIRECTIVE_TEMPLATE", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        template.resolved_value.as_str(),
+        "${ABUNDANTIS_DIRECTIVE_HOST}/api"
+    );
+
+    let url = abundantis
+        .get_for_file("ABUNDANTIS_DIRECTIVE_URL", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(url.resolved_value.as_str(), "localhost/api");
+}
+
+#[cfg(feature = "file")]
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_try_get_for_file_swallows_misses_and_errors() {
+    let dir = workspace_with(&[(".env", "ABUNDANTIS_TRY_GET=1\n"), ("main.rs", "")]);
+    let abundantis = build(dir.path()).await;
+    let file = dir.path().join("main.rs");
+
+    let found = abundantis
+        .try_get_for_file("ABUNDANTIS_TRY_GET", &file)
+        .await;
+    assert_eq!(found.unwrap().resolved_value.as_str(), "1");
+
+    let missing = abundantis
+        .try_get_for_file("ABUNDANTIS_TRY_GET_MISSING", &file)
+        .await;
+    assert!(missing.is_none());
+
+    let outside = TempDir::new().unwrap();
+    let outside_file = outside.path().join("main.rs");
+    let outside_result = abundantis
+        .get_for_file("ABUNDANTIS_TRY_GET", &outside_file)
+        .await;
+    assert!(outside_result.is_err());
+    let outside_value = abundantis
+        .try_get_for_file("ABUNDANTIS_TRY_GET", &outside_file)
+        .await;
+    assert!(outside_value.is_none());
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_resolve_template_file_renders_references() {
+    let dir = workspace_with(&[
+        (
+            ".env",
+            "ABUNDANTIS_TEMPLATE_API_URL=https://api.example.com\n",
+        ),
+        (
+            "nginx.conf.tmpl",
+            "server { url ${ABUNDANTIS_TEMPLATE_API_URL}; }\n",
+        ),
+        (
+            "undefined.tmpl",
+            "key ${ABUNDANTIS_TEMPLATE_MISSING} $ABUNDANTIS_TEMPLATE_MISSING;\n",
+        ),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+    let file = dir.path().join("main.rs");
+    let output = dir.path().join("nginx.conf");
+
+    abundantis
+        .resolve_template_file(&dir.path().join("nginx.conf.tmpl"), &output, &file)
+        .await
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&output).unwrap(),
+        "server { url https://api.example.com; }\n"
+    );
+
+    let kept = dir.path().join("kept.conf");
+    abundantis
+        .resolve_template_file(&dir.path().join("undefined.tmpl"), &kept, &file)
+        .await
+        .unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&kept).unwrap(),
+        "key ${ABUNDANTIS_TEMPLATE_MISSING} $ABUNDANTIS_TEMPLATE_MISSING;\n"
+    );
+
+    let strict = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Custom)
+        .roots(vec!["."])
+        .on_undefined(OnUndefined::Error)
+        .build()
+        .await
+        .unwrap();
+    let failed = dir.path().join("failed.conf");
+    let result = strict
+        .resolve_template_file(&dir.path().join("undefined.tmpl"), &failed, &file)
+        .await;
+    assert!(matches!(
+        result,
+        Err(AbundantisError::UndefinedVariable { key }) if key == "ABUNDANTIS_TEMPLATE_MISSING"
+    ));
+    assert!(!failed.exists());
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_graph_dot_lists_references() {
+    let dir = workspace_with(&[
+        (
+            ".env",
+            "ABUNDANTIS_DOT_A=${ABUNDANTIS_DOT_B}\nABUNDANTIS_DOT_B=1\n",
+        ),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+
+    let dot = abundantis
+        .graph_dot(&dir.path().join("main.rs"))
+        .await
+        .unwrap();
+    assert!(dot.starts_with("digraph env {"));
+    assert!(dot.contains("\"ABUNDANTIS_DOT_A\" -> \"ABUNDANTIS_DOT_B\";"));
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_unset_removes_key_from_file() {
+    let dir = workspace_with(&[
+        (".env", "ABUNDANTIS_UNSET_KEEP=1\nABUNDANTIS_UNSET_DROP=2\n"),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+    let file = dir.path().join("main.rs");
+
+    let before = abundantis
+        .get_for_file("ABUNDANTIS_UNSET_DROP", &file)
+        .await
+        .unwrap();
+    assert!(before.is_some());
+
+    let removed = abundantis
+        .unset("ABUNDANTIS_UNSET_DROP", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(removed.raw_value.as_str(), "2");
+
+    let after = abundantis
+        .get_for_file("ABUNDANTIS_UNSET_DROP", &file)
+        .await
+        .unwrap();
+    assert!(after.is_none());
+    let content = std::fs::read_to_string(dir.path().join(".env")).unwrap();
+    assert!(!content.contains("ABUNDANTIS_UNSET_DROP"));
+    assert!(content.contains("ABUNDANTIS_UNSET_KEEP=1"));
+
+    let missing = abundantis
+        .unset("ABUNDANTIS_UNSET_DROP", &file)
+        .await
+        .unwrap();
+    assert!(missing.is_none());
+}
+
+#[cfg(feature = "file")]
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_set_many_files_applies_every_edit() {
+    let dir = workspace_with(&[
+        (".env", "ABUNDANTIS_MANY_A=1\n"),
+        ("apps/web/.env", "ABUNDANTIS_MANY_B=1\n"),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+    let root_env = dir.path().join(".env");
+    let web_env = dir.path().join("apps/web/.env");
+
+    let before = abundantis
+        .get_for_file("ABUNDANTIS_MANY_A", &dir.path().join("main.rs"))
+        .await
+        .unwrap();
+    assert_eq!(before.unwrap().resolved_value.as_str(), "1");
+
+    abundantis
+        .set_many_files(&[
+            (
+                root_env.clone(),
+                vec![("ABUNDANTIS_MANY_A".into(), "2".into())],
+            ),
+            (
+                web_env.clone(),
+                vec![
+                    ("ABUNDANTIS_MANY_B".into(), "2".into()),
+                    ("ABUNDANTIS_MANY_C".into(), "3".into()),
+                ],
+            ),
+        ])
+        .unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(&root_env).unwrap(),
+        "ABUNDANTIS_MANY_A=2\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(&web_env).unwrap(),
+        "ABUNDANTIS_MANY_B=2\nABUNDANTIS_MANY_C=3\n"
+    );
+    let after = abundantis
+        .get_for_file("ABUNDANTIS_MANY_A", &dir.path().join("main.rs"))
+        .await
+        .unwrap();
+    assert_eq!(after.unwrap().resolved_value.as_str(), "2");
+}
+
+#[cfg(feature = "file")]
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_set_many_files_restores_on_failure() {
+    let dir = workspace_with(&[(".env", "ABUNDANTIS_ROLLBACK=1\n")]);
+    let abundantis = build(dir.path()).await;
+    let root_env = dir.path().join(".env");
+    // Writable, so it passes validation, but not UTF-8, so the edit fails.
+    let broken = dir.path().join("broken.env");
+    std::fs::write(&broken, [0xff, 0xfe, b'\n']).unwrap();
+
+    let result = abundantis.set_many_files(&[
+        (
+            root_env.clone(),
+            vec![("ABUNDANTIS_ROLLBACK".into(), "2".into())],
+        ),
+        (
+            broken.clone(),
+            vec![("ABUNDANTIS_ROLLBACK".into(), "2".into())],
+        ),
+    ]);
+
+    assert!(result.is_err());
+    assert_eq!(
+        std::fs::read_to_string(&root_env).unwrap(),
+        "ABUNDANTIS_ROLLBACK=1\n"
+    );
+    assert_eq!(std::fs::read(&broken).unwrap(), [0xff, 0xfe, b'\n']);
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_validate_file_reports_same_directory_conflicts() {
+    let dir = workspace_with(&[
+        (
+            ".env",
+            "ABUNDANTIS_CONFLICT_PORT=3000\nABUNDANTIS_CONFLICT_HOST=localhost\n",
+        ),
+        (
+            ".env.local",
+            "ABUNDANTIS_CONFLICT_PORT=4000\nABUNDANTIS_CONFLICT_HOST=localhost\n",
+        ),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+    abundantis.set_active_files(&[".env", ".env.local"]);
+
+    let diagnostics = abundantis
+        .validate_file(&dir.path().join("main.rs"))
+        .unwrap();
+    let conflicts: Vec<_> = diagnostics
+        .iter()
+        .filter(|d| d.code == DiagnosticCode::WS002)
+        .collect();
+
+    assert_eq!(conflicts.len(), 1);
+    assert!(conflicts[0].message.contains("ABUNDANTIS_CONFLICT_PORT"));
+    assert!(conflicts[0].path.ends_with(".env.local"));
+    assert_eq!(conflicts[0].line, 1);
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_active_file_matched_twice_is_loaded_once() {
+    let dir = workspace_with(&[
+        (".env", "ABUNDANTIS_DEDUP_KEY=value\n"),
+        ("apps/web/index.js", ""),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+    abundantis.set_active_files(&[".env"]);
+    abundantis.set_active_files_for_directory(dir.path(), &[".env", "apps/../.env"]);
+
+    let file = dir.path().join("main.rs");
+    let active = abundantis.active_env_files(&file);
+    assert_eq!(active.len(), 1);
+    assert!(active[0].ends_with(".env"));
+
+    let variables = abundantis.all_for_file(&file).await.unwrap();
+    let matches = variables
+        .iter()
+        .filter(|v| v.key == "ABUNDANTIS_DEDUP_KEY")
+        .count();
+    assert_eq!(matches, 1);
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_snapshot_is_point_in_time() {
+    let dir = workspace_with(&[
+        (
+            ".env",
+            "ABUNDANTIS_SNAP_HOST=localhost\nABUNDANTIS_SNAP_URL=${ABUNDANTIS_SNAP_HOST}/api\n",
+        ),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+    let file = dir.path().join("main.rs");
+
+    let snapshot = abundantis.snapshot(&file).await.unwrap();
+    assert_eq!(
+        snapshot
+            .get("ABUNDANTIS_SNAP_URL")
+            .unwrap()
+            .resolved_value
+            .as_str(),
+        "localhost/api"
+    );
+
+    std::fs::write(
+        dir.path().join(".env"),
+        "ABUNDANTIS_SNAP_HOST=example.com\nABUNDANTIS_SNAP_URL=${ABUNDANTIS_SNAP_HOST}/api\n",
+    )
+    .unwrap();
+    abundantis
+        .refresh(abundantis::RefreshOptions::reset_all())
+        .await
+        .unwrap();
+
+    let current = abundantis
+        .get_for_file("ABUNDANTIS_SNAP_HOST", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(current.resolved_value.as_str(), "example.com");
+    assert_eq!(
+        snapshot
+            .get("ABUNDANTIS_SNAP_HOST")
+            .unwrap()
+            .resolved_value
+            .as_str(),
+        "localhost"
+    );
+    assert!(snapshot.get("ABUNDANTIS_SNAP_MISSING").is_none());
+}
+
+#[cfg(feature = "blocking")]
+mod blocking {
+    use super::workspace_with;
+    use abundantis::config::MonorepoProviderType;
+    use abundantis::source::{
+        AsyncEnvSource, ParsedVariable, Priority, SourceCapabilities, SourceId, SourceSnapshot,
+        SourceType, VariableSource,
+    };
+    use abundantis::{error::SourceError, Abundantis};
+    use std::sync::Arc;
+
+    struct StaticAsyncSource {
+        id: SourceId,
+    }
+
+    #[async_trait::async_trait]
+    impl AsyncEnvSource for StaticAsyncSource {
+        fn id(&self) -> &SourceId {
+            &self.id
+        }
+
+        fn source_type(&self) -> SourceType {
+            SourceType::Memory
+        }
+
+        fn priority(&self) -> Priority {
+            Priority::MEMORY
+        }
+
+        fn capabilities(&self) -> SourceCapabilities {
+            SourceCapabilities::READ
+        }
+
+        async fn load(&self) -> Result<SourceSnapshot, SourceError> {
+            tokio::task::yield_now().await;
+            Ok(SourceSnapshot {
+                source_id: self.id.clone(),
+                source_type: SourceType::Memory,
+                variables: vec![ParsedVariable::simple(
+                    "ABUNDANTIS_BLOCKING_TOKEN",
+                    "from-async",
+                    VariableSource::Memory,
+                )]
+                .into(),
+                timestamp: std::time::Instant::now(),
+                version: Some(1),
+            })
+        }
+
+        async fn refresh(&self) -> Result<bool, SourceError> {
+            Ok(false)
+        }
+    }
+
+    #[test]
+    fn test_blocking_resolves_async_source() {
+        let dir = workspace_with(&[("main.rs", "")]);
+        let abundantis = abundantis::blocking::block_on(
+            Abundantis::builder()
+                .root(dir.path())
+                .provider(MonorepoProviderType::Custom)
+                .roots(vec!["."])
+                .build(),
+        )
+        .unwrap()
+        .unwrap();
+        abundantis
+            .registry
+            .register_async(Arc::new(StaticAsyncSource {
+                id: SourceId::new("static-async"),
+            }));
+
+        let variable = abundantis
+            .get_for_file_blocking("ABUNDANTIS_BLOCKING_TOKEN", &dir.path().join("main.rs"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(variable.resolved_value.as_str(), "from-async");
+    }
+
+    #[tokio::test]
+    async fn test_blocking_inside_runtime_errors() {
+        let result = abundantis::blocking::block_on(async {});
+        assert!(result.is_err());
+    }
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_environment_selects_layered_files() {
+    let dir = workspace_with(&[
+        (".env", "ABUNDANTIS_ENV_LAYER=base\n"),
+        (".env.staging", "ABUNDANTIS_ENV_LAYER=staging\n"),
+        (".env.staging.local", "ABUNDANTIS_ENV_LAYER=staging-local\n"),
+        (".env.production", "ABUNDANTIS_ENV_LAYER=production\n"),
+        ("main.rs", ""),
+    ]);
+    let abundantis = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Custom)
+        .roots(vec!["."])
+        .environment("staging")
+        .build()
+        .await
+        .unwrap();
+    let file = dir.path().join("main.rs");
+
+    let active: Vec<_> = abundantis
+        .active_env_files(&file)
+        .iter()
+        .map(|p| p.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(active, vec![".env", ".env.staging", ".env.staging.local"]);
+
+    let layer = abundantis
+        .get_for_file("ABUNDANTIS_ENV_LAYER", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(layer.resolved_value.as_str(), "staging-local");
+
+    // The layer is derived at build time, not written into the config.
+    let config = abundantis.config();
+    let defaults = abundantis::AbundantisConfig::default();
+    assert_eq!(config.workspace.env_files, defaults.workspace.env_files);
+    assert_eq!(
+        config.resolution.files.order,
+        defaults.resolution.files.order
+    );
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_file_source_metadata_tracks_refreshes_and_errors() {
+    let dir = workspace_with(&[(".env", "ABUNDANTIS_META=1\n"), ("main.rs", "")]);
+    // The watcher would reload the deleted file and count its own error.
+    let abundantis = build_unwatched(dir.path()).await;
+    let file = dir.path().join("main.rs");
+    let env_path = dir.path().join(".env");
+    let id = abundantis::source::SourceId::new(format!("file:{}", env_path.display()));
+
+    let loaded = abundantis.get_for_file("ABUNDANTIS_META", &file).await;
+    assert!(loaded.unwrap().is_some());
+
+    std::fs::remove_file(&env_path).unwrap();
+    let failed = abundantis
+        .get_for_file("ABUNDANTIS_META_MISSING", &file)
+        .await;
+    assert!(failed.is_err());
+
+    let metadata = abundantis.source_metadata(&id).unwrap();
+    assert!(metadata.last_refreshed.is_some());
+    assert_eq!(metadata.error_count, 1);
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_ignored_directories_are_not_registered() {
+    let dir = workspace_with(&[
+        (".env", "ABUNDANTIS_IGNORE=root\n"),
+        ("node_modules/pkg/.env", "ABUNDANTIS_IGNORE=vendored\n"),
+        ("main.rs", ""),
+    ]);
+    let abundantis = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Custom)
+        .roots(vec!["."])
+        .env_files(vec!["**/.env"])
+        .build()
+        .await
+        .unwrap();
+
+    let registered = abundantis.registry.registered_file_paths();
+    assert_eq!(registered.len(), 1);
+    assert!(registered[0].ends_with(".env"));
+    assert!(!registered[0].to_string_lossy().contains("node_modules"));
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_all_for_package_includes_root_cascade() {
+    let dir = workspace_with(&[
+        ("pnpm-workspace.yaml", "packages:\n  - apps/*\n"),
+        ("package.json", r#"{"name": "root"}"#),
+        (
+            ".env",
+            "ABUNDANTIS_PKG_SHARED=root\nABUNDANTIS_PKG_ROOT_ONLY=1\n",
+        ),
+        ("apps/web/package.json", r#"{"name": "web"}"#),
+        (
+            "apps/web/.env",
+            "ABUNDANTIS_PKG_SHARED=web\nABUNDANTIS_PKG_WEB_ONLY=1\n",
+        ),
+        ("apps/api/package.json", r#"{"name": "api"}"#),
+        ("apps/api/.env", "ABUNDANTIS_PKG_API_ONLY=1\n"),
+    ]);
+    let abundantis = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Pnpm)
+        .cascading(true)
+        .build()
+        .await
+        .unwrap();
+
+    let variables = abundantis.all_for_package("web").await.unwrap();
+    let mut keys: Vec<_> = variables
+        .iter()
+        .filter(|v| v.key.starts_with("ABUNDANTIS_PKG_"))
+        .map(|v| (v.key.to_string(), v.resolved_value.to_string()))
+        .collect();
+    keys.sort();
+    assert_eq!(
+        keys,
+        vec![
+            ("ABUNDANTIS_PKG_ROOT_ONLY".to_string(), "1".to_string()),
+            ("ABUNDANTIS_PKG_SHARED".to_string(), "web".to_string()),
+            ("ABUNDANTIS_PKG_WEB_ONLY".to_string(), "1".to_string()),
+        ]
+    );
+
+    let missing = abundantis.all_for_package("nope").await;
+    assert!(missing.is_err());
+
+    let isolated = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Pnpm)
+        .build()
+        .await
+        .unwrap();
+    let root_env = dir.path().join(".env").canonicalize().unwrap();
+    assert!(!isolated
+        .registry
+        .registered_file_paths()
+        .contains(&root_env));
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_resolve_all_packages_keys_by_package_name() {
+    let dir = workspace_with(&[
+        ("pnpm-workspace.yaml", "packages:\n  - apps/*\n"),
+        ("package.json", r#"{"name": "root"}"#),
+        ("apps/web/package.json", r#"{"name": "web"}"#),
+        ("apps/web/.env", "ABUNDANTIS_ALL_WEB=1\n"),
+        ("apps/api/package.json", r#"{"name": "api"}"#),
+        ("apps/api/.env", "ABUNDANTIS_ALL_API=1\n"),
+    ]);
+    let abundantis = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Pnpm)
+        .build()
+        .await
+        .unwrap();
+
+    let packages = abundantis.resolve_all_packages().await.unwrap();
+    let keys = |name: &str| -> Vec<String> {
+        let mut keys: Vec<_> = packages[name]
+            .iter()
+            .filter(|v| v.key.starts_with("ABUNDANTIS_ALL_"))
+            .map(|v| v.key.to_string())
+            .collect();
+        keys.sort();
+        keys
+    };
+
+    assert_eq!(keys("web"), vec!["ABUNDANTIS_ALL_WEB"]);
+    assert_eq!(keys("api"), vec!["ABUNDANTIS_ALL_API"]);
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_overrides_apply_only_while_guard_lives() {
+    let dir = workspace_with(&[
+        (
+            ".env",
+            "ABUNDANTIS_OVERRIDE=file\nABUNDANTIS_OVERRIDE_URL=${ABUNDANTIS_OVERRIDE}/api\n",
+        ),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+    let file = dir.path().join("main.rs");
+
+    let before = abundantis
+        .get_for_file("ABUNDANTIS_OVERRIDE", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(before.resolved_value.as_str(), "file");
+
+    {
+        let _guard = abundantis.with_overrides(&[("ABUNDANTIS_OVERRIDE", "scoped")]);
+
+        let overridden = abundantis
+            .get_for_file("ABUNDANTIS_OVERRIDE", &file)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(overridden.resolved_value.as_str(), "scoped");
+
+        let dependent = abundantis
+            .get_for_file("ABUNDANTIS_OVERRIDE_URL", &file)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(dependent.resolved_value.as_str(), "scoped/api");
+    }
+
+    let after = abundantis
+        .get_for_file("ABUNDANTIS_OVERRIDE", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(after.resolved_value.as_str(), "file");
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_cross_package_reference_resolves_in_named_package() {
+    let dir = workspace_with(&[
+        ("pnpm-workspace.yaml", "packages:\n  - apps/*\n  - packages/*\n"),
+        ("package.json", r#"{"name": "root"}"#),
+        ("apps/api/package.json", r#"{"name": "api"}"#),
+        (
+            "apps/api/.env",
+            "ABUNDANTIS_XPKG_API=${pkg:shared.ABUNDANTIS_XPKG_BASE}/api\n",
+        ),
+        ("apps/api/main.rs", ""),
+        ("packages/shared/package.json", r#"{"name": "shared"}"#),
+        (
+            "packages/shared/.env",
+            "ABUNDANTIS_XPKG_HOST=shared.example.com\nABUNDANTIS_XPKG_BASE=https://${ABUNDANTIS_XPKG_HOST}\n",
+        ),
+    ]);
+    let abundantis = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Pnpm)
+        .interpolation_features(abundantis::config::InterpolationFeatures {
+            cross_package: true,
+            ..Default::default()
+        })
+        .build()
+        .await
+        .unwrap();
+    let file = dir.path().join("apps/api/main.rs");
+
+    let api = abundantis
+        .get_for_file("ABUNDANTIS_XPKG_API", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        api.resolved_value.as_str(),
+        "https://shared.example.com/api"
+    );
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn test_build_blocking_outside_runtime() {
+    let dir = workspace_with(&[(".env", "ABUNDANTIS_BLOCKING_BUILD=1\n"), ("main.rs", "")]);
+
+    let abundantis = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Custom)
+        .roots(vec!["."])
+        .build_blocking()
+        .unwrap();
+
+    let active = abundantis.active_env_files(dir.path().join("main.rs"));
+    assert!(active.iter().any(|path| path.ends_with(".env")));
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_build_blocking_inside_runtime_errors() {
+    let dir = workspace_with(&[(".env", "ABUNDANTIS_BLOCKING_BUILD=1\n")]);
+
+    let result = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Custom)
+        .roots(vec!["."])
+        .build_blocking();
+
+    assert!(matches!(
+        result,
+        Err(abundantis::AbundantisError::Runtime(_))
+    ));
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_refresh_reports_rediscovered_sources() {
+    use abundantis::events::{AbundantisEvent, EventSubscriber};
+    use std::sync::{Arc, Mutex};
+
+    struct Recorder(Mutex<Vec<AbundantisEvent>>);
+    impl EventSubscriber for Recorder {
+        fn on_event(&self, event: &AbundantisEvent) {
+            self.0.lock().unwrap().push(event.clone());
+        }
+    }
+
+    let dir = workspace_with(&[(".env", "ABUNDANTIS_REDISCOVER=1\n")]);
+    let abundantis = build(dir.path()).await;
+    let recorder = Arc::new(Recorder(Mutex::new(Vec::new())));
+    abundantis.event_bus().subscribe(recorder.clone());
+
+    std::fs::write(dir.path().join(".env.local"), "ABUNDANTIS_REDISCOVER=2\n").unwrap();
+    std::fs::remove_file(dir.path().join(".env")).unwrap();
+    abundantis
+        .refresh(abundantis::RefreshOptions::reset_all())
+        .await
+        .unwrap();
+
+    let events = recorder.0.lock().unwrap();
+    let (added, removed) = events
+        .iter()
+        .find_map(|event| match event {
+            AbundantisEvent::SourcesRediscovered { added, removed } => Some((added, removed)),
+            _ => None,
+        })
+        .expect("refresh should report rediscovered sources");
+    let ids = |ids: &[abundantis::SourceId]| -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    };
+
+    assert_eq!(added.len(), 1);
+    assert!(ids(added)[0].starts_with("file:"));
+    assert!(ids(added)[0].ends_with(".env.local"));
+    assert_eq!(removed.len(), 1);
+    assert!(ids(removed)[0].ends_with("/.env"));
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_schema_supplies_defaults_and_descriptions() {
+    let dir = workspace_with(&[
+        (".env", "ABUNDANTIS_SCHEMA_HOST=localhost\n"),
+        (
+            ".env.example",
+            "# Host to bind\nABUNDANTIS_SCHEMA_HOST=0.0.0.0\n\n# Port the server listens on\nABUNDANTIS_SCHEMA_PORT=3000\n",
+        ),
+        ("main.rs", ""),
+    ]);
+    let abundantis = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Custom)
+        .roots(vec!["."])
+        .schema_file(".env.example")
+        .build()
+        .await
+        .unwrap();
+    let file = dir.path().join("main.rs");
+
+    let port = abundantis
+        .get_for_file("ABUNDANTIS_SCHEMA_PORT", &file)
+        .await
+        .unwrap()
+        .expect("declared default should resolve");
+    assert_eq!(port.resolved_value.as_str(), "3000");
+    assert_eq!(
+        port.description.as_deref(),
+        Some("Port the server listens on")
+    );
+    assert!(port.source.file_path().unwrap().ends_with(".env.example"));
+
+    let host = abundantis
+        .get_for_file("ABUNDANTIS_SCHEMA_HOST", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(host.resolved_value.as_str(), "localhost");
+    assert_eq!(host.description.as_deref(), Some("Host to bind"));
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_schema_defaults_reload_and_keep_their_own_id() {
+    use abundantis::BytesSource;
+    use std::sync::Arc;
+
+    let dir = workspace_with(&[
+        (".env.example", "ABUNDANTIS_SCHEMA_RELOAD=before\n"),
+        ("main.rs", ""),
+    ]);
+    let abundantis = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Custom)
+        .roots(vec!["."])
+        .schema_file(".env.example")
+        .with_source(Arc::new(BytesSource::new(
+            "schema",
+            b"ABUNDANTIS_SCHEMA_PIPED=piped\n",
+        )))
+        .watch(false)
+        .build()
+        .await
+        .unwrap();
+    let file = dir.path().join("main.rs");
+
+    let reloaded = abundantis
+        .get_for_file("ABUNDANTIS_SCHEMA_RELOAD", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reloaded.resolved_value.as_str(), "before");
+
+    std::fs::write(
+        dir.path().join(".env.example"),
+        "ABUNDANTIS_SCHEMA_RELOAD=after\nABUNDANTIS_SCHEMA_PIPED=default\n",
+    )
+    .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(600));
+
+    let reloaded = abundantis
+        .get_for_file("ABUNDANTIS_SCHEMA_RELOAD", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reloaded.resolved_value.as_str(), "after");
+
+    // A source named `schema` is a source like any other, not a default.
+    let piped = abundantis
+        .get_for_file("ABUNDANTIS_SCHEMA_PIPED", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(piped.resolved_value.as_str(), "piped");
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_profile_records_every_resolved_key() {
+    let dir = workspace_with(&[
+        (
+            ".env",
+            "ABUNDANTIS_PROFILE_HOST=localhost\nABUNDANTIS_PROFILE_URL=http://${ABUNDANTIS_PROFILE_HOST}\n",
+        ),
+        ("main.rs", ""),
+    ]);
+    let abundantis = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Custom)
+        .roots(vec!["."])
+        .profile(true)
+        .build()
+        .await
+        .unwrap();
+
+    let variables = abundantis
+        .all_for_file(&dir.path().join("main.rs"))
+        .await
+        .unwrap();
+    let profile = abundantis.last_profile();
+
+    assert!(!variables.is_empty());
+    for variable in &variables {
+        let recorded = profile.iter().find(|(key, _)| *key == variable.key);
+        assert!(recorded.is_some(), "no timing for {}", variable.key);
+        assert!(recorded.unwrap().1 >= std::time::Duration::ZERO);
+    }
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_clear_cache_picks_up_external_edits() {
+    use abundantis::events::{AbundantisEvent, EventSubscriber};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct Counter(AtomicUsize);
+    impl EventSubscriber for Counter {
+        fn on_event(&self, event: &AbundantisEvent) {
+            if matches!(event, AbundantisEvent::CacheInvalidated { scope: None }) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+        }
+    }
+
+    let dir = workspace_with(&[(".env", "ABUNDANTIS_CLEAR=old\n"), ("main.rs", "")]);
+    // The watcher would publish its own `CacheInvalidated` for the edit.
+    let abundantis = build_unwatched(dir.path()).await;
+    let file = dir.path().join("main.rs");
+    let counter = Arc::new(Counter(AtomicUsize::new(0)));
+    abundantis.event_bus().subscribe(counter.clone());
+
+    let cached = abundantis
+        .get_for_file("ABUNDANTIS_CLEAR", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(cached.resolved_value.as_str(), "old");
+
+    std::fs::write(dir.path().join(".env"), "ABUNDANTIS_CLEAR=new\n").unwrap();
+    abundantis.clear_cache();
+
+    let fresh = abundantis
+        .get_for_file("ABUNDANTIS_CLEAR", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(fresh.resolved_value.as_str(), "new");
+    assert_eq!(counter.0.load(Ordering::SeqCst), 1);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn test_on_change_forwards_events() {
+    use abundantis::AbundantisEvent;
+
+    let dir = workspace_with(&[(".env", "ABUNDANTIS_ON_CHANGE=1\n")]);
+    let abundantis = build(dir.path()).await;
+    let mut changes = abundantis.on_change();
+
+    abundantis
+        .refresh(abundantis::RefreshOptions::reset_all())
+        .await
+        .unwrap();
+
+    let event = tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        loop {
+            match changes.recv().await {
+                Some(event @ AbundantisEvent::CacheInvalidated { .. }) => return event,
+                Some(_) => continue,
+                None => panic!("channel closed"),
+            }
+        }
+    })
+    .await
+    .expect("no CacheInvalidated event");
+    assert!(matches!(
+        event,
+        AbundantisEvent::CacheInvalidated { scope: None }
+    ));
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_export_for_shell_quotes_values() {
+    let dir = workspace_with(&[
+        (".env", "ABUNDANTIS_EXPORT=\"it's here\"\n"),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+    let file = dir.path().join("main.rs");
+
+    let bash = abundantis
+        .export_for_shell(&file, abundantis::ShellKind::Bash)
+        .await
+        .unwrap();
+    assert!(bash.contains("export ABUNDANTIS_EXPORT='it'\\''s here'\n"));
+
+    let powershell = abundantis
+        .export_for_shell(&file, abundantis::ShellKind::PowerShell)
+        .await
+        .unwrap();
+    assert!(powershell.contains("$env:ABUNDANTIS_EXPORT='it''s here'\n"));
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_typed_accessors() {
+    let dir = workspace_with(&[
+        (
+            ".env",
+            "ABUNDANTIS_TYPED_DEBUG=yes\nABUNDANTIS_TYPED_TIMEOUT=30s\nABUNDANTIS_TYPED_HOSTS=a,b,c\nABUNDANTIS_TYPED_PORT=8080\nABUNDANTIS_TYPED_BAD=maybe\n",
+        ),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+    let file = dir.path().join("main.rs");
+
+    let debug = abundantis.get_bool("ABUNDANTIS_TYPED_DEBUG", &file).await;
+    assert_eq!(debug.unwrap(), Some(true));
+
+    let timeout = abundantis
+        .get_duration("ABUNDANTIS_TYPED_TIMEOUT", &file)
+        .await;
+    assert_eq!(timeout.unwrap(), Some(std::time::Duration::from_secs(30)));
+
+    let hosts = abundantis
+        .get_list("ABUNDANTIS_TYPED_HOSTS", ",", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(hosts, vec!["a", "b", "c"]);
+
+    let port = abundantis
+        .get_as::<u16>("ABUNDANTIS_TYPED_PORT", &file)
+        .await;
+    assert_eq!(port.unwrap(), Some(8080));
+
+    let bad = abundantis.get_bool("ABUNDANTIS_TYPED_BAD", &file).await;
+    assert!(matches!(
+        bad,
+        Err(abundantis::AbundantisError::InvalidValue { .. })
+    ));
+
+    let missing = abundantis.get_bool("ABUNDANTIS_TYPED_MISSING", &file).await;
+    assert_eq!(missing.unwrap(), None);
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_active_files_for_package_includes_root_files() {
+    let dir = workspace_with(&[
+        ("pnpm-workspace.yaml", "packages:\n  - apps/*\n"),
+        ("package.json", r#"{"name": "root"}"#),
+        (".env", "ABUNDANTIS_ACTIVE_PKG=root\n"),
+        ("apps/web/package.json", r#"{"name": "web"}"#),
+        ("apps/web/.env", "ABUNDANTIS_ACTIVE_PKG=web\n"),
+        ("apps/web/.env.local", "ABUNDANTIS_ACTIVE_PKG=web-local\n"),
+        ("apps/api/package.json", r#"{"name": "api"}"#),
+        ("apps/api/.env", "ABUNDANTIS_ACTIVE_PKG=api\n"),
+    ]);
+    let abundantis = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Pnpm)
+        .build()
+        .await
+        .unwrap();
+    let root = dir.path().canonicalize().unwrap();
+
+    assert_eq!(
+        abundantis.active_files_for_package("web"),
+        vec![
+            root.join(".env"),
+            root.join("apps/web/.env"),
+            root.join("apps/web/.env.local"),
+        ]
+    );
+    assert_eq!(
+        abundantis.active_files_for_package("apps/api"),
+        vec![root.join(".env"), root.join("apps/api/.env")]
+    );
+    assert!(abundantis.active_files_for_package("nope").is_empty());
+}
+
+#[cfg(feature = "file")]
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_refresh_reports_added_sources() {
+    let dir = workspace_with(&[
+        (".env", "ABUNDANTIS_REFRESH_REPORT=base\n"),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+    let file = dir.path().join("main.rs");
+    abundantis
+        .get_for_file("ABUNDANTIS_REFRESH_REPORT", &file)
+        .await
+        .unwrap();
+
+    let local = dir.path().join(".env.local");
+    std::fs::write(&local, "ABUNDANTIS_REFRESH_REPORT=local\n").unwrap();
+    let report = abundantis
+        .refresh(abundantis::RefreshOptions::reset_all())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        report.sources_added,
+        vec![abundantis::SourceId::canonical(
+            abundantis::SourceType::File,
+            local.canonicalize().unwrap().display(),
+        )]
+    );
+    assert!(report.sources_removed.is_empty());
+    assert_eq!(report.cache_entries_cleared, 1);
+}
+
+#[cfg(feature = "file")]
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_config_file_registers_json_by_extension() {
+    let dir = workspace_with(&[
+        (
+            "config.json",
+            r#"{"abundantis_json": {"db": {"url": "postgres://x"}, "hosts": ["a", "b"]}}"#,
+        ),
+        ("config.yaml", "key: value\n"),
+        ("main.rs", ""),
+    ]);
+    let file = dir.path().join("main.rs");
+    let abundantis = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Custom)
+        .roots(vec!["."])
+        .config_file("config.json")
+        .build()
+        .await
+        .unwrap();
+
+    for (key, expected) in [
+        ("ABUNDANTIS_JSON_DB_URL", "postgres://x"),
+        ("ABUNDANTIS_JSON_HOSTS_0", "a"),
+        ("ABUNDANTIS_JSON_HOSTS_1", "b"),
+    ] {
+        let value = abundantis.get_for_file(key, &file).await.unwrap().unwrap();
+        assert_eq!(value.resolved_value, expected);
+    }
+
+    let unsupported = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Custom)
+        .roots(vec!["."])
+        .config_file("config.yaml")
+        .build()
+        .await;
+    assert!(matches!(
+        unsupported,
+        Err(abundantis::AbundantisError::Config { .. })
+    ));
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_package_for_file() {
+    let dir = workspace_with(&[
+        ("pnpm-workspace.yaml", "packages:\n  - apps/*\n"),
+        ("package.json", r#"{"name": "root"}"#),
+        ("apps/web/package.json", r#"{"name": "web"}"#),
+        ("apps/web/src/index.js", ""),
+        ("apps/api/package.json", r#"{"name": "api"}"#),
+    ]);
+    let outside = workspace_with(&[("main.rs", "")]);
+    let abundantis = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Pnpm)
+        .build()
+        .await
+        .unwrap();
+
+    let package = abundantis
+        .package_for_file(&dir.path().join("apps/web/src/index.js"))
+        .unwrap();
+    assert_eq!(package.name.as_deref(), Some("web"));
+    assert_eq!(package.relative_path, "apps/web");
+
+    assert_eq!(
+        abundantis.package_for_file(&outside.path().join("main.rs")),
+        None
+    );
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_get_for_file_picks_up_packages_added_to_config() {
+    let dir = workspace_with(&[
+        ("pnpm-workspace.yaml", "packages:\n  - apps/*\n"),
+        ("package.json", r#"{"name": "root"}"#),
+        ("apps/web/package.json", r#"{"name": "web"}"#),
+        ("libs/ui/package.json", r#"{"name": "ui"}"#),
+        ("libs/ui/.env", "ABUNDANTIS_UI_KEY=ui\n"),
+        ("libs/ui/src/index.js", ""),
+    ]);
+    let abundantis = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Pnpm)
+        .watch(false)
+        .build()
+        .await
+        .unwrap();
+    let file = dir.path().join("libs/ui/src/index.js");
+    assert!(abundantis.package_for_file(&file).is_none());
+
+    std::fs::write(
+        dir.path().join("pnpm-workspace.yaml"),
+        "packages:\n  - apps/*\n  - libs/*\n",
+    )
+    .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(600));
+
+    let package = abundantis.package_for_file(&file).unwrap();
+    assert_eq!(package.relative_path, "libs/ui");
+    let resolved = abundantis
+        .get_for_file("ABUNDANTIS_UI_KEY", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(resolved.resolved_value, "ui");
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_conflict_resolver_picks_among_equal_sources() {
+    use abundantis::BytesSource;
+    use std::sync::{Arc, Mutex};
+
+    let dir = workspace_with(&[("main.rs", "")]);
+    let file = dir.path().join("main.rs");
+    let build_with = |resolver: Option<abundantis::ConflictResolver>| {
+        let mut builder = Abundantis::builder()
+            .root(dir.path())
+            .provider(MonorepoProviderType::Custom)
+            .roots(vec!["."])
+            .with_source(Arc::new(BytesSource::new(
+                "first",
+                b"ABUNDANTIS_CONFLICT=one\n",
+            )))
+            .with_source(Arc::new(BytesSource::new(
+                "second",
+                b"ABUNDANTIS_CONFLICT=two\n",
+            )));
+        if let Some(resolver) = resolver {
+            builder = builder.conflict_resolver(resolver);
+        }
+        builder
+    };
+
+    let default = build_with(None).build().await.unwrap();
+    let value = default
+        .get_for_file("ABUNDANTIS_CONFLICT", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(value.resolved_value.as_str(), "two");
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&seen);
+    let resolver: abundantis::ConflictResolver = Arc::new(move |key, candidates| {
+        recorded.lock().unwrap().push((
+            key.to_string(),
+            candidates
+                .iter()
+                .map(|(id, value)| format!("{}={}", id, value))
+                .collect::<Vec<_>>(),
+        ));
+        0
+    });
+    let resolved = build_with(Some(resolver)).build().await.unwrap();
+    let value = resolved
+        .get_for_file("ABUNDANTIS_CONFLICT", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(value.resolved_value.as_str(), "one");
+    assert_eq!(
+        seen.lock().unwrap()[0],
+        (
+            "ABUNDANTIS_CONFLICT".to_string(),
+            vec![
+                "memory:first=one".to_string(),
+                "memory:second=two".to_string()
+            ]
+        )
+    );
+}
+
+#[cfg(feature = "shell")]
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_get_with_source_reports_origin() {
+    std::env::set_var("ABUNDANTIS_ORIGIN_SHELL", "from-shell");
+    let dir = workspace_with(&[
+        (".env", "ABUNDANTIS_ORIGIN_FILE=from-file\n"),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+    let file = dir.path().join("main.rs");
+
+    let (value, source) = abundantis
+        .get_with_source("ABUNDANTIS_ORIGIN_FILE", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(value, "from-file");
+    assert_eq!(
+        source.file_path().and_then(|p| p.file_name()),
+        Some(std::ffi::OsStr::new(".env"))
+    );
+
+    let (value, source) = abundantis
+        .get_with_source("ABUNDANTIS_ORIGIN_SHELL", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(value, "from-shell");
+    assert_eq!(source, abundantis::VariableSource::Shell);
+
+    let missing = abundantis
+        .get_with_source("ABUNDANTIS_ORIGIN_MISSING", &file)
+        .await
+        .unwrap();
+    assert!(missing.is_none());
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_cache_stats_reports_both_caches() {
+    let dir = workspace_with(&[(".env", "ABUNDANTIS_STATS_KEY=value\n"), ("main.rs", "")]);
+    let abundantis = build(dir.path()).await;
+    let file = dir.path().join("main.rs");
+
+    for _ in 0..3 {
+        let value = abundantis
+            .get_for_file("ABUNDANTIS_STATS_KEY", &file)
+            .await
+            .unwrap();
+        assert_eq!(value.unwrap().resolved_value, "value");
+    }
+
+    let report = abundantis.cache_stats();
+    assert!(report.resolution.misses >= 1);
+    assert!(report.resolution.hits >= 2);
+    assert!(report.resolution.hit_rate() > 0.5);
+    assert!(report.path.hits >= 1);
+    assert!(report.path.hit_rate() > 0.0 && report.path.hit_rate() < 1.0);
+
+    abundantis.clear_cache();
+    assert_eq!(abundantis.cache_stats().resolution, report.resolution);
+}
+
+#[cfg(all(feature = "watch", feature = "async"))]
+#[tokio::test]
+async fn test_watch_and_stream_yields_changed_keys() {
+    use futures::StreamExt;
+
+    let dir = workspace_with(&[
+        (
+            ".env",
+            "ABUNDANTIS_STREAM_KEY=one\nABUNDANTIS_STREAM_SAME=same\n",
+        ),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+    let file = dir.path().join("main.rs");
+
+    let stream = abundantis.watch_and_stream(&file);
+    futures::pin_mut!(stream);
+
+    let env_path = dir.path().join(".env");
+    let (diff, _) = tokio::join!(
+        tokio::time::timeout(std::time::Duration::from_secs(10), stream.next()),
+        async {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            std::fs::write(
+                &env_path,
+                "ABUNDANTIS_STREAM_KEY=two\nABUNDANTIS_STREAM_SAME=same\n",
+            )
+            .unwrap();
+        }
+    );
+    let diff = diff.expect("no change streamed").unwrap();
+
+    assert_eq!(
+        diff.keys().collect::<Vec<_>>(),
+        vec!["ABUNDANTIS_STREAM_KEY"]
+    );
+    let (before, after) = &diff.changed[0];
+    assert_eq!(before.resolved_value, "one");
+    assert_eq!(after.resolved_value, "two");
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_package_dirs_uses_listed_directories_only() {
+    let dir = workspace_with(&[
+        ("svc-a/.env", "ABUNDANTIS_DIRS=a\n"),
+        ("svc-b/.env", "ABUNDANTIS_DIRS=b\n"),
+        ("svc-c/.env", "ABUNDANTIS_DIRS=c\n"),
+        ("svc-c/nested/.env", "ABUNDANTIS_DIRS=nested\n"),
+    ]);
+    let abundantis = Abundantis::builder()
+        .root(dir.path())
+        .package_dirs(vec!["svc-a", "./svc-b/"])
+        .build()
+        .await
+        .unwrap();
+
+    let mut packages: Vec<_> = abundantis
+        .workspace
+        .read()
+        .packages()
+        .into_iter()
+        .map(|p| p.relative_path.to_string())
+        .collect();
+    packages.sort();
+    assert_eq!(packages, vec!["svc-a", "svc-b"]);
+}
+
+#[cfg(feature = "shell")]
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_get_for_file_with_precedence_excludes_shell() {
+    use abundantis::config::SourcePrecedence;
+
+    std::env::set_var("ABUNDANTIS_SCOPED_PRECEDENCE", "shell");
+    let dir = workspace_with(&[
+        (".env", "ABUNDANTIS_SCOPED_PRECEDENCE=file\n"),
+        ("main.rs", ""),
+    ]);
+    let file = dir.path().join("main.rs");
+    let abundantis = build(dir.path()).await;
+
+    let before = abundantis
+        .get_for_file("ABUNDANTIS_SCOPED_PRECEDENCE", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(before.resolved_value, "shell");
+
+    let scoped = abundantis
+        .get_for_file_with_precedence(
+            "ABUNDANTIS_SCOPED_PRECEDENCE",
+            &file,
+            &[SourcePrecedence::File],
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(scoped.resolved_value, "file");
+
+    let after = abundantis
+        .get_for_file("ABUNDANTIS_SCOPED_PRECEDENCE", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(after.resolved_value, "shell");
+    assert_eq!(
+        abundantis.config().resolution.precedence,
+        vec![SourcePrecedence::Shell, SourcePrecedence::File]
+    );
+}
+
+#[cfg(feature = "shell")]
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_get_for_file_with_precedence_interpolates_without_shell() {
+    use abundantis::config::SourcePrecedence;
+
+    std::env::set_var("ABUNDANTIS_SCOPED_HOST", "shell-host");
+    let dir = workspace_with(&[
+        (
+            ".env",
+            "ABUNDANTIS_SCOPED_HOST=file-host\nABUNDANTIS_SCOPED_URL=http://${ABUNDANTIS_SCOPED_HOST}\n",
+        ),
+        ("main.rs", ""),
+    ]);
+    let file = dir.path().join("main.rs");
+    let abundantis = build(dir.path()).await;
+
+    let scoped = abundantis
+        .get_for_file_with_precedence("ABUNDANTIS_SCOPED_URL", &file, &[SourcePrecedence::File])
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(scoped.resolved_value, "http://file-host");
+}
+
+#[cfg(feature = "shell")]
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_precedence_order_decides_conflicts() {
+    use abundantis::config::SourcePrecedence;
+
+    std::env::set_var("ABUNDANTIS_PRECEDENCE", "shell");
+    let dir = workspace_with(&[(".env", "ABUNDANTIS_PRECEDENCE=file\n"), ("main.rs", "")]);
+    let file = dir.path().join("main.rs");
+
+    let default_order = build(dir.path()).await;
+    let value = default_order
+        .get_for_file("ABUNDANTIS_PRECEDENCE", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(value.resolved_value, "shell");
+
+    let file_first = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Custom)
+        .roots(vec!["."])
+        .precedence(vec![SourcePrecedence::File, SourcePrecedence::Shell])
+        .build()
+        .await
+        .unwrap();
+    let value = file_first
+        .get_for_file("ABUNDANTIS_PRECEDENCE", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(value.resolved_value, "file");
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_describe_collects_variable_details() {
+    let dir = workspace_with(&[
+        (
+            ".env",
+            "ABUNDANTIS_DESCRIBE_HOST=localhost\nABUNDANTIS_DESCRIBE_URL=http://${ABUNDANTIS_DESCRIBE_HOST}\nABUNDANTIS_DESCRIBE_DUP=a\nABUNDANTIS_DESCRIBE_DUP=b\n",
+        ),
+        (
+            ".env.example",
+            "# Where the API lives\nABUNDANTIS_DESCRIBE_URL=\n",
+        ),
+        ("main.rs", ""),
+    ]);
+    let abundantis = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Custom)
+        .roots(vec!["."])
+        .schema_file(".env.example")
+        .build()
+        .await
+        .unwrap();
+    let file = dir.path().join("main.rs");
+
+    let info = abundantis
+        .describe("ABUNDANTIS_DESCRIBE_URL", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(info.key, "ABUNDANTIS_DESCRIBE_URL");
+    assert_eq!(info.resolved_value, "http://localhost");
+    assert_eq!(info.raw_value, "http://${ABUNDANTIS_DESCRIBE_HOST}");
+    assert_eq!(info.description.as_deref(), Some("Where the API lives"));
+    assert!(info.source.file_path().unwrap().ends_with(".env"));
+    assert!(info.diagnostics.is_empty());
+
+    let duplicate = abundantis
+        .describe("ABUNDANTIS_DESCRIBE_DUP", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(duplicate.diagnostics.len(), 1);
+    assert_eq!(duplicate.diagnostics[0].code, DiagnosticCode::EDF002);
+
+    let prefixed_dir = workspace_with(&[
+        (
+            ".env",
+            "#!abundantis prefix=ABUNDANTIS_DESCRIBE_\nPREFIXED=a\nPREFIXED=b\n",
+        ),
+        ("main.rs", ""),
+    ]);
+    let prefixed = build(prefixed_dir.path())
+        .await
+        .describe(
+            "ABUNDANTIS_DESCRIBE_PREFIXED",
+            &prefixed_dir.path().join("main.rs"),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(prefixed.raw_value, "b");
+    assert_eq!(prefixed.diagnostics.len(), 1);
+    assert_eq!(prefixed.diagnostics[0].code, DiagnosticCode::EDF002);
+    assert_eq!(prefixed.diagnostics[0].line, 3);
+
+    let missing = abundantis
+        .describe("ABUNDANTIS_DESCRIBE_MISSING", &file)
+        .await
+        .unwrap();
+    assert!(missing.is_none());
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_max_sources_rejects_too_many_env_files() {
+    let dir = workspace_with(&[
+        (".env.a", "A=1\n"),
+        (".env.b", "B=1\n"),
+        (".env.c", "C=1\n"),
+    ]);
+    let result = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Custom)
+        .roots(vec!["."])
+        .env_files(vec![".env.*"])
+        .source_defaults(SourceDefaults {
+            max_sources: Some(2),
+            ..Default::default()
+        })
+        .build()
+        .await;
+
+    match result {
+        Err(AbundantisError::Config { message, .. }) => {
+            assert!(message.contains(".env.*"), "{}", message);
+            assert!(message.contains("max_sources"), "{}", message);
+        }
+        Err(e) => panic!("unexpected error: {}", e),
+        Ok(_) => panic!("expected the source cap to be enforced"),
+    }
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_diagnostics_json_reports_undefined_references() {
+    let dir = workspace_with(&[
+        (
+            ".env",
+            "ABUNDANTIS_JSON_HOST=localhost\n\
+             ABUNDANTIS_JSON_URL=http://${ABUNDANTIS_JSON_HOST}/${ABUNDANTIS_JSON_MISSING}\n\
+             ABUNDANTIS_JSON_PORT=${ABUNDANTIS_JSON_UNSET:-8080}\n",
+        ),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+
+    let json = abundantis
+        .diagnostics_json(&dir.path().join("main.rs"))
+        .unwrap();
+    let entries: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(entries.len(), 1, "{}", json);
+    let entry = &entries[0];
+    assert_eq!(entry["code"], "RES001");
+    assert_eq!(entry["file"], ".env");
+    assert_eq!(entry["line"], 2);
+    assert_eq!(entry["col"], 1);
+    assert_eq!(entry["severity"], "warning");
+    assert!(entry["message"]
+        .as_str()
+        .unwrap()
+        .contains("ABUNDANTIS_JSON_MISSING"));
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_validate_file_reports_each_undefined_reference_once() {
+    let dir = workspace_with(&[
+        (
+            ".env",
+            "ABUNDANTIS_ONCE_URL=${ABUNDANTIS_ONCE_MISSING}/${ABUNDANTIS_ONCE_MISSING}
+",
+        ),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+
+    let diagnostics = abundantis
+        .validate_file(&dir.path().join("main.rs"))
+        .unwrap();
+    let undefined: Vec<_> = diagnostics
+        .iter()
+        .filter(|d| d.code == DiagnosticCode::RES001)
+        .collect();
+
+    assert_eq!(undefined.len(), 1, "{:?}", diagnostics);
+    assert!(undefined[0].message.contains("ABUNDANTIS_ONCE_MISSING"));
+}
+
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_overlay_overrides_file_values_for_one_lookup() {
+    let dir = workspace_with(&[
+        (
+            ".env",
+            "ABUNDANTIS_OVERLAY_TENANT=default\n\
+             ABUNDANTIS_OVERLAY_URL=https://${ABUNDANTIS_OVERLAY_TENANT}.example.com\n",
+        ),
+        ("main.rs", ""),
+    ]);
+    let abundantis = build(dir.path()).await;
+    let file = dir.path().join("main.rs");
+    let overlay = HashMap::from([("ABUNDANTIS_OVERLAY_TENANT".into(), "acme".into())]);
+
+    let value = |variable: Option<std::sync::Arc<abundantis::ResolvedVariable>>| {
+        variable.unwrap().resolved_value.to_string()
+    };
+    let tenant = abundantis
+        .get_for_file_with_overlay("ABUNDANTIS_OVERLAY_TENANT", &file, &overlay)
+        .await
+        .unwrap();
+    assert_eq!(value(tenant), "acme");
+    let url = abundantis
+        .get_for_file_with_overlay("ABUNDANTIS_OVERLAY_URL", &file, &overlay)
+        .await
+        .unwrap();
+    assert_eq!(value(url), "https://acme.example.com");
+
+    let url = abundantis
+        .get_for_file("ABUNDANTIS_OVERLAY_URL", &file)
+        .await
+        .unwrap();
+    assert_eq!(value(url), "https://default.example.com");
+}
+
+#[cfg(feature = "git")]
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_git_source_resolves_for_files() {
+    use std::sync::Arc;
+
+    let dir = workspace_with(&[
+        (".env", "LEGACY_URL=https://old.example.com\n"),
+        ("main.rs", ""),
+    ]);
+    let repo = git2::Repository::init(dir.path()).unwrap();
+    let mut index = repo.index().unwrap();
+    index.add_path(Path::new(".env")).unwrap();
+    let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+    let signature = git2::Signature::now("test", "test@example.com").unwrap();
+    repo.commit(Some("HEAD"), &signature, &signature, "first", &tree, &[])
+        .unwrap();
+    std::fs::write(dir.path().join(".env"), "URL=https://new.example.com\n").unwrap();
+
+    let abundantis = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Custom)
+        .roots(vec!["."])
+        .with_source(Arc::new(
+            abundantis::GitSource::new(dir.path(), "HEAD", ".env").unwrap(),
+        ))
+        .build()
+        .await
+        .unwrap();
+
+    let file = dir.path().join("main.rs");
+    let legacy = abundantis
+        .get_for_file("LEGACY_URL", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(legacy.resolved_value, "https://old.example.com");
+    let current = abundantis
+        .get_for_file("URL", &file)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(current.resolved_value, "https://new.example.com");
+}
+
+#[cfg(feature = "file")]
+#[cfg_attr(feature = "async", maybe_async::must_be_async, tokio::test)]
+#[cfg_attr(not(feature = "async"), maybe_async::must_be_sync, test)]
+async fn test_max_sources_leaves_workspace_unchanged_on_failure() {
+    let dir = workspace_with(&[(".env.a", "A=1\n")]);
+    let crowded = workspace_with(&[
+        (".env.a", "A=1\n"),
+        (".env.b", "B=1\n"),
+        (".env.c", "C=1\n"),
+    ]);
+    let abundantis = Abundantis::builder()
+        .root(dir.path())
+        .provider(MonorepoProviderType::Custom)
+        .roots(vec!["."])
+        .env_files(vec![".env.*"])
+        .source_defaults(SourceDefaults {
+            max_sources: Some(2),
+            ..Default::default()
+        })
+        .watch(false)
+        .build()
+        .await
+        .unwrap();
+    let root = abundantis.workspace.read().root().to_path_buf();
+    let sources = abundantis.registry.source_count();
+
+    let moved = abundantis.set_root(crowded.path()).await;
+    assert!(moved.is_err());
+    assert_eq!(abundantis.workspace.read().root(), root);
+    assert_eq!(abundantis.registry.source_count(), sources);
+
+    std::fs::write(dir.path().join(".env.b"), "B=1\n").unwrap();
+    std::fs::write(dir.path().join(".env.c"), "C=1\n").unwrap();
+    let refreshed = abundantis
+        .refresh(abundantis::RefreshOptions::reset_all())
+        .await;
+    assert!(refreshed.is_err());
+    assert_eq!(abundantis.registry.source_count(), sources);
+}
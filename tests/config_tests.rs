@@ -95,9 +95,10 @@ fn test_monorepo_provider_types() {
         MonorepoProviderType::Yarn,
         MonorepoProviderType::Cargo,
         MonorepoProviderType::Custom,
+        MonorepoProviderType::Directories,
     ];
 
-    assert_eq!(providers.len(), 8);
+    assert_eq!(providers.len(), 9);
 }
 
 #[test]
@@ -240,12 +241,14 @@ fn test_interpolation_features_all_enabled() {
         alternates: true,
         recursion: true,
         commands: true,
+        cross_package: true,
     };
 
     assert!(config.defaults);
     assert!(config.alternates);
     assert!(config.recursion);
     assert!(config.commands);
+    assert!(config.cross_package);
 }
 
 #[test]
@@ -300,8 +303,9 @@ fn test_monorepo_provider_type_hash() {
     set.insert(MonorepoProviderType::Yarn);
     set.insert(MonorepoProviderType::Cargo);
     set.insert(MonorepoProviderType::Custom);
+    set.insert(MonorepoProviderType::Directories);
 
-    assert_eq!(set.len(), 8);
+    assert_eq!(set.len(), 9);
 }
 
 #[test]
@@ -368,12 +372,14 @@ fn test_interpolation_features_all_disabled() {
         alternates: false,
         recursion: false,
         commands: false,
+        cross_package: false,
     };
 
     assert!(!config.defaults);
     assert!(!config.alternates);
     assert!(!config.recursion);
     assert!(!config.commands);
+    assert!(!config.cross_package);
 }
 
 #[test]
@@ -446,3 +452,29 @@ fn test_config_default_all_modules() {
     assert!(config.interpolation.enabled);
     assert!(config.cache.enabled);
 }
+
+#[test]
+fn test_config_toml_round_trip() {
+    let mut config = AbundantisConfig::default();
+    config.workspace.provider = Some(MonorepoProviderType::Pnpm);
+    config.cache.ttl = std::time::Duration::from_secs(90);
+    config.resolution.precedence = vec![SourcePrecedence::File, SourcePrecedence::Shell];
+
+    let serialized = config.to_toml().unwrap();
+    assert!(serialized.contains("ttl = \"1m 30s\""));
+
+    let parsed = AbundantisConfig::from_toml(&serialized).unwrap();
+    assert_eq!(parsed, config);
+    assert_eq!(parsed.cache.ttl, std::time::Duration::from_secs(90));
+    assert_eq!(
+        parsed.resolution.precedence,
+        vec![SourcePrecedence::File, SourcePrecedence::Shell]
+    );
+    assert_eq!(parsed.workspace.provider, Some(MonorepoProviderType::Pnpm));
+}
+
+#[test]
+fn test_config_from_invalid_toml_is_config_error() {
+    let err = AbundantisConfig::from_toml("cache = 5").unwrap_err();
+    assert!(matches!(err, abundantis::AbundantisError::Config { .. }));
+}
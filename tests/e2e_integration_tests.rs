@@ -55,6 +55,7 @@ fn test_resolution_cache() {
         enabled: true,
         hot_cache_size: 10,
         ttl: std::time::Duration::from_secs(60),
+        remote_ttl: None,
     };
 
     let cache = ResolutionCache::new(&config);
@@ -230,6 +231,8 @@ fn test_diagnostic() {
         path: PathBuf::from("/test.env"),
         line: 10,
         column: 5,
+        snippet: None,
+        label: None,
     };
 
     assert_eq!(diagnostic.severity, DiagnosticSeverity::Error);
@@ -284,10 +287,14 @@ fn test_abundantis_stats() {
     let stats = abundantis::AbundantisStats {
         cached_variables: 100,
         source_count: 5,
+        cache_hits: 40,
+        cache_misses: 10,
     };
 
     assert_eq!(stats.cached_variables, 100);
     assert_eq!(stats.source_count, 5);
+    assert_eq!(stats.cache_hits, 40);
+    assert_eq!(stats.cache_misses, 10);
 }
 
 #[test]
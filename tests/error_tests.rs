@@ -129,11 +129,13 @@ fn test_error_max_depth_exceeded() {
     let err = AbundantisError::MaxDepthExceeded {
         key: "RECURSIVE_VAR".to_string(),
         depth: 100,
+        chain: "OUTER -> RECURSIVE_VAR".to_string(),
     };
 
     let display = format!("{}", err);
     assert!(display.contains("RECURSIVE_VAR"));
     assert!(display.contains("100"));
+    assert!(display.contains("OUTER -> RECURSIVE_VAR"));
     assert!(display.contains("Max interpolation depth"));
 }
 
@@ -235,6 +237,8 @@ fn test_diagnostic_creation() {
         path: PathBuf::from("/.env"),
         line: 10,
         column: 5,
+        snippet: None,
+        label: None,
     };
 
     assert_eq!(diagnostic.severity, DiagnosticSeverity::Error);
@@ -297,6 +301,8 @@ fn test_diagnostic_clone() {
         path: PathBuf::from("/.env"),
         line: 5,
         column: 2,
+        snippet: None,
+        label: None,
     };
 
     let diag2 = diag1.clone();
@@ -318,6 +324,8 @@ fn test_diagnostic_equality() {
         path: PathBuf::from("/.env"),
         line: 10,
         column: 5,
+        snippet: None,
+        label: None,
     };
 
     let diag2 = Diagnostic {
@@ -327,6 +335,8 @@ fn test_diagnostic_equality() {
         path: PathBuf::from("/.env"),
         line: 10,
         column: 5,
+        snippet: None,
+        label: None,
     };
 
     assert_eq!(diag1, diag2);
@@ -341,6 +351,8 @@ fn test_diagnostic_inequality() {
         path: PathBuf::from("/.env"),
         line: 10,
         column: 5,
+        snippet: None,
+        label: None,
     };
 
     let diag2 = Diagnostic {
@@ -350,6 +362,8 @@ fn test_diagnostic_inequality() {
         path: PathBuf::from("/.env"),
         line: 10,
         column: 5,
+        snippet: None,
+        label: None,
     };
 
     assert_ne!(diag1, diag2);
@@ -453,6 +467,70 @@ fn test_abundantis_error_debug() {
     assert!(debug_str.contains("/config.toml"));
 }
 
+#[test]
+fn test_diagnostic_render_points_at_column() {
+    let diagnostic = Diagnostic {
+        severity: DiagnosticSeverity::Error,
+        code: DiagnosticCode::EDF001,
+        message: "Expected `KEY=VALUE`, found `PORT 3000`".to_string(),
+        path: PathBuf::from(".env"),
+        line: 12,
+        column: 3,
+        snippet: Some("  PORT 3000".to_string()),
+        label: Some("expected `KEY=VALUE`".to_string()),
+    };
+
+    let rendered = diagnostic.render();
+    assert_eq!(
+        rendered,
+        "error[EDF001]: Expected `KEY=VALUE`, found `PORT 3000`\n   \
+         --> .env:12:3\n   \
+         |\n\
+         12 |   PORT 3000\n   \
+         |   ^ expected `KEY=VALUE`\n"
+    );
+}
+
+#[test]
+fn test_diagnostic_render_counts_characters_before_column() {
+    let diagnostic = Diagnostic {
+        severity: DiagnosticSeverity::Error,
+        code: DiagnosticCode::EDF001,
+        message: "Expected `KEY=VALUE`, found `ünïcødé oops`".to_string(),
+        path: PathBuf::from(".env"),
+        line: 2,
+        column: 9,
+        snippet: Some("ünïcødé oops".to_string()),
+        label: None,
+    };
+
+    let rendered = diagnostic.render();
+    assert!(
+        rendered.ends_with("2 | ünïcødé oops\n  |         ^\n"),
+        "{}",
+        rendered
+    );
+}
+
+#[test]
+fn test_diagnostic_render_without_snippet() {
+    let diagnostic = Diagnostic {
+        severity: DiagnosticSeverity::Warning,
+        code: DiagnosticCode::EDF002,
+        message: "Duplicate key".to_string(),
+        path: PathBuf::from(".env"),
+        line: 4,
+        column: 1,
+        snippet: None,
+        label: None,
+    };
+
+    assert_eq!(
+        diagnostic.render(),
+        "warning[EDF002]: Duplicate key\n  --> .env:4:1\n"
+    );
+}
+
 #[test]
 fn test_diagnostic_with_empty_path() {
     let diagnostic = Diagnostic {
@@ -462,6 +540,8 @@ fn test_diagnostic_with_empty_path() {
         path: PathBuf::new(),
         line: 0,
         column: 0,
+        snippet: None,
+        label: None,
     };
 
     assert!(diagnostic.path.as_os_str().is_empty());
@@ -476,6 +556,8 @@ fn test_diagnostic_with_large_line_column() {
         path: PathBuf::from("/.env"),
         line: 999999,
         column: 999999,
+        snippet: None,
+        label: None,
     };
 
     assert_eq!(diagnostic.line, 999999);
@@ -529,6 +611,7 @@ fn test_abundantis_error_all_variants() {
         AbundantisError::MaxDepthExceeded {
             key: "test".to_string(),
             depth: 0,
+            chain: "test".to_string(),
         },
         AbundantisError::UndefinedVariable {
             key: "test".to_string(),
@@ -570,3 +653,51 @@ fn test_source_error_all_variants() {
 
     assert_eq!(errors.len(), 6);
 }
+
+#[test]
+fn test_source_error_from_remote_io_errors() {
+    let timeout = std::io::Error::new(std::io::ErrorKind::TimedOut, "deadline elapsed");
+    assert!(matches!(
+        SourceError::from_remote("vault", timeout),
+        SourceError::Timeout { source_name } if source_name == "vault"
+    ));
+
+    let refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+    assert!(matches!(
+        SourceError::from_remote("vault", refused),
+        SourceError::Connection { provider, .. } if provider == "vault"
+    ));
+
+    let other = std::fmt::Error;
+    assert!(matches!(
+        SourceError::from_remote("vault", other),
+        SourceError::Remote { provider, .. } if provider == "vault"
+    ));
+}
+
+#[cfg(feature = "remote")]
+#[tokio::test]
+async fn test_source_error_from_reqwest_timeout() {
+    // Accepts the connection but never answers.
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let _server = tokio::spawn(async move {
+        let (_socket, _) = listener.accept().await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_secs(10)).await;
+    });
+
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_millis(50))
+        .build()
+        .unwrap();
+    let err = client
+        .get(format!("http://{}/secrets", address))
+        .send()
+        .await
+        .unwrap_err();
+
+    assert!(matches!(
+        SourceError::from(err),
+        SourceError::Timeout { source_name } if source_name == "127.0.0.1"
+    ));
+}
@@ -27,9 +27,10 @@ fn test_monorepo_provider_type_values() {
         MonorepoProviderType::Yarn,
         MonorepoProviderType::Cargo,
         MonorepoProviderType::Custom,
+        MonorepoProviderType::Directories,
     ];
 
-    assert_eq!(providers.len(), 8);
+    assert_eq!(providers.len(), 9);
 }
 
 #[test]
@@ -424,7 +425,7 @@ fn test_memory_source_id_consistency() {
     let id2 = source.id();
 
     assert_eq!(id1, id2);
-    assert_eq!(id1.as_str(), "memory");
+    assert_eq!(id1.as_str(), "memory:default");
 }
 
 #[test]
@@ -452,3 +453,44 @@ fn test_memory_source_capabilities_check() {
     assert!(caps.contains(abundantis::source::SourceCapabilities::CACHEABLE));
     assert!(!caps.contains(abundantis::source::SourceCapabilities::WATCH));
 }
+
+#[cfg(all(feature = "watch", feature = "async"))]
+#[tokio::test]
+async fn test_watched_file_write_fires_callback() {
+    use abundantis::watch::{ChangeKind, FileWatcher};
+    use std::sync::{Arc, Mutex};
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let file = dir.path().join(".env");
+    std::fs::write(&file, "KEY=old\n").unwrap();
+
+    let watcher = FileWatcher::new().unwrap();
+    watcher.watch(&file, "file:.env");
+    let changes = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&changes);
+    watcher.register_callback(Arc::new(move |change| sink.lock().unwrap().push(change)));
+
+    std::fs::write(&file, "KEY=new\n").unwrap();
+
+    tokio::time::timeout(std::time::Duration::from_secs(5), async {
+        loop {
+            let fired = changes
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|change| change.kind == ChangeKind::Modified);
+            if fired {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .expect("writing to a watched file did not fire the callback");
+
+    drop(watcher);
+    let fired = changes.lock().unwrap().len();
+    std::fs::write(&file, "KEY=newer\n").unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    assert_eq!(changes.lock().unwrap().len(), fired);
+}
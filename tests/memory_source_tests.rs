@@ -3,7 +3,7 @@ use abundantis::source::{EnvSource, MemorySource, Priority, SourceCapabilities,
 #[test]
 fn test_memory_source_creation() {
     let source = MemorySource::new();
-    assert_eq!(source.id().as_str(), "memory");
+    assert_eq!(source.id().as_str(), "memory:default");
     assert_eq!(source.source_type(), SourceType::Memory);
     assert_eq!(source.priority(), Priority::MEMORY);
 }
@@ -11,7 +11,7 @@ fn test_memory_source_creation() {
 #[test]
 fn test_memory_source_default() {
     let source = MemorySource::default();
-    assert_eq!(source.id().as_str(), "memory");
+    assert_eq!(source.id().as_str(), "memory:default");
 }
 
 #[test]
@@ -169,7 +169,7 @@ fn test_memory_source_load() {
 
     let snapshot = source.load().unwrap();
     assert_eq!(snapshot.variables.len(), 3);
-    assert_eq!(snapshot.source_id.as_str(), "memory");
+    assert_eq!(snapshot.source_id.as_str(), "memory:default");
     assert!(snapshot.timestamp.elapsed().as_secs() < 1);
     assert!(snapshot.version.is_some());
 }
@@ -1,3 +1,4 @@
+mod abundantis_tests;
 mod config_tests;
 mod error_tests;
 mod integration_tests;
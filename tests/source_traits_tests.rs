@@ -82,13 +82,8 @@ fn test_parsed_variable_simple() {
 
 #[test]
 fn test_parsed_variable_with_description() {
-    let var = ParsedVariable {
-        key: "KEY".into(),
-        raw_value: "value".into(),
-        source: VariableSource::Memory,
-        description: Some("Test description".into()),
-        is_commented: false,
-    };
+    let var = ParsedVariable::simple("KEY", "value", VariableSource::Memory)
+        .with_description("Test description");
 
     assert_eq!(var.description.as_deref(), Some("Test description"));
 }
@@ -132,6 +127,7 @@ fn test_source_snapshot_creation() {
 
     let snapshot = SourceSnapshot {
         source_id: SourceId::new("test"),
+        source_type: SourceType::Memory,
         variables: vars.into(),
         timestamp: std::time::Instant::now(),
         version: Some(1),
@@ -153,6 +149,7 @@ fn test_source_snapshot_arc_conversion() {
 
     let snapshot = SourceSnapshot {
         source_id: SourceId::new("test"),
+        source_type: SourceType::Memory,
         variables: vars.into(),
         timestamp: std::time::Instant::now(),
         version: None,
@@ -252,6 +249,7 @@ fn test_parsed_variable_debug() {
 fn test_source_snapshot_display() {
     let snapshot = SourceSnapshot {
         source_id: SourceId::new("test-source"),
+        source_type: SourceType::Memory,
         variables: vec![].into(),
         timestamp: std::time::Instant::now(),
         version: Some(42),
@@ -285,6 +283,7 @@ fn test_source_id_with_special_chars() {
 fn test_source_timestamp_creation() {
     let snapshot = SourceSnapshot {
         source_id: SourceId::new("test"),
+        source_type: SourceType::Memory,
         variables: vec![].into(),
         timestamp: std::time::Instant::now(),
         version: None,
@@ -306,13 +305,7 @@ fn test_variable_source_equality() {
 
 #[test]
 fn test_parsed_variable_with_commented() {
-    let var = ParsedVariable {
-        key: "KEY".into(),
-        raw_value: "value".into(),
-        source: VariableSource::Memory,
-        description: None,
-        is_commented: true,
-    };
+    let var = ParsedVariable::simple("KEY", "value", VariableSource::Memory).with_commented(true);
 
     assert!(var.is_commented);
 }
@@ -321,6 +314,7 @@ fn test_parsed_variable_with_commented() {
 fn test_source_version_tracking() {
     let snapshot1 = SourceSnapshot {
         source_id: SourceId::new("test"),
+        source_type: SourceType::Memory,
         variables: vec![].into(),
         timestamp: std::time::Instant::now(),
         version: Some(1),
@@ -328,6 +322,7 @@ fn test_source_version_tracking() {
 
     let snapshot2 = SourceSnapshot {
         source_id: SourceId::new("test"),
+        source_type: SourceType::Memory,
         variables: vec![].into(),
         timestamp: std::time::Instant::now(),
         version: Some(2),
@@ -362,3 +357,50 @@ fn test_source_id_partial_eq() {
     assert!(id.as_str().starts_with("test-source"));
     assert!(id.as_str().ends_with("123"));
 }
+
+#[test]
+fn test_source_type_id_prefixes() {
+    assert_eq!(SourceType::File.id_prefix(), "file:");
+    assert_eq!(SourceType::Shell.id_prefix(), "shell:");
+    assert_eq!(SourceType::Memory.id_prefix(), "memory:");
+    assert_eq!(SourceType::Remote.id_prefix(), "remote:");
+
+    let id = SourceId::canonical(SourceType::Remote, "vault");
+    assert_eq!(id.as_str(), "remote:vault");
+    assert_eq!(id.source_type(), Some(SourceType::Remote));
+    assert_eq!(SourceId::new("custom").source_type(), None);
+}
+
+#[test]
+fn test_legacy_external_ids_read_as_remote() {
+    let remote = SourceId::canonical(SourceType::Remote, "doppler");
+    assert_eq!(SourceId::new("external:doppler"), remote);
+    assert_eq!(SourceId::from("external:doppler"), remote);
+    assert_eq!(SourceId::from(String::from("external:doppler")), remote);
+    assert_eq!(remote.source_type(), Some(SourceType::Remote));
+}
+
+#[test]
+fn test_builtin_source_ids_match_source_type() {
+    use abundantis::source::{BytesSource, EnvSource, FileSource, MemorySource, ShellSource};
+
+    let dir = tempfile::TempDir::new().unwrap();
+    let path = dir.path().join(".env");
+    std::fs::write(&path, "KEY=value\n").unwrap();
+
+    let sources: Vec<Box<dyn EnvSource>> = vec![
+        Box::new(FileSource::new(&path).unwrap()),
+        Box::new(ShellSource::new()),
+        Box::new(MemorySource::new()),
+        Box::new(BytesSource::new("stdin", b"KEY=value\n")),
+    ];
+
+    for source in &sources {
+        let id = source.id();
+        assert!(id.as_str().starts_with(source.source_type().id_prefix()));
+        assert_eq!(id.source_type(), Some(source.source_type()));
+
+        let snapshot = source.load().unwrap();
+        assert_eq!(snapshot.source_id.source_type(), Some(snapshot.source_type));
+    }
+}
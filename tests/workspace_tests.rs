@@ -1,4 +1,8 @@
-use abundantis::workspace::{PackageInfo, WorkspaceContext};
+use abundantis::config::{MonorepoProviderType, WorkspaceConfig};
+use abundantis::workspace::{
+    provider::{LernaProvider, NpmProvider, PnpmProvider},
+    MonorepoProvider, PackageInfo, ProviderRegistry, WorkspaceContext, WorkspaceManager,
+};
 use std::path::PathBuf;
 
 #[test]
@@ -402,3 +406,239 @@ fn test_package_info_empty_name() {
 
     assert_eq!(info.name.as_deref(), Some(""));
 }
+
+#[test]
+fn test_pnpm_workspace_globs() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("pnpm-workspace.yaml"),
+        "packages:\n  - 'apps/*'\n  - 'packages/**'\n",
+    )
+    .unwrap();
+
+    let globs = PnpmProvider::new().workspace_globs(dir.path());
+    assert_eq!(globs, vec!["apps/*", "packages/**"]);
+}
+
+fn npm_workspace(package_json: &str) -> tempfile::TempDir {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(dir.path().join("package.json"), package_json).unwrap();
+    for name in ["web", "admin"] {
+        let pkg_dir = dir.path().join("apps").join(name);
+        std::fs::create_dir_all(&pkg_dir).unwrap();
+        std::fs::write(
+            pkg_dir.join("package.json"),
+            format!(r#"{{"name":"@apps/{}"}}"#, name),
+        )
+        .unwrap();
+    }
+    dir
+}
+
+fn discovered_names(provider: &NpmProvider, root: &std::path::Path) -> Vec<String> {
+    let mut names: Vec<String> = provider
+        .discover_packages(root)
+        .unwrap()
+        .into_iter()
+        .filter_map(|p| p.name.map(|n| n.to_string()))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_npm_workspaces_object_form_matches_array_form() {
+    let provider = NpmProvider::new();
+    let array = npm_workspace(r#"{"workspaces":["apps/*"]}"#);
+    let object = npm_workspace(r#"{"workspaces":{"packages":["apps/*"],"nohoist":["**/react"]}}"#);
+
+    assert!(provider.detect(object.path()));
+    assert_eq!(
+        discovered_names(&provider, object.path()),
+        vec!["@apps/admin", "@apps/web"]
+    );
+    assert_eq!(
+        discovered_names(&provider, object.path()),
+        discovered_names(&provider, array.path())
+    );
+    assert_eq!(provider.workspace_globs(object.path()), vec!["apps/*"]);
+}
+
+#[test]
+fn test_lerna_falls_back_to_package_json_workspaces() {
+    let dir = npm_workspace(r#"{"workspaces":["apps/*"]}"#);
+    std::fs::write(
+        dir.path().join("lerna.json"),
+        r#"{"version":"independent"}"#,
+    )
+    .unwrap();
+
+    let provider = LernaProvider::new();
+    let mut names: Vec<String> = provider
+        .discover_packages(dir.path())
+        .unwrap()
+        .into_iter()
+        .filter_map(|p| p.name.map(|n| n.to_string()))
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["@apps/admin", "@apps/web"]);
+    assert_eq!(provider.workspace_globs(dir.path()), vec!["apps/*"]);
+
+    std::fs::write(
+        dir.path().join("lerna.json"),
+        r#"{"version":"independent","packages":["apps/web"]}"#,
+    )
+    .unwrap();
+    assert_eq!(provider.workspace_globs(dir.path()), vec!["apps/web"]);
+}
+
+#[test]
+fn test_all_env_files_cascades_root_files() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let files = [
+        ("pnpm-workspace.yaml", "packages:\n  - apps/*\n"),
+        ("package.json", r#"{"name":"root"}"#),
+        (".env", ""),
+        ("apps/web/package.json", r#"{"name":"web"}"#),
+        ("apps/web/.env", ""),
+        ("apps/web/.env.local", ""),
+        ("apps/api/package.json", r#"{"name":"api"}"#),
+    ];
+    for (path, content) in files {
+        let path = dir.path().join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    let config = WorkspaceConfig {
+        provider: Some(MonorepoProviderType::Pnpm),
+        cascading: true,
+        ..Default::default()
+    };
+    let root = dir.path().canonicalize().unwrap();
+    let manager = WorkspaceManager::with_root(root.clone(), &config).unwrap();
+
+    let env_files: Vec<(String, Vec<PathBuf>)> = manager
+        .all_env_files()
+        .into_iter()
+        .map(|(package, files)| (package.relative_path.to_string(), files))
+        .collect();
+    assert_eq!(
+        env_files,
+        vec![
+            ("apps/api".to_string(), vec![root.join(".env")]),
+            (
+                "apps/web".to_string(),
+                vec![
+                    root.join(".env"),
+                    root.join("apps/web/.env"),
+                    root.join("apps/web/.env.local"),
+                ]
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_turbo_declared_env_keys() {
+    let dir = tempfile::TempDir::new().unwrap();
+    std::fs::write(
+        dir.path().join("turbo.json"),
+        r#"{
+            "globalEnv": ["CI"],
+            "globalPassThroughEnv": ["AWS_PROFILE"],
+            "pipeline": {
+                "build": { "env": ["API_URL", "NEXT_PUBLIC_*", "!NEXT_PUBLIC_DEBUG"] },
+                "test": { "passThroughEnv": ["DATABASE_URL"] }
+            }
+        }"#,
+    )
+    .unwrap();
+
+    let config = WorkspaceConfig {
+        provider: Some(MonorepoProviderType::Turbo),
+        ..Default::default()
+    };
+    let manager = WorkspaceManager::with_root(dir.path().to_path_buf(), &config).unwrap();
+
+    let mut keys: Vec<_> = manager
+        .declared_env_keys()
+        .into_iter()
+        .map(|k| k.to_string())
+        .collect();
+    keys.sort();
+    assert_eq!(
+        keys,
+        vec![
+            "API_URL",
+            "AWS_PROFILE",
+            "CI",
+            "DATABASE_URL",
+            "NEXT_PUBLIC_*"
+        ]
+    );
+}
+
+#[test]
+fn test_detect_falls_back_to_lockfiles() {
+    for (lockfile, expected) in [
+        ("pnpm-lock.yaml", MonorepoProviderType::Pnpm),
+        ("yarn.lock", MonorepoProviderType::Yarn),
+    ] {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("package.json"), r#"{"name":"solo"}"#).unwrap();
+        std::fs::write(dir.path().join(lockfile), "").unwrap();
+
+        assert_eq!(ProviderRegistry::detect(dir.path()), Some(expected));
+
+        // The detected provider accepts the repository as a single package.
+        let config = WorkspaceConfig {
+            provider: Some(expected),
+            ..Default::default()
+        };
+        let manager = WorkspaceManager::with_root(dir.path().to_path_buf(), &config).unwrap();
+        let packages = manager.packages();
+        assert_eq!(packages.len(), 1, "{}", lockfile);
+        assert_eq!(packages[0].name.as_deref(), Some("solo"));
+        assert_eq!(packages[0].relative_path.as_str(), ".");
+    }
+
+    let empty = tempfile::TempDir::new().unwrap();
+    assert_eq!(ProviderRegistry::detect(empty.path()), None);
+}
+
+#[test]
+fn test_refresh_rediscovers_after_config_change() {
+    let dir = tempfile::TempDir::new().unwrap();
+    for package in ["apps/web", "libs/ui"] {
+        let package_dir = dir.path().join(package);
+        std::fs::create_dir_all(&package_dir).unwrap();
+        std::fs::write(
+            package_dir.join("package.json"),
+            format!(r#"{{"name":"{}"}}"#, package),
+        )
+        .unwrap();
+        std::fs::write(package_dir.join("index.ts"), "").unwrap();
+    }
+    let workspace_file = dir.path().join("pnpm-workspace.yaml");
+    std::fs::write(&workspace_file, "packages:\n  - 'apps/*'\n").unwrap();
+
+    let config = WorkspaceConfig {
+        provider: Some(MonorepoProviderType::Pnpm),
+        ..Default::default()
+    };
+    let manager = WorkspaceManager::with_root(dir.path().to_path_buf(), &config).unwrap();
+    let ui_file = dir.path().join("libs/ui/index.ts");
+    assert!(manager.context_for_file(&ui_file).is_none());
+    assert!(!manager.needs_rediscovery());
+
+    std::fs::write(&workspace_file, "packages:\n  - 'apps/*'\n  - 'libs/*'\n").unwrap();
+    assert!(manager.needs_rediscovery());
+    assert!(manager.context_for_file(&ui_file).is_none());
+
+    manager.refresh().unwrap();
+    let context = manager.context_for_file(&ui_file).unwrap();
+    assert_eq!(context.package_name.as_deref(), Some("libs/ui"));
+    assert_eq!(manager.packages().len(), 2);
+    assert!(!manager.needs_rediscovery());
+}